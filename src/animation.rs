@@ -1,5 +1,5 @@
 use bevy::prelude::*;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 
 #[derive(Clone)]
 pub struct Animation {
@@ -8,6 +8,13 @@ pub struct Animation {
     pub frame_duration: f32, // Duration per frame in seconds
 }
 
+impl Animation {
+    /// A state that plays this animation from its first frame.
+    pub fn start(&self) -> AnimationState {
+        AnimationState::new(&self.name, self.frame_duration)
+    }
+}
+
 #[derive(Resource)]
 pub struct AnimationLibrary {
     animations: HashMap<String, Animation>,
@@ -99,6 +106,20 @@ impl AnimationLibrary {
             frame_duration: 0.2,
         });
 
+        // Row 13: Paws up to the face, whiskers out
+        animations.insert("cute".to_string(), Animation {
+            name: "cute".to_string(),
+            frames: (156..158).collect(),
+            frame_duration: 0.3,
+        });
+
+        // Row 14: Tumbling, used while the cat is carried
+        animations.insert("dangle".to_string(), Animation {
+            name: "dangle".to_string(),
+            frames: (168..172).collect(),
+            frame_duration: 0.15,
+        });
+
         
         AnimationLibrary { animations }
     }
@@ -118,6 +139,7 @@ pub struct AnimationState {
     pub current_frame: usize,
     pub timer: Timer,
     pub next_animation: Option<(String, Timer)>, // Animation to play after timer expires
+    pub queued: VecDeque<(String, f32)>, // Animations to follow next_animation, with how long the one before lasts
 }
 
 impl Default for AnimationState {
@@ -127,6 +149,7 @@ impl Default for AnimationState {
             current_frame: 0,
             timer: Timer::from_seconds(0.5, TimerMode::Repeating),
             next_animation: None,
+            queued: VecDeque::new(),
         }
     }
 }
@@ -138,6 +161,7 @@ impl AnimationState {
             current_frame: 0,
             timer: Timer::from_seconds(frame_duration, TimerMode::Repeating),
             next_animation: None,
+            queued: VecDeque::new(),
         }
     }
     
@@ -145,6 +169,7 @@ impl AnimationState {
         self.current_animation = animation_name.to_string();
         self.current_frame = 0;
         self.next_animation = None;
+        self.queued.clear();
         
         // Set correct timer for the new animation
         if let Some(animation) = animation_library.get(animation_name) {
@@ -159,12 +184,24 @@ impl AnimationState {
             return_to.to_string(),
             Timer::from_seconds(duration, TimerMode::Once)
         ));
+        self.queued.clear();
         
         // Set correct timer for the new animation
         if let Some(animation) = animation_library.get(animation_name) {
             self.timer = Timer::from_seconds(animation.frame_duration, TimerMode::Repeating);
         }
     }
+
+    /// Plays each animation for its number of seconds in turn, then settles into `return_to`.
+    pub fn play_sequence(&mut self, steps: &[(String, f32)], return_to: &str, animation_library: &AnimationLibrary) {
+        let Some((first, first_duration)) = steps.first() else {
+            return;
+        };
+        // Each step hands over to the one after it, and the last to `return_to`
+        let next_name = |index: usize| steps.get(index).map_or(return_to, |(name, _)| name.as_str()).to_string();
+        self.play_animation_then_return(first, &next_name(1), *first_duration, animation_library);
+        self.queued = (1..steps.len()).map(|index| (next_name(index + 1), steps[index].1)).collect();
+    }
 }
 
 pub fn animate_sprite_system(
@@ -180,7 +217,10 @@ pub fn animate_sprite_system(
                 // Switch to next animation
                 state.current_animation = next_anim_name.clone();
                 state.current_frame = 0;
-                state.next_animation = None;
+                state.next_animation = state
+                    .queued
+                    .pop_front()
+                    .map(|(name, duration)| (name, Timer::from_seconds(duration, TimerMode::Once)));
                 
                 // Update timer for new animation
                 if let Some(animation) = animation_library.get(&state.current_animation) {
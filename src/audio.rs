@@ -0,0 +1,278 @@
+use bevy::asset::io::file::FileAssetReader;
+use bevy::audio::{PlaybackMode, Volume};
+use bevy::prelude::*;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+
+use crate::animation::AnimationState;
+use crate::background::ActiveBackground;
+use crate::daynight::{DayPhase, GameClock};
+use crate::game::AnimatedCat;
+use crate::rng::{GameRng, RngStream};
+
+const SOUNDS_FILE: &str = "audio/sounds.ron";
+const MUSIC_FADE_SECONDS: f32 = 2.0;
+
+/// Mixer channel a sound plays on.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AudioCategory {
+    #[default]
+    Sfx,
+    Voice,
+    Music,
+}
+
+/// Volume for each category, multiplied by the master volume.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct AudioVolumes {
+    pub master: f32,
+    pub sfx: f32,
+    pub voice: f32,
+    pub music: f32,
+}
+
+impl Default for AudioVolumes {
+    fn default() -> Self {
+        Self { master: 0.8, sfx: 1.0, voice: 1.0, music: 0.5 }
+    }
+}
+
+impl AudioVolumes {
+    pub fn effective(&self, category: AudioCategory) -> f32 {
+        let channel = match category {
+            AudioCategory::Sfx => self.sfx,
+            AudioCategory::Voice => self.voice,
+            AudioCategory::Music => self.music,
+        };
+        (self.master * channel).clamp(0.0, 1.0)
+    }
+}
+
+/// A named sound. One of `files` is picked at random each time, played at a
+/// random speed from `pitch` so repeats don't sound identical.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct SoundDef {
+    #[serde(default)]
+    pub category: AudioCategory,
+    pub files: Vec<String>,
+    #[serde(default = "full_volume")]
+    pub volume: f32,
+    #[serde(default = "no_pitch_change")]
+    pub pitch: (f32, f32),
+}
+
+fn full_volume() -> f32 {
+    1.0
+}
+
+fn no_pitch_change() -> (f32, f32) {
+    (1.0, 1.0)
+}
+
+/// Plays a sound when an animation reaches a frame.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct FrameSound {
+    pub animation: String,
+    pub frame: usize,
+    pub sound: String,
+}
+
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct MusicDef {
+    pub default: Option<String>,
+    pub night: Option<String>,
+}
+
+/// `assets/audio/sounds.ron`: every sound, and what triggers them.
+#[derive(Resource, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct SoundLibrary {
+    pub sounds: HashMap<String, SoundDef>,
+    /// Sound to play when an animation starts
+    pub animations: HashMap<String, String>,
+    pub frames: Vec<FrameSound>,
+    pub music: MusicDef,
+}
+
+impl SoundLibrary {
+    pub fn load() -> Self {
+        let path = FileAssetReader::get_base_path().join("assets").join(SOUNDS_FILE);
+        let parsed = fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|contents| ron::from_str(&contents).map_err(|err| err.to_string()));
+        parsed.unwrap_or_else(|err| {
+            println!("Could not load {}: {}", SOUNDS_FILE, err);
+            Self::default()
+        })
+    }
+
+    /// Picks the file and speed for one play of a sound.
+    pub fn pick_variation(&self, name: &str, rng: &mut impl Rng) -> Option<(&SoundDef, &str, f32)> {
+        let def = self.sounds.get(name)?;
+        let file = def.files.choose(rng)?;
+        let (low, high) = def.pitch;
+        let speed = if high > low { rng.gen_range(low..=high) } else { low };
+        Some((def, file, speed))
+    }
+
+    /// The music for a background and time of day.
+    pub fn music_for(&self, background_music: Option<&str>, phase: DayPhase) -> Option<String> {
+        let night = if phase == DayPhase::Night { self.music.night.as_deref() } else { None };
+        background_music.or(night).or(self.music.default.as_deref()).map(str::to_string)
+    }
+}
+
+/// Play a sound from the library by name.
+#[derive(Event)]
+pub struct PlaySound(pub String);
+
+/// A music track and its place in a crossfade.
+#[derive(Component)]
+pub struct MusicTrack {
+    pub path: String,
+    /// 0 = silent, 1 = full volume
+    fade: f32,
+    fading_out: bool,
+}
+
+pub struct GameAudioPlugin;
+
+impl Plugin for GameAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SoundLibrary::load())
+            .init_resource::<AudioVolumes>()
+            .add_event::<PlaySound>()
+            .add_systems(Update, (
+                animation_sounds_system,
+                play_sound_system,
+                select_music_system,
+                crossfade_music_system,
+            ).chain());
+    }
+}
+
+/// True when Bevy's audio plugin is running. Without it (headless runs and
+/// tests) every sound request is dropped.
+fn audio_available(sources: &Option<Res<Assets<AudioSource>>>) -> bool {
+    sources.is_some()
+}
+
+/// Turns animation starts and frames into sound requests.
+pub fn animation_sounds_system(
+    library: Res<SoundLibrary>,
+    cats: Query<&AnimationState, With<AnimatedCat>>,
+    mut sounds: EventWriter<PlaySound>,
+    mut last: Local<Option<(String, usize)>>,
+) {
+    let Ok(state) = cats.get_single() else {
+        return;
+    };
+    let current = (state.current_animation.clone(), state.current_frame);
+    if last.as_ref() == Some(&current) {
+        return;
+    }
+    let started = last.as_ref().is_none_or(|(animation, _)| *animation != current.0);
+    *last = Some(current);
+
+    if started && let Some(sound) = library.animations.get(&state.current_animation) {
+        sounds.send(PlaySound(sound.clone()));
+    }
+    for frame_sound in &library.frames {
+        if frame_sound.animation == state.current_animation && frame_sound.frame == state.current_frame {
+            sounds.send(PlaySound(frame_sound.sound.clone()));
+        }
+    }
+}
+
+pub fn play_sound_system(
+    mut commands: Commands,
+    mut requests: EventReader<PlaySound>,
+    asset_server: Res<AssetServer>,
+    library: Res<SoundLibrary>,
+    volumes: Res<AudioVolumes>,
+    sources: Option<Res<Assets<AudioSource>>>,
+    mut rng: ResMut<GameRng>,
+) {
+    if !audio_available(&sources) {
+        requests.clear();
+        return;
+    }
+    let rng = rng.stream(RngStream::Effects);
+    for PlaySound(name) in requests.read() {
+        let Some((def, file, speed)) = library.pick_variation(name, rng) else {
+            println!("Unknown sound '{}'", name);
+            continue;
+        };
+        commands.spawn(AudioBundle {
+            source: asset_server.load(file.to_string()),
+            settings: PlaybackSettings::DESPAWN
+                .with_volume(Volume::new(def.volume * volumes.effective(def.category)))
+                .with_speed(speed),
+        });
+    }
+}
+
+/// Starts a crossfade when the background or time of day calls for other music.
+pub fn select_music_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    library: Res<SoundLibrary>,
+    active: Res<ActiveBackground>,
+    clock: Res<GameClock>,
+    sources: Option<Res<Assets<AudioSource>>>,
+    mut tracks: Query<&mut MusicTrack>,
+) {
+    if !audio_available(&sources) {
+        return;
+    }
+    let background_music = active.0.as_ref().and_then(|background| background.music.as_deref());
+    let wanted = library.music_for(background_music, clock.phase());
+    let playing = tracks.iter().find(|track| !track.fading_out).map(|track| track.path.clone());
+    if wanted == playing {
+        return;
+    }
+
+    for mut track in &mut tracks {
+        track.fading_out = true;
+    }
+    if let Some(path) = wanted {
+        println!("Music: {}", path);
+        commands.spawn((
+            AudioBundle {
+                source: asset_server.load(path.clone()),
+                settings: PlaybackSettings {
+                    mode: PlaybackMode::Loop,
+                    volume: Volume::new(0.0),
+                    ..default()
+                },
+            },
+            MusicTrack { path, fade: 0.0, fading_out: false },
+        ));
+    }
+}
+
+/// Fades tracks in and out and keeps them at the music volume.
+pub fn crossfade_music_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    volumes: Res<AudioVolumes>,
+    mut tracks: Query<(Entity, &mut MusicTrack, Option<&AudioSink>)>,
+) {
+    let step = time.delta_seconds() / MUSIC_FADE_SECONDS;
+    for (entity, mut track, sink) in &mut tracks {
+        track.fade = if track.fading_out { track.fade - step } else { track.fade + step }.clamp(0.0, 1.0);
+        if track.fading_out && track.fade <= 0.0 {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        // The sink only appears once the track has loaded and started
+        if let Some(sink) = sink {
+            sink.set_volume(track.fade * volumes.effective(AudioCategory::Music));
+        }
+    }
+}
@@ -0,0 +1,589 @@
+use bevy::asset::io::file::FileAssetReader;
+use bevy::prelude::*;
+use rand::Rng;
+use rand::seq::SliceRandom;
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+use crate::controls::{Action, ActionState};
+use crate::daynight::{DayPhase, GameClock, SceneTint};
+use crate::game::{AnimatedCat, ClickCounter, Statistics};
+use crate::rotation::{
+    ActiveRotationPolicy, RotationContext, register_builtin_policies, update_rotation_policy_system,
+};
+use crate::loading::LoadingAssets;
+use crate::pickup::CatBody;
+use crate::profile::ProfileManager;
+use crate::rng::{GameRng, RngStream};
+use crate::state::AppState;
+use crate::theme::ActiveTheme;
+use crate::transition::ChangeBackground;
+
+// Layer images are 576x324, so keep their 16:9 shape at the old 800px height
+pub const LAYER_SIZE: Vec2 = Vec2::new(1422.0, 800.0);
+// Nearest layer scrolls at this fraction of the anchor's movement
+const MAX_PARALLAX: f32 = 0.5;
+
+#[derive(Component)]
+pub struct Background;
+
+/// One depth slice of a layered background. The layer is drawn three times
+/// side by side so it can scroll in either direction without showing an edge.
+#[derive(Component)]
+pub struct BackgroundLayer {
+    pub depth: f32,
+    pub parallax: f32,
+}
+
+const BACKGROUNDS_DIR: &str = "backgrounds";
+pub const BACKGROUND_Z: f32 = -1.0;
+const METADATA_FILE: &str = "background.ron";
+
+/// When a background joins the rotation.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+pub enum UnlockRequirement {
+    #[default]
+    Always,
+    TotalClicks(u64),
+    BackgroundChanges(u32),
+}
+
+impl UnlockRequirement {
+    pub fn is_met(&self, stats: &Statistics) -> bool {
+        match self {
+            UnlockRequirement::Always => true,
+            UnlockRequirement::TotalClicks(clicks) => stats.total_clicks >= *clicks,
+            UnlockRequirement::BackgroundChanges(changes) => stats.background_changes >= *changes,
+        }
+    }
+}
+
+/// Optional `background.ron` inside a background folder. Every field can be
+/// left out, in which case it is worked out from the folder contents.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct BackgroundMetadata {
+    /// Name shown to the player
+    pub name: String,
+    /// Layer file names from back to front
+    pub layers: Vec<String>,
+    /// Parallax factor for each layer, in the same order as `layers`
+    pub parallax: Vec<f32>,
+    /// Vertical position of the cat on this background (0 = screen centre)
+    pub floor_height: f32,
+    /// RGB multiplier applied to every layer
+    pub tint: [f32; 3],
+    pub unlock: UnlockRequirement,
+    /// Only show this background at night, in place of the daytime ones
+    pub night: bool,
+    /// Music to play while this background is shown, instead of the default
+    pub music: Option<String>,
+}
+
+impl Default for BackgroundMetadata {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            layers: Vec::new(),
+            parallax: Vec::new(),
+            floor_height: 0.0,
+            tint: [1.0, 1.0, 1.0],
+            unlock: UnlockRequirement::Always,
+            night: false,
+            music: None,
+        }
+    }
+}
+
+/// Sprite drawing one tile of a layer, pointing back at its background root.
+#[derive(Component)]
+pub struct BackgroundTile {
+    pub root: Entity,
+    pub tint: Color,
+}
+
+/// Opacity of a whole background, applied to all of its tiles.
+#[derive(Component)]
+pub struct BackgroundOpacity(pub f32);
+
+/// Hands out every index once in random order before any repeats, and never
+/// gives the same index twice in a row across refills.
+#[derive(Default)]
+pub struct ShuffleBag {
+    remaining: Vec<usize>,
+    last: Option<usize>,
+}
+
+impl ShuffleBag {
+    pub fn next(&mut self, candidates: &[usize], rng: &mut impl Rng) -> Option<usize> {
+        // Drop anything that stopped being a candidate since the bag was filled
+        self.remaining.retain(|index| candidates.contains(index));
+        if self.remaining.is_empty() {
+            self.remaining = candidates.to_vec();
+            self.remaining.shuffle(rng);
+            // The next pick comes off the end, so keep the previous pick away from it
+            if self.remaining.len() > 1 && self.remaining.last() == self.last.as_ref() {
+                let end = self.remaining.len() - 1;
+                self.remaining.swap(0, end);
+            }
+        }
+        let pick = self.remaining.pop();
+        if pick.is_some() {
+            self.last = pick;
+        }
+        pick
+    }
+}
+
+/// A background folder and the layer images found inside it, back to front.
+#[derive(Clone, Debug)]
+pub struct BackgroundSet {
+    pub folder: String,
+    pub name: String,
+    pub layers: Vec<String>,
+    pub parallax: Vec<f32>,
+    pub floor_height: f32,
+    pub tint: Color,
+    pub unlock: UnlockRequirement,
+    pub night: bool,
+    pub music: Option<String>,
+}
+
+impl BackgroundSet {
+    /// Builds a background from an asset folder, reading its `background.ron`
+    /// if there is one. Without a layer list the numbered images (`1.png`,
+    /// `2.png`, ...) are used, and folders without those fall back to their
+    /// flattened image.
+    pub fn discover(folder: &str) -> Result<Self, String> {
+        let dir = FileAssetReader::get_base_path().join("assets").join(folder);
+        let metadata: BackgroundMetadata = match fs::read_to_string(dir.join(METADATA_FILE)) {
+            Ok(contents) => ron::from_str(&contents)
+                .map_err(|err| format!("{}/{}: {}", folder, METADATA_FILE, err))?,
+            Err(_) => BackgroundMetadata::default(),
+        };
+
+        let files = if metadata.layers.is_empty() {
+            find_layer_files(&dir)
+        } else {
+            metadata.layers.clone()
+        };
+        if files.is_empty() {
+            return Err(format!("{} has no images", folder));
+        }
+
+        let parallax = if metadata.parallax.is_empty() {
+            default_parallax(files.len())
+        } else if metadata.parallax.len() == files.len() {
+            metadata.parallax
+        } else {
+            return Err(format!(
+                "{} lists {} parallax factors for {} layers",
+                folder,
+                metadata.parallax.len(),
+                files.len()
+            ));
+        };
+
+        let folder_name = folder.rsplit('/').next().unwrap_or(folder);
+        let [r, g, b] = metadata.tint;
+        Ok(Self {
+            folder: folder.to_string(),
+            name: if metadata.name.is_empty() { display_name(folder_name) } else { metadata.name },
+            layers: files.into_iter().map(|file| format!("{}/{}", folder, file)).collect(),
+            parallax,
+            floor_height: metadata.floor_height,
+            tint: Color::srgb(r, g, b),
+            unlock: metadata.unlock,
+            night: metadata.night,
+            music: metadata.music,
+        })
+    }
+}
+
+fn find_layer_files(dir: &Path) -> Vec<String> {
+    let mut numbered = Vec::new();
+    let mut flattened = Vec::new();
+
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let Ok(file_name) = entry.file_name().into_string() else {
+                continue;
+            };
+            let Some(stem) = file_name.strip_suffix(".png") else {
+                continue;
+            };
+            match stem.parse::<u32>() {
+                Ok(index) => numbered.push((index, file_name)),
+                Err(_) => flattened.push(file_name),
+            }
+        }
+    }
+
+    numbered.sort();
+    flattened.sort();
+    if numbered.is_empty() {
+        flattened.into_iter().take(1).collect()
+    } else {
+        numbered.into_iter().map(|(_, file_name)| file_name).collect()
+    }
+}
+
+/// Parallax factor for each layer: the back layer stays put and the
+/// front layer moves at `MAX_PARALLAX`.
+fn default_parallax(count: usize) -> Vec<f32> {
+    (0..count)
+        .map(|i| if count > 1 { MAX_PARALLAX * i as f32 / (count - 1) as f32 } else { 0.0 })
+        .collect()
+}
+
+/// Turns folder names like `summer5` or `summer 2` into `Summer 5` / `Summer 2`.
+pub fn display_name(folder_name: &str) -> String {
+    let mut name = String::new();
+    let mut previous: Option<char> = None;
+    for c in folder_name.chars() {
+        let c = if c == '_' || c == '-' { ' ' } else { c };
+        match previous {
+            None => name.extend(c.to_uppercase()),
+            Some(prev) if prev.is_alphabetic() && c.is_ascii_digit() => {
+                name.push(' ');
+                name.push(c);
+            }
+            Some(' ') => name.extend(c.to_uppercase()),
+            Some(_) => name.push(c),
+        }
+        previous = Some(c);
+    }
+    name
+}
+
+#[derive(Resource)]
+pub struct BackgroundConfig {
+    backgrounds: Vec<BackgroundSet>,
+    shuffle: ShuffleBag,
+}
+
+impl Default for BackgroundConfig {
+    fn default() -> Self {
+        Self::discover(BACKGROUNDS_DIR)
+    }
+}
+
+impl BackgroundConfig {
+    /// Scans every sub folder of `assets/<dir>` for backgrounds. Folders that
+    /// can't be used are reported and skipped.
+    pub fn discover(dir: &str) -> Self {
+        let root = FileAssetReader::get_base_path().join("assets").join(dir);
+        let mut folders: Vec<String> = fs::read_dir(&root)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.path().is_dir())
+                    .filter_map(|entry| entry.file_name().into_string().ok())
+                    .collect()
+            })
+            .unwrap_or_else(|err| {
+                println!("Could not read {}: {}", root.display(), err);
+                Vec::new()
+            });
+        folders.sort();
+
+        let backgrounds = folders
+            .iter()
+            .filter_map(|folder| match BackgroundSet::discover(&format!("{}/{}", dir, folder)) {
+                Ok(set) => Some(set),
+                Err(err) => {
+                    println!("Skipping background: {}", err);
+                    None
+                }
+            })
+            .collect();
+        Self { backgrounds, shuffle: ShuffleBag::default() }
+    }
+
+    pub fn backgrounds(&self) -> &[BackgroundSet] {
+        &self.backgrounds
+    }
+
+    /// Picks the next background from the ones the player has unlocked. Every
+    /// unlocked background is shown once before any of them repeat. At night
+    /// the night variants are used if there are any.
+    pub fn next_background(&mut self, stats: &Statistics, phase: DayPhase, rng: &mut impl Rng) -> Option<&BackgroundSet> {
+        let unlocked: Vec<usize> = (0..self.backgrounds.len())
+            .filter(|&index| self.backgrounds[index].unlock.is_met(stats))
+            .collect();
+        let wants_night = phase == DayPhase::Night;
+        let mut pool: Vec<usize> = unlocked
+            .iter()
+            .copied()
+            .filter(|&index| self.backgrounds[index].night == wants_night)
+            .collect();
+        if pool.is_empty() {
+            pool = unlocked;
+        }
+        let index = self.shuffle.next(&pool, rng)?;
+        Some(&self.backgrounds[index])
+    }
+}
+
+/// The background currently on screen.
+#[derive(Resource, Default)]
+pub struct ActiveBackground(pub Option<BackgroundSet>);
+
+impl ActiveBackground {
+    /// Height the cat stands at, in world units. The floor moves with the
+    /// background when it is scaled up.
+    pub fn floor_y(&self, scale: &BackgroundScale) -> f32 {
+        self.0.as_ref().map_or(0.0, |background| background.floor_height * scale.0)
+    }
+}
+
+/// Scale applied to whole backgrounds so they cover the window.
+#[derive(Resource)]
+pub struct BackgroundScale(pub f32);
+
+impl Default for BackgroundScale {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+/// What the parallax layers scroll against.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ParallaxAnchor {
+    Camera,
+    #[default]
+    Cat,
+}
+
+pub struct BackgroundPlugin;
+
+impl Plugin for BackgroundPlugin {
+    fn build(&self, app: &mut App) {
+        register_builtin_policies(app);
+        // The theme plugin may already have picked the backgrounds
+        app.init_resource::<BackgroundConfig>()
+            .insert_resource(ActiveBackground::default())
+            .insert_resource(ParallaxAnchor::default())
+            .init_resource::<ParallaxScroll>()
+            .init_resource::<BackgroundScale>()
+            .init_resource::<ActiveRotationPolicy>()
+            .add_systems(Startup, setup_background)
+            .add_systems(Update, (
+                (update_rotation_policy_system, rotate_background_system).chain().run_if(in_state(AppState::Playing)),
+                parallax_system,
+                apply_background_scale_system,
+                apply_floor_height_system,
+                apply_background_color_system,
+            ));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn setup_background(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut background_config: ResMut<BackgroundConfig>,
+    mut active: ResMut<ActiveBackground>,
+    mut loading: ResMut<LoadingAssets>,
+    stats: Res<Statistics>,
+    clock: Res<GameClock>,
+    mut rng: ResMut<GameRng>,
+) {
+    let root = commands
+        .spawn((
+            SpatialBundle::from_transform(Transform::from_xyz(0.0, 0.0, BACKGROUND_Z)), // Behind everything
+            Background,
+            BackgroundOpacity(1.0),
+        ))
+        .id();
+
+    println!("Found {} backgrounds", background_config.backgrounds().len());
+
+    // Spawn random background
+    let Some(background) = background_config.next_background(&stats, clock.phase(), rng.stream(RngStream::Backgrounds)).cloned() else {
+        println!("No backgrounds found in assets/{}", BACKGROUNDS_DIR);
+        return;
+    };
+    println!("Loading background: {} ({})", background.name, background.folder);
+    // The loading screen waits for the first background
+    let handles = spawn_background_layers(&mut commands, root, &background, &asset_server);
+    for (path, handle) in background.layers.iter().zip(handles) {
+        loading.track(path, handle);
+    }
+    active.0 = Some(background);
+}
+
+/// Spawns the layers of `background` under `root` and returns the image
+/// handles so callers can wait for them to load.
+pub fn spawn_background_layers(
+    commands: &mut Commands,
+    root: Entity,
+    background: &BackgroundSet,
+    asset_server: &AssetServer,
+) -> Vec<Handle<Image>> {
+    let mut handles = Vec::new();
+    commands.entity(root).with_children(|parent| {
+        for (index, (path, parallax)) in background.layers.iter().zip(&background.parallax).enumerate() {
+            let texture: Handle<Image> = asset_server.load(path.clone());
+            handles.push(texture.clone());
+            // Small z steps keep every layer behind the cat
+            let depth = index as f32 * 0.01;
+
+            parent
+                .spawn((
+                    SpatialBundle::from_transform(Transform::from_xyz(0.0, 0.0, depth)),
+                    BackgroundLayer { depth, parallax: *parallax },
+                ))
+                .with_children(|layer| {
+                    for tile in -1..=1 {
+                        layer.spawn((
+                            SpriteBundle {
+                                texture: texture.clone(),
+                                transform: Transform::from_xyz(tile as f32 * LAYER_SIZE.x, 0.0, 0.0),
+                                sprite: Sprite {
+                                    color: background.tint,
+                                    custom_size: Some(LAYER_SIZE),
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                            BackgroundTile { root, tint: background.tint },
+                        ));
+                    }
+                });
+        }
+    });
+    handles
+}
+
+/// Colours every tile from its background's tint, the theme, the time of day and the
+/// opacity of the background it belongs to.
+pub fn apply_background_color_system(
+    scene_tint: Res<SceneTint>,
+    theme: Res<ActiveTheme>,
+    roots: Query<&BackgroundOpacity>,
+    mut tiles: Query<(&mut Sprite, &BackgroundTile)>,
+) {
+    let (scene, theme) = (scene_tint.0.to_srgba(), theme.0.tint.to_srgba());
+    for (mut sprite, tile) in &mut tiles {
+        let opacity = roots.get(tile.root).map(|opacity| opacity.0).unwrap_or(1.0);
+        let own = tile.tint.to_srgba();
+        sprite.color = Color::srgba(
+            own.red * scene.red * theme.red,
+            own.green * scene.green * theme.green,
+            own.blue * scene.blue * theme.blue,
+            opacity,
+        );
+    }
+}
+
+/// Horizontal position of a layer for the given camera and anchor positions,
+/// wrapped so one of its three tiles always covers the camera.
+pub fn parallax_offset(camera_x: f32, anchor_x: f32, parallax: f32, width: f32) -> f32 {
+    let origin = camera_x - anchor_x * parallax;
+    camera_x + (origin - camera_x + width / 2.0).rem_euclid(width) - width / 2.0
+}
+
+/// Distance the scene has scrolled past the anchor, for modes where the
+/// world moves and the cat stays put.
+#[derive(Resource, Default)]
+pub struct ParallaxScroll(pub f32);
+
+pub fn parallax_system(
+    anchor: Res<ParallaxAnchor>,
+    scroll: Res<ParallaxScroll>,
+    scale: Res<BackgroundScale>,
+    cameras: Query<&GlobalTransform, With<Camera>>,
+    cats: Query<&GlobalTransform, With<AnimatedCat>>,
+    mut layers: Query<(&mut Transform, &BackgroundLayer)>,
+) {
+    let Ok(camera) = cameras.get_single() else {
+        return;
+    };
+    let camera_x = camera.translation().x;
+    let anchor_x = match *anchor {
+        ParallaxAnchor::Camera => camera_x,
+        ParallaxAnchor::Cat => cats.get_single().map(|cat| cat.translation().x).unwrap_or(0.0),
+    } + scroll.0;
+
+    // Layers sit under the scaled background root, so the offset is worked out
+    // in world units and then scaled back into the root's space
+    let scale = scale.0;
+    for (mut transform, layer) in &mut layers {
+        transform.translation.x = parallax_offset(camera_x, anchor_x, layer.parallax, LAYER_SIZE.x * scale) / scale;
+        transform.translation.z = layer.depth;
+    }
+}
+
+/// Grows every background, including one that is transitioning in, to the
+/// current cover scale.
+pub fn apply_background_scale_system(
+    scale: Res<BackgroundScale>,
+    mut roots: Query<&mut Transform, With<BackgroundOpacity>>,
+) {
+    for mut transform in &mut roots {
+        if transform.scale.x != scale.0 {
+            transform.scale = Vec3::new(scale.0, scale.0, 1.0);
+        }
+    }
+}
+
+/// Stands the cat on the floor of the current background, unless it is up
+/// in the air.
+pub fn apply_floor_height_system(
+    active: Res<ActiveBackground>,
+    scale: Res<BackgroundScale>,
+    mut cats: Query<(&mut Transform, Option<&CatBody>), With<AnimatedCat>>,
+    added: Query<(), Added<AnimatedCat>>,
+) {
+    if !active.is_changed() && !scale.is_changed() && added.is_empty() {
+        return;
+    }
+    if active.0.is_none() {
+        return;
+    }
+    for (mut transform, body) in &mut cats {
+        if body.is_none_or(CatBody::is_grounded) {
+            transform.translation.y = active.floor_y(&scale);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn rotate_background_system(
+    time: Res<Time>,
+    actions: Res<ActionState>,
+    counter: Res<ClickCounter>,
+    mut policy: ResMut<ActiveRotationPolicy>,
+    mut background_config: ResMut<BackgroundConfig>,
+    mut stats: ResMut<Statistics>,
+    mut change_events: EventWriter<ChangeBackground>,
+    clock: Res<GameClock>,
+    manager: Res<ProfileManager>,
+    mut rng: ResMut<GameRng>,
+    mut previous_clicks: Local<Option<(String, u32)>>,
+) {
+    // The first frame after loading a profile isn't a change in clicks
+    let profile = manager.active.clone().unwrap_or_default();
+    let previous = match &*previous_clicks {
+        Some((name, clicks)) if *name == profile => *clicks,
+        _ => counter.0,
+    };
+    let context = RotationContext {
+        previous_clicks: previous,
+        clicks: counter.0,
+        delta_seconds: time.delta_seconds(),
+        local_hour: clock.hours as u32,
+    };
+    *previous_clicks = Some((profile, counter.0));
+
+    // N always skips to the next background, whatever the policy
+    let rotate = policy.0.should_rotate(&context) | actions.just_pressed(Action::NextBackground);
+    let next = rotate.then(|| background_config.next_background(&stats, clock.phase(), rng.stream(RngStream::Backgrounds)));
+    if let Some(Some(new_background)) = next {
+        println!("Changing background to: {} ({})", new_background.name, new_background.folder);
+        change_events.send(ChangeBackground(new_background.clone()));
+        stats.background_changes += 1;
+    }
+}
@@ -0,0 +1,369 @@
+use bevy::input::InputSystem;
+use bevy::input::gamepad::{GamepadButton, GamepadButtonType, Gamepads};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+use crate::settings::UserConfig;
+use crate::state::AppState;
+
+/// Something the player can do. Game systems ask the `ActionState` about
+/// actions rather than looking at keys and buttons themselves.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Action {
+    /// Clicking, stroking and dragging: the cat, toys, food and event props
+    Pet,
+    /// Picking the cat up and carrying it
+    Carry,
+    Pause,
+    /// Shows or hides the food tray
+    OpenShop,
+    NextBackground,
+    LaserChase,
+    Runner,
+    Rhythm,
+    /// Jumping in the runner and hitting notes in the rhythm game
+    Jump,
+    ToggleDebug,
+    DebugParallax,
+    DebugRotation,
+    DebugClock,
+    DebugTheme,
+    DebugScaling,
+    /// Debug shortcut for the test animation on digit `n`
+    PlayAnimation(u8),
+}
+
+/// When an action can fire. Bindings only conflict if their actions can
+/// fire at the same time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ActionContext {
+    /// Whenever the game is running
+    Always,
+    /// With the cat, outside the mini-games
+    Cat,
+    Minigame,
+}
+
+impl Action {
+    pub const ALL: [Action; 25] = [
+        Action::Pet,
+        Action::Carry,
+        Action::Pause,
+        Action::OpenShop,
+        Action::NextBackground,
+        Action::LaserChase,
+        Action::Runner,
+        Action::Rhythm,
+        Action::Jump,
+        Action::ToggleDebug,
+        Action::DebugParallax,
+        Action::DebugRotation,
+        Action::DebugClock,
+        Action::DebugTheme,
+        Action::DebugScaling,
+        Action::PlayAnimation(1),
+        Action::PlayAnimation(2),
+        Action::PlayAnimation(3),
+        Action::PlayAnimation(4),
+        Action::PlayAnimation(5),
+        Action::PlayAnimation(6),
+        Action::PlayAnimation(7),
+        Action::PlayAnimation(8),
+        Action::PlayAnimation(9),
+        Action::PlayAnimation(0),
+    ];
+
+    /// English name for the controls menu. Test animations also show their digit.
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::Pet => "Pet",
+            Action::Carry => "Pick up",
+            Action::Pause => "Pause",
+            Action::OpenShop => "Food tray",
+            Action::NextBackground => "Next background",
+            Action::LaserChase => "Laser chase",
+            Action::Runner => "Runner",
+            Action::Rhythm => "Rhythm",
+            Action::Jump => "Jump",
+            Action::ToggleDebug => "Debug mode",
+            Action::DebugParallax => "Parallax anchor",
+            Action::DebugRotation => "Rotation policy",
+            Action::DebugClock => "Clock mode",
+            Action::DebugTheme => "Theme",
+            Action::DebugScaling => "Scaling mode",
+            Action::PlayAnimation(_) => "Animation",
+        }
+    }
+
+    pub fn context(self) -> ActionContext {
+        match self {
+            Action::Pet
+            | Action::Carry
+            | Action::OpenShop
+            | Action::LaserChase
+            | Action::Runner
+            | Action::Rhythm => ActionContext::Cat,
+            Action::Jump => ActionContext::Minigame,
+            _ => ActionContext::Always,
+        }
+    }
+
+    fn overlaps(self, other: Action) -> bool {
+        let (a, b) = (self.context(), other.context());
+        a == b || a == ActionContext::Always || b == ActionContext::Always
+    }
+
+    fn default_bindings(self) -> Vec<Binding> {
+        use Binding::{Gamepad, Key, Mouse};
+        match self {
+            Action::Pet => vec![Mouse(MouseButton::Left)],
+            Action::Carry => vec![Mouse(MouseButton::Right)],
+            Action::Pause => vec![Key(KeyCode::Escape), Gamepad(GamepadButtonType::Start)],
+            Action::OpenShop => vec![Key(KeyCode::KeyI), Gamepad(GamepadButtonType::North)],
+            Action::NextBackground => vec![Key(KeyCode::KeyN), Gamepad(GamepadButtonType::RightTrigger)],
+            Action::LaserChase => vec![Key(KeyCode::KeyL), Gamepad(GamepadButtonType::West)],
+            Action::Runner => vec![Key(KeyCode::KeyE), Gamepad(GamepadButtonType::East)],
+            Action::Rhythm => vec![Key(KeyCode::KeyB), Gamepad(GamepadButtonType::LeftTrigger)],
+            Action::Jump => vec![
+                Key(KeyCode::Space),
+                Key(KeyCode::ArrowUp),
+                Key(KeyCode::KeyF),
+                Key(KeyCode::KeyJ),
+                Mouse(MouseButton::Left),
+                Gamepad(GamepadButtonType::South),
+            ],
+            Action::ToggleDebug => vec![Key(KeyCode::KeyD), Gamepad(GamepadButtonType::Select)],
+            Action::DebugParallax => vec![Key(KeyCode::KeyP)],
+            Action::DebugRotation => vec![Key(KeyCode::KeyR)],
+            Action::DebugClock => vec![Key(KeyCode::KeyT)],
+            Action::DebugTheme => vec![Key(KeyCode::KeyY)],
+            Action::DebugScaling => vec![Key(KeyCode::KeyS)],
+            Action::PlayAnimation(digit) => DIGITS.get(digit as usize).map(|key| vec![Key(*key)]).unwrap_or_default(),
+        }
+    }
+}
+
+const DIGITS: [KeyCode; 10] = [
+    KeyCode::Digit0,
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
+
+/// A key, mouse button or gamepad button. Gamepad buttons work on any
+/// connected pad.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Gamepad(GamepadButtonType),
+}
+
+impl Binding {
+    pub fn label(self) -> String {
+        match self {
+            Binding::Key(key) => {
+                let name = format!("{:?}", key);
+                match name.strip_prefix("Key").or_else(|| name.strip_prefix("Digit")) {
+                    Some(short) => short.to_string(),
+                    None => name,
+                }
+            }
+            Binding::Mouse(MouseButton::Left) => "Left mouse".to_string(),
+            Binding::Mouse(MouseButton::Right) => "Right mouse".to_string(),
+            Binding::Mouse(MouseButton::Middle) => "Middle mouse".to_string(),
+            Binding::Mouse(button) => format!("Mouse {:?}", button),
+            Binding::Gamepad(button) => format!("Pad {:?}", button),
+        }
+    }
+
+    fn is_gamepad(self) -> bool {
+        matches!(self, Binding::Gamepad(_))
+    }
+}
+
+/// Which bindings trigger each action, saved in the player's config.
+/// Actions missing from the file keep their default bindings, and an empty
+/// list leaves an action unbound.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(transparent)]
+pub struct InputMap(BTreeMap<Action, Vec<Binding>>);
+
+impl Default for InputMap {
+    fn default() -> Self {
+        Self(Action::ALL.iter().map(|action| (*action, action.default_bindings())).collect())
+    }
+}
+
+impl InputMap {
+    pub fn bindings(&self, action: Action) -> Vec<Binding> {
+        self.0.get(&action).cloned().unwrap_or_else(|| action.default_bindings())
+    }
+
+    /// Another action that `binding` would clash with if given to `action`.
+    pub fn conflict(&self, action: Action, binding: Binding) -> Option<Action> {
+        Action::ALL
+            .into_iter()
+            .find(|other| *other != action && action.overlaps(*other) && self.bindings(*other).contains(&binding))
+    }
+
+    /// Binds `action` to `binding`, in place of its other bindings on the same
+    /// kind of device: keyboard and mouse, or gamepad. Refuses, naming the
+    /// other action, if the binding is already in use.
+    pub fn rebind(&mut self, action: Action, binding: Binding) -> Result<(), Action> {
+        if let Some(other) = self.conflict(action, binding) {
+            return Err(other);
+        }
+        let mut bindings = self.bindings(action);
+        bindings.retain(|existing| existing.is_gamepad() != binding.is_gamepad());
+        // Keyboard and mouse first, then the gamepad
+        if binding.is_gamepad() { bindings.push(binding) } else { bindings.insert(0, binding) }
+        self.0.insert(action, bindings);
+        Ok(())
+    }
+
+    /// The bindings of `action` as shown in menus, such as `D / Pad Select`.
+    pub fn describe(&self, action: Action) -> String {
+        let labels: Vec<String> = self.bindings(action).into_iter().map(Binding::label).collect();
+        if labels.is_empty() { "-".to_string() } else { labels.join(" / ") }
+    }
+}
+
+/// Which actions are held this frame, and which started or stopped.
+#[derive(Resource, Default)]
+pub struct ActionState {
+    pressed: HashSet<Action>,
+    just_pressed: HashSet<Action>,
+    just_released: HashSet<Action>,
+}
+
+impl ActionState {
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.just_pressed.contains(&action)
+    }
+
+    pub fn just_released(&self, action: Action) -> bool {
+        self.just_released.contains(&action)
+    }
+
+    /// Moves on a frame, given what is held now and what was pressed this
+    /// frame. A press has to come from a binding's own press, so a key still
+    /// held after rebinding doesn't count as pressed again.
+    pub fn update(&mut self, pressed: HashSet<Action>, just_pressed: HashSet<Action>) {
+        self.just_released = self.pressed.difference(&pressed).copied().collect();
+        self.just_pressed = just_pressed;
+        self.pressed = pressed;
+    }
+}
+
+/// The action waiting in the controls menu for its new binding, and why the
+/// last attempt was refused.
+#[derive(Resource, Default)]
+pub struct Rebinding {
+    pub action: Option<Action>,
+    pub conflict: Option<(Binding, Action)>,
+}
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ActionState>()
+            .init_resource::<Rebinding>()
+            .add_systems(PreUpdate, update_actions_system.after(InputSystem))
+            .add_systems(OnExit(AppState::Settings), cancel_rebinding);
+    }
+}
+
+/// Reads the keyboard, mouse and gamepads into actions. Nothing fires while
+/// the controls menu is waiting for a new binding.
+pub fn update_actions_system(
+    config: Res<UserConfig>,
+    rebinding: Res<Rebinding>,
+    keys: Option<Res<ButtonInput<KeyCode>>>,
+    mouse: Option<Res<ButtonInput<MouseButton>>>,
+    pads: Option<Res<ButtonInput<GamepadButton>>>,
+    gamepads: Option<Res<Gamepads>>,
+    mut actions: ResMut<ActionState>,
+) {
+    if rebinding.action.is_some() {
+        actions.update(HashSet::new(), HashSet::new());
+        return;
+    }
+    // Whether a binding is held, or was pressed this frame
+    let check = |binding: Binding, just: bool| match binding {
+        Binding::Key(key) => keys.as_ref().is_some_and(|keys| if just { keys.just_pressed(key) } else { keys.pressed(key) }),
+        Binding::Mouse(button) => {
+            mouse.as_ref().is_some_and(|mouse| if just { mouse.just_pressed(button) } else { mouse.pressed(button) })
+        }
+        Binding::Gamepad(button) => match (&pads, &gamepads) {
+            (Some(pads), Some(gamepads)) => gamepads.iter().any(|gamepad| {
+                let button = GamepadButton::new(gamepad, button);
+                if just { pads.just_pressed(button) } else { pads.pressed(button) }
+            }),
+            _ => false,
+        },
+    };
+    let find = |just: bool| -> HashSet<Action> {
+        Action::ALL
+            .into_iter()
+            .filter(|action| config.controls.bindings(*action).into_iter().any(|binding| check(binding, just)))
+            .collect()
+    };
+    actions.update(find(false), find(true));
+}
+
+/// Binds the first key or button pressed to the action being rebound. `Esc`
+/// cancels. Runs before the settings buttons, so the click that starts a
+/// rebind isn't taken as the new binding.
+pub fn capture_binding_system(
+    mut rebinding: ResMut<Rebinding>,
+    mut config: ResMut<UserConfig>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    pads: Option<Res<ButtonInput<GamepadButton>>>,
+) {
+    let Some(action) = rebinding.action else {
+        return;
+    };
+    if keys.just_pressed(KeyCode::Escape) {
+        rebinding.action = None;
+        return;
+    }
+    let pressed = keys
+        .get_just_pressed()
+        .next()
+        .map(|key| Binding::Key(*key))
+        .or_else(|| mouse.get_just_pressed().next().map(|button| Binding::Mouse(*button)))
+        .or_else(|| pads.and_then(|pads| pads.get_just_pressed().next().map(|button| Binding::Gamepad(button.button_type))));
+    let Some(binding) = pressed else {
+        return;
+    };
+
+    rebinding.action = None;
+    match config.controls.rebind(action, binding) {
+        Ok(()) => {
+            println!("Bound {:?} to {}", action, binding.label());
+            rebinding.conflict = None;
+        }
+        Err(other) => {
+            println!("{} is already used by {:?}", binding.label(), other);
+            rebinding.conflict = Some((binding, other));
+        }
+    }
+}
+
+fn cancel_rebinding(mut rebinding: ResMut<Rebinding>) {
+    *rebinding = Rebinding::default();
+}
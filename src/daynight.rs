@@ -0,0 +1,199 @@
+use bevy::prelude::*;
+use chrono::Timelike;
+use serde::Deserialize;
+
+use crate::animation::{AnimationLibrary, AnimationState};
+use crate::game::{AnimatedCat, CounterText, RestingAnimation};
+use crate::theme::ActiveTheme;
+
+// One in-game day every two minutes in accelerated mode
+const ACCELERATED_HOURS_PER_SECOND: f32 = 24.0 / 120.0;
+
+// Scene tint through the day as (hour, rgb), interpolated between entries
+const DAYLIGHT_KEYS: [(f32, [f32; 3]); 8] = [
+    (0.0, [0.35, 0.4, 0.65]),
+    (5.0, [0.35, 0.4, 0.65]),
+    (7.0, [1.0, 0.85, 0.75]),
+    (9.0, [1.0, 1.0, 1.0]),
+    (17.0, [1.0, 1.0, 1.0]),
+    (19.0, [1.0, 0.7, 0.55]),
+    (21.0, [0.35, 0.4, 0.65]),
+    (24.0, [0.35, 0.4, 0.65]),
+];
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DayPhase {
+    Morning,
+    #[default]
+    Day,
+    Dusk,
+    Night,
+}
+
+impl DayPhase {
+    pub fn from_hour(hour: f32) -> Self {
+        match hour.rem_euclid(24.0) {
+            h if (5.0..9.0).contains(&h) => DayPhase::Morning,
+            h if (9.0..17.0).contains(&h) => DayPhase::Day,
+            h if (17.0..21.0).contains(&h) => DayPhase::Dusk,
+            _ => DayPhase::Night,
+        }
+    }
+
+    /// Whether the scene is dark enough to need light text.
+    pub fn is_dark(self) -> bool {
+        matches!(self, DayPhase::Dusk | DayPhase::Night)
+    }
+}
+
+/// Colour multiplier for the scene at a time of day.
+pub fn daylight_tint(hour: f32) -> Color {
+    let hour = hour.rem_euclid(24.0);
+    for pair in DAYLIGHT_KEYS.windows(2) {
+        let ((start, from), (end, to)) = (pair[0], pair[1]);
+        if hour >= start && hour <= end {
+            let t = (hour - start) / (end - start);
+            return Color::srgb(
+                from[0] + (to[0] - from[0]) * t,
+                from[1] + (to[1] - from[1]) * t,
+                from[2] + (to[2] - from[2]) * t,
+            );
+        }
+    }
+    Color::WHITE
+}
+
+/// Where the time of day comes from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ClockMode {
+    /// Follow the local system clock
+    System,
+    /// Run a whole day in a couple of minutes, for testing
+    Accelerated,
+    /// Stay at a fixed hour
+    Fixed(f32),
+}
+
+/// The time of day the scene is showing, in hours from midnight.
+#[derive(Resource)]
+pub struct GameClock {
+    pub mode: ClockMode,
+    pub hours: f32,
+}
+
+impl Default for GameClock {
+    fn default() -> Self {
+        Self::new(ClockMode::System)
+    }
+}
+
+impl GameClock {
+    pub fn new(mode: ClockMode) -> Self {
+        let mut clock = Self { mode, hours: 0.0 };
+        clock.advance(0.0);
+        clock
+    }
+
+    pub fn advance(&mut self, delta_seconds: f32) {
+        self.hours = match self.mode {
+            ClockMode::System => {
+                let now = chrono::Local::now();
+                now.hour() as f32 + now.minute() as f32 / 60.0 + now.second() as f32 / 3600.0
+            }
+            ClockMode::Accelerated => (self.hours + delta_seconds * ACCELERATED_HOURS_PER_SECOND).rem_euclid(24.0),
+            ClockMode::Fixed(hours) => hours.rem_euclid(24.0),
+        };
+    }
+
+    pub fn phase(&self) -> DayPhase {
+        DayPhase::from_hour(self.hours)
+    }
+
+    /// The next mode for the debug key: system, accelerated, fixed night, fixed noon.
+    pub fn next_mode(&self) -> ClockMode {
+        match self.mode {
+            ClockMode::System => ClockMode::Accelerated,
+            ClockMode::Accelerated => ClockMode::Fixed(23.0),
+            ClockMode::Fixed(hours) if DayPhase::from_hour(hours) == DayPhase::Night => ClockMode::Fixed(12.0),
+            ClockMode::Fixed(_) => ClockMode::System,
+        }
+    }
+
+    /// Parses `HH:MM` (or just `HH`) for the `--time` override.
+    pub fn parse_time(text: &str) -> Option<f32> {
+        let (hours, minutes) = text.split_once(':').unwrap_or((text, "0"));
+        let hours: u32 = hours.parse().ok()?;
+        let minutes: u32 = minutes.parse().ok()?;
+        (hours < 24 && minutes < 60).then(|| hours as f32 + minutes as f32 / 60.0)
+    }
+}
+
+/// Colour multiplier applied on top of each background's own tint.
+#[derive(Resource)]
+pub struct SceneTint(pub Color);
+
+impl Default for SceneTint {
+    fn default() -> Self {
+        Self(Color::WHITE)
+    }
+}
+
+pub struct DayNightPlugin;
+
+impl Plugin for DayNightPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameClock>()
+            .init_resource::<SceneTint>()
+            .add_systems(Update, (
+                advance_clock_system,
+                apply_day_phase_system,
+            ).chain());
+    }
+}
+
+pub fn advance_clock_system(time: Res<Time>, mut clock: ResMut<GameClock>, mut tint: ResMut<SceneTint>) {
+    clock.advance(time.delta_seconds());
+    tint.0 = daylight_tint(clock.hours);
+}
+
+/// Recolours the UI and swaps the cat's resting animation when the phase or
+/// theme changes.
+#[allow(clippy::too_many_arguments)]
+pub fn apply_day_phase_system(
+    clock: Res<GameClock>,
+    theme: Res<ActiveTheme>,
+    animation_library: Res<AnimationLibrary>,
+    mut resting: ResMut<RestingAnimation>,
+    mut texts: Query<&mut Text, With<CounterText>>,
+    added_text: Query<(), Added<CounterText>>,
+    mut cats: Query<&mut AnimationState, With<AnimatedCat>>,
+    mut current_phase: Local<Option<DayPhase>>,
+) {
+    let phase = clock.phase();
+    let phase_changed = *current_phase != Some(phase);
+    // The counter is respawned each time a profile is loaded
+    if !phase_changed && !theme.is_changed() && added_text.is_empty() {
+        return;
+    }
+    if phase_changed {
+        println!("Time of day: {:?} ({:.1}h)", phase, clock.hours);
+        *current_phase = Some(phase);
+    }
+
+    for mut text in &mut texts {
+        for section in &mut text.sections {
+            section.style.color = theme.0.text_color(phase.is_dark());
+        }
+    }
+
+    // The cat dozes off at night
+    let rest = if phase == DayPhase::Night { "sleep" } else { "idle" };
+    if resting.0 != rest {
+        let previous = std::mem::replace(&mut resting.0, rest.to_string());
+        for mut state in &mut cats {
+            if state.current_animation == previous && state.next_animation.is_none() {
+                state.play_animation(rest, &animation_library);
+            }
+        }
+    }
+}
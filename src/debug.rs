@@ -1,20 +1,38 @@
 use bevy::prelude::*;
 use crate::animation::{AnimationLibrary, AnimationState};
+use crate::background::ParallaxAnchor;
+use crate::controls::{Action, ActionState, InputMap};
+use crate::daynight::GameClock;
+use crate::game::AnimatedCat;
+use crate::loading::GameTextures;
+use crate::profile::ProfileSettings;
+use crate::rng::GameRng;
+use crate::settings::UserConfig;
+use crate::theme::ThemeLibrary;
+use crate::state::AppState;
 
-#[derive(Resource)]
+#[derive(Resource, Default)]
 pub struct DebugMode {
     pub enabled: bool,
 }
 
-impl Default for DebugMode {
-    fn default() -> Self {
-        Self { enabled: false }
-    }
-}
-
 #[derive(Component)]
 pub struct DebugOverlay;
 
+/// Animations the number keys play in debug mode, by digit.
+const TEST_ANIMATIONS: [(u8, &str); 10] = [
+    (1, "idle"),
+    (2, "walk"),
+    (3, "pancake"),
+    (4, "sleep"),
+    (5, "play"),
+    (6, "run"),
+    (7, "jump"),
+    (8, "box_play"),
+    (9, "dance"),
+    (0, "damage"),
+];
+
 pub struct DebugPlugin;
 
 impl Plugin for DebugPlugin {
@@ -24,19 +42,36 @@ impl Plugin for DebugPlugin {
                 toggle_debug_system,
                 update_debug_overlay_system,
                 update_debug_text_system,
-            ).chain());
+            ).chain().run_if(in_state(AppState::Playing)));
     }
 }
 
+#[allow(clippy::too_many_arguments, clippy::collapsible_if)]
 pub fn toggle_debug_system(
-    keyboard: Res<ButtonInput<KeyCode>>,
+    actions: Res<ActionState>,
     animation_library: Res<AnimationLibrary>,
     mut debug_mode: ResMut<DebugMode>,
+    mut parallax_anchor: ResMut<ParallaxAnchor>,
+    mut settings: ResMut<ProfileSettings>,
+    mut clock: ResMut<GameClock>,
+    themes: Res<ThemeLibrary>,
+    mut config: ResMut<UserConfig>,
     mut commands: Commands,
     debug_overlays: Query<Entity, With<DebugOverlay>>,
-    mut cat_query: Query<&mut AnimationState>,
+    mut cat_query: Query<&mut AnimationState, With<AnimatedCat>>,
 ) {
-    if keyboard.just_pressed(KeyCode::KeyD) {
+    // With debug keys turned off in the settings, leave debug mode and ignore them
+    if !config.debug_keys {
+        if debug_mode.enabled {
+            debug_mode.enabled = false;
+            for entity in debug_overlays.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+        return;
+    }
+
+    if actions.just_pressed(Action::ToggleDebug) {
         debug_mode.enabled = !debug_mode.enabled;
         println!("Debug mode: {}", debug_mode.enabled);
         
@@ -48,24 +83,43 @@ pub fn toggle_debug_system(
         }
     }
     
+    // Switch what the background layers scroll against
+    if debug_mode.enabled && actions.just_pressed(Action::DebugParallax) {
+        *parallax_anchor = match *parallax_anchor {
+            ParallaxAnchor::Cat => ParallaxAnchor::Camera,
+            ParallaxAnchor::Camera => ParallaxAnchor::Cat,
+        };
+        println!("Parallax anchor: {:?}", *parallax_anchor);
+    }
+
+    // Cycle through the built-in background rotation policies
+    if debug_mode.enabled && actions.just_pressed(Action::DebugRotation) {
+        settings.background_rotation = settings.background_rotation.next_builtin();
+    }
+
+    // Cycle the clock between real time, a fast day and fixed night/noon
+    if debug_mode.enabled && actions.just_pressed(Action::DebugClock) {
+        clock.mode = clock.next_mode();
+        println!("Clock mode: {:?}", clock.mode);
+    }
+
+    // Cycle between following the calendar and each theme pack
+    if debug_mode.enabled && actions.just_pressed(Action::DebugTheme) {
+        settings.theme = themes.next_choice(&settings.theme);
+        println!("Theme: {:?}", settings.theme);
+    }
+
+    // Cycle how the scene is scaled to the window
+    if debug_mode.enabled && actions.just_pressed(Action::DebugScaling) {
+        config.scaling = config.scaling.next();
+        println!("Scaling mode: {:?}", config.scaling);
+    }
+
     // Animation testing shortcuts (only in debug mode)
     if debug_mode.enabled {
         if let Ok(mut state) = cat_query.get_single_mut() {
-            let animations = [
-                (KeyCode::Digit1, "idle"),
-                (KeyCode::Digit2, "walk"),
-                (KeyCode::Digit3, "pancake"),
-                (KeyCode::Digit4, "sleep"),
-                (KeyCode::Digit5, "play"),
-                (KeyCode::Digit6, "run"),
-                (KeyCode::Digit7, "jump"),
-                (KeyCode::Digit8, "box_play"),
-                (KeyCode::Digit9, "dance"),
-                (KeyCode::Digit0, "damage"),
-            ];
-            
-            for (key, anim_name) in animations {
-                if keyboard.just_pressed(key) {
+            for (digit, anim_name) in TEST_ANIMATIONS {
+                if actions.just_pressed(Action::PlayAnimation(digit)) {
                     println!("Playing {} animation", anim_name);
                     state.play_animation(anim_name, &animation_library);
                     break;
@@ -75,12 +129,13 @@ pub fn toggle_debug_system(
     }
 }
 
+#[allow(clippy::collapsible_if)]
 pub fn update_debug_overlay_system(
     debug_mode: Res<DebugMode>,
     mut commands: Commands,
-    cat_query: Query<(&TextureAtlas, &AnimationState, &Handle<Image>)>,
+    cat_query: Query<(&TextureAtlas, &AnimationState, &Handle<Image>), With<AnimatedCat>>,
     existing_overlays: Query<Entity, With<DebugOverlay>>,
-    asset_server: Res<AssetServer>,
+    textures: Res<GameTextures>,
 ) {
     if !debug_mode.enabled {
         return;
@@ -118,7 +173,7 @@ pub fn update_debug_overlay_system(
             .insert(DebugOverlay);
         
         // Show the entire sprite sheet with grid overlay
-        let texture = asset_server.load("cat_black/cat_spritesheet.png");
+        let texture = textures.cat.clone();
         commands.spawn((
             SpriteBundle {
                 texture,
@@ -192,8 +247,11 @@ pub fn update_debug_overlay_system(
 pub fn update_debug_text_system(
     debug_mode: Res<DebugMode>,
     animation_library: Res<AnimationLibrary>,
+    clock: Res<GameClock>,
+    rng: Res<GameRng>,
+    config: Res<UserConfig>,
     mut text_query: Query<&mut Text, With<DebugOverlay>>,
-    cat_query: Query<(&TextureAtlas, &AnimationState)>,
+    cat_query: Query<(&TextureAtlas, &AnimationState), With<AnimatedCat>>,
 ) {
     if !debug_mode.enabled {
         return;
@@ -214,22 +272,52 @@ pub fn update_debug_text_system(
         
         for mut text in text_query.iter_mut() {
             text.sections[0].value = format!(
-                "Debug Mode (Press D to toggle)\n\
+                "Debug Mode (Press {} to toggle)\n\
                 Current Animation: {}\n\
                 Frame Index: {}\n\
                 Frame: {}\n\
                 {}\n\
+                Animations Loaded: {}\n\
+                Time: {:02}:{:02} {:?} ({:?})\n\
+                Seed: {}\n\
                 \n\
                 Animation Shortcuts:\n\
-                1: Idle  2: Walk  3: Sleep  4: Groom\n\
-                5: Play  6: Jump  7: Cute   8: BoxPlay\n\
+                {}\n\
                 \n\
-                Click on cat for cute animation",
+                Tap cat with {} for cute animation",
+                config.controls.describe(Action::ToggleDebug),
                 state.current_animation,
                 atlas.index,
                 frame_info,
-                next_anim_info
+                next_anim_info,
+                animation_library.get_animation_names().len(),
+                clock.hours as u32,
+                (clock.hours.fract() * 60.0) as u32,
+                clock.phase(),
+                clock.mode,
+                rng.seed(),
+                debug_shortcuts(&config.controls),
+                config.controls.describe(Action::Pet)
             );
         }
     }
 }
+
+/// The debug shortcuts with whatever they are bound to.
+fn debug_shortcuts(controls: &InputMap) -> String {
+    let animations: Vec<String> = TEST_ANIMATIONS
+        .iter()
+        .map(|(digit, name)| format!("{}: {}", controls.describe(Action::PlayAnimation(*digit)), name))
+        .collect();
+    let mut lines: Vec<String> = animations.chunks(5).map(|row| row.join("  ")).collect();
+    for (action, description) in [
+        (Action::DebugParallax, "Toggle parallax anchor"),
+        (Action::DebugRotation, "Next background rotation policy"),
+        (Action::DebugClock, "Next clock mode"),
+        (Action::DebugTheme, "Next theme"),
+        (Action::DebugScaling, "Next scaling mode"),
+    ] {
+        lines.push(format!("{}: {}", controls.describe(action), description));
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,442 @@
+use bevy::asset::io::file::FileAssetReader;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::animation::{AnimationLibrary, AnimationState};
+use crate::audio::PlaySound;
+use crate::controls::{Action, ActionState};
+use crate::game::{AnimatedCat, ClickCounter, RestingAnimation, cat_contains, cursor_world_position};
+use crate::particles::ParticleBurst;
+use crate::settings::UserConfig;
+use crate::state::{AppState, InGame, Minigame};
+
+const FOODS_FILE: &str = "food/foods.ron";
+// Needs run out after about 15 and 20 minutes of play
+const FULLNESS_DECAY_PER_SECOND: f32 = 1.0 / 900.0;
+const HAPPINESS_DECAY_PER_SECOND: f32 = 1.0 / 1200.0;
+const TRAY_ICON_SIZE: f32 = 40.0;
+const DRAGGED_FOOD_SIZE: f32 = 48.0;
+// In front of the cat and its accessory
+const DRAGGED_FOOD_Z: f32 = 20.0;
+
+/// One food from `assets/food/foods.ron`.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct FoodDef {
+    pub name: String,
+    /// Image path relative to `assets/`
+    pub image: String,
+    /// Cost in clicks
+    pub price: u32,
+    /// How much of each need it restores, out of 1
+    pub fullness: f32,
+    pub happiness: f32,
+    /// Animations the cat plays after eating it, with how long each lasts
+    #[serde(default)]
+    pub reaction: Vec<(String, f32)>,
+}
+
+/// Every food that can be bought, by id.
+#[derive(Resource, Default)]
+pub struct FoodLibrary {
+    foods: Vec<(String, FoodDef)>,
+}
+
+impl FoodLibrary {
+    /// Fails if a reaction names an animation the cat doesn't have.
+    pub fn new(foods: BTreeMap<String, FoodDef>, animations: &AnimationLibrary) -> Result<Self, String> {
+        for (id, food) in &foods {
+            if let Some((clip, _)) = food.reaction.iter().find(|(clip, _)| animations.get(clip).is_none()) {
+                return Err(format!("{} reacts with unknown animation '{}'", id, clip));
+            }
+        }
+        Ok(Self { foods: foods.into_iter().collect() })
+    }
+
+    pub fn load() -> Self {
+        let path = FileAssetReader::get_base_path().join("assets").join(FOODS_FILE);
+        let parsed = fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|contents| ron::from_str(&contents).map_err(|err| err.to_string()))
+            .and_then(|foods| Self::new(foods, &AnimationLibrary::new()));
+        match parsed {
+            Ok(library) => library,
+            Err(err) => {
+                println!("Could not load {}: {}", FOODS_FILE, err);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn get(&self, id: &str) -> Option<&FoodDef> {
+        self.foods.iter().find(|(food, _)| food == id).map(|(_, def)| def)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &FoodDef)> {
+        self.foods.iter().map(|(id, def)| (id.as_str(), def))
+    }
+}
+
+/// Food the active profile owns, by id. Saved with the profile.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Inventory(pub BTreeMap<String, u32>);
+
+impl Inventory {
+    pub fn count(&self, id: &str) -> u32 {
+        self.0.get(id).copied().unwrap_or(0)
+    }
+
+    pub fn add(&mut self, id: &str, amount: u32) {
+        *self.0.entry(id.to_string()).or_default() += amount;
+    }
+
+    /// Uses up one of a food, returning false if there was none left.
+    pub fn take(&mut self, id: &str) -> bool {
+        match self.0.get_mut(id) {
+            Some(count) if *count > 0 => {
+                *count -= 1;
+                if *count == 0 {
+                    self.0.remove(id);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Spends clicks on one of a food, returning false if they were short.
+    pub fn buy(&mut self, id: &str, food: &FoodDef, counter: &mut ClickCounter) -> bool {
+        if counter.0 < food.price {
+            return false;
+        }
+        counter.0 -= food.price;
+        self.add(id, 1);
+        true
+    }
+}
+
+/// How fed and how happy the cat is, each from 0 to 1. Both slowly run down
+/// while playing and are topped up by food.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(default)]
+pub struct CatNeeds {
+    pub fullness: f32,
+    pub happiness: f32,
+}
+
+impl Default for CatNeeds {
+    fn default() -> Self {
+        Self { fullness: 0.8, happiness: 0.8 }
+    }
+}
+
+impl CatNeeds {
+    pub fn decay(&mut self, seconds: f32) {
+        self.fullness = (self.fullness - seconds * FULLNESS_DECAY_PER_SECOND).max(0.0);
+        self.happiness = (self.happiness - seconds * HAPPINESS_DECAY_PER_SECOND).max(0.0);
+    }
+
+    pub fn feed(&mut self, food: &FoodDef) {
+        self.fullness = (self.fullness + food.fullness).min(1.0);
+        self.happiness = (self.happiness + food.happiness).min(1.0);
+    }
+}
+
+#[derive(Component)]
+pub struct FoodTray;
+
+/// Pressing it picks up one of the food to drag onto the cat.
+#[derive(Component)]
+pub struct FoodSlot(pub String);
+
+#[derive(Component)]
+pub struct BuyFoodButton(pub String);
+
+#[derive(Component)]
+pub struct FoodCountText(pub String);
+
+#[derive(Component)]
+pub struct NeedsText;
+
+/// Whether the player has the food tray open. It starts open.
+#[derive(Resource)]
+pub struct FoodTrayOpen(pub bool);
+
+impl Default for FoodTrayOpen {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+/// The food following the cursor while it is being dragged.
+#[derive(Component)]
+pub struct DraggedFood(pub String);
+
+pub struct FeedingPlugin;
+
+impl Plugin for FeedingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(FoodLibrary::load())
+            .init_resource::<Inventory>()
+            .init_resource::<CatNeeds>()
+            .init_resource::<FoodTrayOpen>()
+            .add_systems(OnEnter(InGame), spawn_food_tray)
+            .add_systems(OnExit(Minigame::None), drop_dragged_food)
+            .add_systems(OnEnter(AppState::Paused), drop_dragged_food)
+            .add_systems(Update, (
+                toggle_food_tray_system,
+                buy_food_system,
+                start_food_drag_system,
+                drag_food_system,
+                decay_needs_system,
+            ).chain().run_if(in_state(AppState::Playing).and_then(in_state(Minigame::None))))
+            .add_systems(Update, (
+                update_food_tray_system,
+                food_tray_visibility_system,
+            ).run_if(in_state(InGame)));
+    }
+}
+
+/// The tray in the bottom left corner, with a row per food.
+fn spawn_food_tray(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    foods: Res<FoodLibrary>,
+    config: Res<UserConfig>,
+) {
+    let text_style = |size: f32| TextStyle {
+        font_size: size,
+        color: Color::WHITE,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(12.0),
+                    bottom: Val::Px(12.0),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(6.0),
+                    padding: UiRect::all(Val::Px(8.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+                ..default()
+            },
+            FoodTray,
+            StateScoped(InGame),
+        ))
+        .with_children(|tray| {
+            tray.spawn((TextBundle::from_section("", text_style(16.0)), NeedsText));
+            for (id, food) in foods.iter() {
+                tray.spawn(NodeBundle {
+                    style: Style {
+                        align_items: AlignItems::Center,
+                        column_gap: Val::Px(8.0),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|row| {
+                    row.spawn((
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(TRAY_ICON_SIZE),
+                                height: Val::Px(TRAY_ICON_SIZE),
+                                ..default()
+                            },
+                            image: UiImage::new(asset_server.load(&food.image)),
+                            ..default()
+                        },
+                        FoodSlot(id.to_string()),
+                    ));
+                    row.spawn((TextBundle::from_section("", text_style(18.0)), FoodCountText(id.to_string())));
+                    row.spawn((
+                        ButtonBundle {
+                            style: Style {
+                                padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+                                ..default()
+                            },
+                            background_color: BackgroundColor(Color::srgb(0.25, 0.25, 0.25)),
+                            ..default()
+                        },
+                        BuyFoodButton(id.to_string()),
+                    ))
+                    .with_children(|button| {
+                        button.spawn(TextBundle::from_section(
+                            format!("{} ({})", config.language.translate("Buy"), food.price),
+                            text_style(16.0),
+                        ));
+                    });
+                });
+            }
+        });
+}
+
+pub fn buy_food_system(
+    buttons: Query<(&Interaction, &BuyFoodButton), Changed<Interaction>>,
+    foods: Res<FoodLibrary>,
+    mut inventory: ResMut<Inventory>,
+    mut counter: ResMut<ClickCounter>,
+    mut sounds: EventWriter<PlaySound>,
+) {
+    for (interaction, button) in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        sounds.send(PlaySound("ui_click".to_string()));
+        if let Some(food) = foods.get(&button.0)
+            && inventory.buy(&button.0, food, &mut counter)
+        {
+            println!("Bought {} for {} clicks", food.name, food.price);
+        }
+    }
+}
+
+/// Picks up a food from the tray when its icon is pressed and one is left.
+pub fn start_food_drag_system(
+    mut commands: Commands,
+    slots: Query<(&Interaction, &FoodSlot), Changed<Interaction>>,
+    dragged: Query<(), With<DraggedFood>>,
+    asset_server: Res<AssetServer>,
+    foods: Res<FoodLibrary>,
+    inventory: Res<Inventory>,
+) {
+    for (interaction, slot) in &slots {
+        if *interaction != Interaction::Pressed || !dragged.is_empty() || inventory.count(&slot.0) == 0 {
+            continue;
+        }
+        let Some(food) = foods.get(&slot.0) else {
+            continue;
+        };
+        commands.spawn((
+            SpriteBundle {
+                texture: asset_server.load(&food.image),
+                sprite: Sprite {
+                    custom_size: Some(Vec2::splat(DRAGGED_FOOD_SIZE)),
+                    ..default()
+                },
+                // Placed under the cursor by the drag system
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            DraggedFood(slot.0.clone()),
+            StateScoped(InGame),
+        ));
+    }
+}
+
+/// Moves the dragged food with the cursor and feeds it to the cat if it is
+/// let go over the cat. Dropped anywhere else it goes back to the tray.
+#[allow(clippy::too_many_arguments)]
+pub fn drag_food_system(
+    mut commands: Commands,
+    actions: Res<ActionState>,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut dragged: Query<(Entity, &DraggedFood, &mut Transform, &mut Visibility)>,
+    cats: Query<(&GlobalTransform, &Sprite), With<AnimatedCat>>,
+    mut animations: Query<&mut AnimationState, With<AnimatedCat>>,
+    foods: Res<FoodLibrary>,
+    animation_library: Res<AnimationLibrary>,
+    resting: Res<RestingAnimation>,
+    mut inventory: ResMut<Inventory>,
+    mut needs: ResMut<CatNeeds>,
+    mut bursts: EventWriter<ParticleBurst>,
+) {
+    let Ok((entity, food_id, mut transform, mut visibility)) = dragged.get_single_mut() else {
+        return;
+    };
+    let (camera, camera_transform) = cameras.single();
+    let world_pos = cursor_world_position(windows.single(), camera, camera_transform);
+    if let Some(world_pos) = world_pos {
+        transform.translation = world_pos.extend(DRAGGED_FOOD_Z);
+        *visibility = Visibility::Visible;
+    }
+
+    if !actions.just_released(Action::Pet) {
+        return;
+    }
+    commands.entity(entity).despawn_recursive();
+
+    let on_cat = world_pos.is_some_and(|world_pos| {
+        cats.get_single().is_ok_and(|(cat_transform, sprite)| cat_contains(cat_transform, sprite, world_pos))
+    });
+    if !on_cat {
+        return;
+    }
+    let Some(food) = foods.get(&food_id.0) else {
+        return;
+    };
+    if !inventory.take(&food_id.0) {
+        return;
+    }
+    needs.feed(food);
+    if let Ok(mut state) = animations.get_single_mut() {
+        state.play_sequence(&food.reaction, &resting.0, &animation_library);
+    }
+    bursts.send(ParticleBurst { effect: "hearts".to_string(), position: transform.translation.with_z(0.0) });
+    println!("Fed the cat {}", food.name);
+}
+
+/// Lets go of anything being dragged when a mini-game starts or the game is
+/// paused, since the drag system cannot see the button being let go then.
+fn drop_dragged_food(mut commands: Commands, dragged: Query<Entity, With<DraggedFood>>) {
+    for entity in &dragged {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub fn decay_needs_system(time: Res<Time>, mut needs: ResMut<CatNeeds>) {
+    needs.decay(time.delta_seconds());
+}
+
+pub fn update_food_tray_system(
+    inventory: Res<Inventory>,
+    needs: Res<CatNeeds>,
+    config: Res<UserConfig>,
+    added: Query<(), Added<FoodTray>>,
+    mut counts: Query<(&mut Text, &FoodCountText), Without<NeedsText>>,
+    mut needs_text: Query<&mut Text, With<NeedsText>>,
+) {
+    if !(inventory.is_changed() || needs.is_changed() || config.is_changed() || !added.is_empty()) {
+        return;
+    }
+    for (mut text, count) in &mut counts {
+        text.sections[0].value = format!("x{}", inventory.count(&count.0));
+    }
+    for mut text in &mut needs_text {
+        text.sections[0].value = format!(
+            "{} {:.0}%  {} {:.0}%",
+            config.language.translate("Food"),
+            needs.fullness * 100.0,
+            config.language.translate("Mood"),
+            needs.happiness * 100.0,
+        );
+    }
+}
+
+/// The food tray action, `I` by default, opens and closes the tray.
+pub fn toggle_food_tray_system(actions: Res<ActionState>, mut open: ResMut<FoodTrayOpen>) {
+    if actions.just_pressed(Action::OpenShop) {
+        open.0 = !open.0;
+    }
+}
+
+/// The tray can be closed, and makes way for mini-games.
+pub fn food_tray_visibility_system(
+    minigame: Res<State<Minigame>>,
+    open: Res<FoodTrayOpen>,
+    mut trays: Query<&mut Visibility, With<FoodTray>>,
+) {
+    let shown = open.0 && *minigame.get() == Minigame::None;
+    let wanted = if shown { Visibility::Inherited } else { Visibility::Hidden };
+    for mut visibility in &mut trays {
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use bevy::prelude::*;
+
+use crate::game::ClicksEarned;
+use crate::settings::UserConfig;
+
+// Texts kept around for reuse; when all are showing the oldest is recycled
+pub const POOL_SIZE: usize = 24;
+const LIFETIME_SECONDS: f32 = 0.9;
+const RISE_SPEED: f32 = 70.0;
+// In front of the cat and particles, behind the debug overlay
+const FLOATING_TEXT_Z: f32 = 8.0;
+
+/// A "+N" label rising from a click. Inactive ones are hidden and wait in the pool.
+#[derive(Component, Default)]
+pub struct FloatingText {
+    age: f32,
+    active: bool,
+}
+
+/// Hidden floating texts ready to be shown again.
+#[derive(Resource, Default)]
+pub struct FloatingTextPool {
+    free: Vec<Entity>,
+}
+
+pub struct FloatingTextPlugin;
+
+impl Plugin for FloatingTextPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FloatingTextPool>()
+            .add_systems(Startup, fill_floating_text_pool)
+            .add_systems(Update, (show_floating_text_system, animate_floating_text_system).chain());
+    }
+}
+
+fn floating_text_bundle() -> (Text2dBundle, FloatingText) {
+    (
+        Text2dBundle {
+            text: Text::from_section(
+                "",
+                TextStyle {
+                    font_size: 32.0,
+                    color: Color::srgb(1.0, 0.85, 0.2),
+                    ..default()
+                },
+            ),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        FloatingText::default(),
+    )
+}
+
+pub fn fill_floating_text_pool(mut commands: Commands, mut pool: ResMut<FloatingTextPool>) {
+    for _ in 0..POOL_SIZE {
+        pool.free.push(commands.spawn(floating_text_bundle()).id());
+    }
+}
+
+/// Shows a "+N" at each click, taking a text from the pool. When the pool is
+/// empty the oldest text on screen is reused, so fast clicking never spawns
+/// more than the pool holds.
+pub fn show_floating_text_system(
+    mut events: EventReader<ClicksEarned>,
+    mut pool: ResMut<FloatingTextPool>,
+    mut texts: Query<(Entity, &mut FloatingText, &mut Text, &mut Transform, &mut Visibility)>,
+) {
+    for event in events.read() {
+        let entity = match pool.free.pop() {
+            Some(entity) => entity,
+            None => match texts
+                .iter()
+                .filter(|(_, text, ..)| text.active)
+                .max_by(|(_, a, ..), (_, b, ..)| a.age.total_cmp(&b.age))
+            {
+                Some((entity, ..)) => entity,
+                None => continue,
+            },
+        };
+        let Ok((_, mut floating, mut text, mut transform, mut visibility)) = texts.get_mut(entity) else {
+            continue;
+        };
+        floating.age = 0.0;
+        floating.active = true;
+        text.sections[0].value = format!("+{}", event.amount);
+        text.sections[0].style.color.set_alpha(1.0);
+        transform.translation = event.position.extend(FLOATING_TEXT_Z);
+        *visibility = Visibility::Inherited;
+    }
+}
+
+/// Raises (unless motion is reduced) and fades the visible texts, returning finished ones to the pool.
+pub fn animate_floating_text_system(
+    time: Res<Time>,
+    config: Res<UserConfig>,
+    mut pool: ResMut<FloatingTextPool>,
+    mut texts: Query<(Entity, &mut FloatingText, &mut Text, &mut Transform, &mut Visibility)>,
+) {
+    let delta = time.delta_seconds();
+    let rise = if config.reduce_motion { 0.0 } else { RISE_SPEED };
+    for (entity, mut floating, mut text, mut transform, mut visibility) in &mut texts {
+        if !floating.active {
+            continue;
+        }
+        floating.age += delta;
+        if floating.age >= LIFETIME_SECONDS {
+            floating.active = false;
+            *visibility = Visibility::Hidden;
+            pool.free.push(entity);
+            continue;
+        }
+        transform.translation.y += rise * delta;
+        let fade = 1.0 - floating.age / LIFETIME_SECONDS;
+        text.sections[0].style.color.set_alpha(fade);
+    }
+}
@@ -1,135 +1,168 @@
 use bevy::prelude::*;
-use crate::animation::{AnimationState, AnimationLibrary};
-use rand::Rng;
+use crate::animation::{Animation, AnimationState, AnimationLibrary};
+use crate::gesture::{CatGesture, Gesture, recognize_gestures_system};
+use crate::loading::GameTextures;
+use crate::particles::ParticleBurst;
+use crate::pickup::CatBody;
+use crate::settings::UserConfig;
+use crate::state::{AppState, InGame, Minigame};
+use serde::{Deserialize, Serialize};
+
+/// Half the height of the cat sprite, so obstacles and toys stand on the
+/// same floor as the cat
+pub const CAT_HALF_HEIGHT: f32 = 38.0;
 
 #[derive(Resource, Default)]
 pub struct ClickCounter(pub u32);
 
-#[derive(Component)]
-pub struct AnimatedCat;
-
-#[derive(Component)]
-pub struct CounterText;
-
-#[derive(Component)]
-pub struct Background;
+/// Lifetime statistics for the active profile
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Statistics {
+    pub total_clicks: u64,
+    pub background_changes: u32,
+    /// Most laser dots caught in one round of the laser chase
+    pub best_laser_catches: u32,
+    /// Highest score in the endless runner
+    pub best_runner_score: u32,
+    /// Highest score for a song in the rhythm game
+    pub best_rhythm_score: u32,
+}
 
+/// How many clicks each click on the cat is worth.
 #[derive(Resource)]
-pub struct BackgroundConfig {
-    backgrounds: Vec<String>,
-}
+pub struct ClickMultiplier(pub u32);
 
-impl Default for BackgroundConfig {
+impl Default for ClickMultiplier {
     fn default() -> Self {
-        Self {
-            backgrounds: vec![
-                "backgrounds/summer 2/Summer2.png".to_string(),
-                "backgrounds/summer 3/Summer3.png".to_string(),
-                "backgrounds/summer 4/Summer4.png".to_string(),
-                "backgrounds/summer5/Summer5.png".to_string(),
-                "backgrounds/summer6/Summer6.png".to_string(),
-                "backgrounds/summer7/Summer7.png".to_string(),
-                "backgrounds/summer8/Summer8.png".to_string(),
-            ],
-        }
+        Self(1)
     }
 }
 
-impl BackgroundConfig {
-    pub fn get_random_background(&self) -> &String {
-        let mut rng = rand::thread_rng();
-        let index = rng.gen_range(0..self.backgrounds.len());
-        &self.backgrounds[index]
+/// Sent when a click on the cat earns clicks, with the world position of the click.
+#[derive(Event)]
+pub struct ClicksEarned {
+    pub amount: u32,
+    pub position: Vec2,
+}
+
+/// The animation the cat settles back into after reacting to something.
+#[derive(Resource)]
+pub struct RestingAnimation(pub String);
+
+impl Default for RestingAnimation {
+    fn default() -> Self {
+        Self("idle".to_string())
     }
 }
 
+#[derive(Component)]
+pub struct AnimatedCat;
+
+#[derive(Component)]
+pub struct CounterText;
+
 pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(ClickCounter::default())
-            .insert_resource(BackgroundConfig::default())
+            .insert_resource(Statistics::default())
+            .init_resource::<RestingAnimation>()
+            .init_resource::<ClickMultiplier>()
+            .add_event::<ClicksEarned>()
+            .add_systems(OnEnter(InGame), spawn_game_entities)
             .add_systems(Update, (
-                handle_cat_clicks_system,
-                update_counter_text_system,
-                change_background_on_click_system,
+                handle_cat_clicks_system
+                    .after(recognize_gestures_system)
+                    .run_if(in_state(AppState::Playing).and_then(in_state(Minigame::None))),
+                update_counter_text_system.run_if(in_state(InGame)),
             ));
     }
 }
 
+/// Each tap on the cat earns clicks. Strokes and long presses are gestures of
+/// their own and earn nothing.
+#[allow(clippy::too_many_arguments)]
 pub fn handle_cat_clicks_system(
-    buttons: Res<ButtonInput<MouseButton>>,
-    windows: Query<&Window>,
-    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut gestures: EventReader<CatGesture>,
     mut counter: ResMut<ClickCounter>,
-    cat_query: Query<(&GlobalTransform, &Sprite), With<AnimatedCat>>,
+    multiplier: Res<ClickMultiplier>,
+    mut stats: ResMut<Statistics>,
     mut animation_query: Query<&mut AnimationState, With<AnimatedCat>>,
     animation_library: Res<AnimationLibrary>,
+    resting: Res<RestingAnimation>,
+    mut bursts: EventWriter<ParticleBurst>,
+    mut earned: EventWriter<ClicksEarned>,
 ) {
-    if buttons.just_pressed(MouseButton::Left) {
-        let window = windows.single();
-        if let Some(cursor_pos) = window.cursor_position() {
-            let (camera, camera_transform) = cameras.single();
-            if let Some(world_pos) = camera.viewport_to_world_2d(camera_transform, cursor_pos) {
-                if let Ok((cat_transform, sprite)) = cat_query.get_single() {
-                    let size = sprite.custom_size.unwrap_or(Vec2::new(128.0, 128.0)) * cat_transform.compute_transform().scale.xy();
-                    let half_size = size / 2.0;
-                    let cat_pos = cat_transform.translation().xy();
-                    let min = cat_pos - half_size;
-                    let max = cat_pos + half_size;
-                    
-                    if world_pos.x >= min.x && world_pos.x <= max.x && world_pos.y >= min.y && world_pos.y <= max.y {
-                        counter.0 += 1;
-                        if let Ok(mut state) = animation_query.get_single_mut() {
-                            // Play cute animation, then return to idle after 2 seconds
-                            state.play_animation_then_return("cute", "idle", 2.0, &animation_library);
-                        }
-                    }
-                }
-            }
+    for event in gestures.read() {
+        if !event.gesture.is_tap() {
+            continue;
+        }
+        let amount = multiplier.0.max(1);
+        counter.0 += amount;
+        stats.total_clicks += 1;
+        earned.send(ClicksEarned { amount, position: event.position });
+        bursts.send(ParticleBurst { effect: "hearts".to_string(), position: event.position.extend(0.0) });
+        // Double-clicks and rapid taps have their own reactions
+        if event.gesture == Gesture::Tap
+            && let Ok(mut state) = animation_query.get_single_mut()
+        {
+            // Play cute animation, then settle back down after 2 seconds
+            state.play_animation_then_return("cute", &resting.0, 2.0, &animation_library);
         }
     }
 }
 
+/// Where the mouse cursor is in the world, if it is over the window.
+pub fn cursor_world_position(window: &Window, camera: &Camera, camera_transform: &GlobalTransform) -> Option<Vec2> {
+    window
+        .cursor_position()
+        .and_then(|cursor_pos| camera.viewport_to_world_2d(camera_transform, cursor_pos))
+}
+
+/// Size of the cat sprite in the world.
+pub fn cat_size(sprite: &Sprite, scale: Vec3) -> Vec2 {
+    sprite.custom_size.unwrap_or(Vec2::new(128.0, 128.0)) * scale.xy()
+}
+
+/// Whether a world position lands on the cat sprite. Used for clicks and for
+/// anything dropped onto the cat.
+pub fn cat_contains(cat_transform: &GlobalTransform, sprite: &Sprite, world_pos: Vec2) -> bool {
+    let half_size = cat_size(sprite, cat_transform.compute_transform().scale) / 2.0;
+    let cat_pos = cat_transform.translation().xy();
+    let min = cat_pos - half_size;
+    let max = cat_pos + half_size;
+
+    world_pos.x >= min.x && world_pos.x <= max.x && world_pos.y >= min.y && world_pos.y <= max.y
+}
+
 pub fn update_counter_text_system(
-    mut text_query: Query<&mut Text, With<CounterText>>, 
-    counter: Res<ClickCounter>
+    mut text_query: Query<&mut Text, With<CounterText>>,
+    counter: Res<ClickCounter>,
+    config: Res<UserConfig>,
 ) {
     if let Ok(mut text) = text_query.get_single_mut() {
-        text.sections[0].value = format!("Clicks: {}", counter.0);
+        text.sections[0].value = format!("{}: {}", config.language.translate("Clicks"), counter.0);
     }
 }
 
-pub fn setup_game_entities(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
-    background_config: Res<BackgroundConfig>,
-) {
+pub fn setup_game_entities(mut commands: Commands) {
     // Spawn camera
     commands.spawn(Camera2dBundle::default());
+}
 
-    // Spawn random background
-    let background_path = background_config.get_random_background();
-    let background_texture: Handle<Image> = asset_server.load(background_path.clone());
-    println!("Loading background: {}", background_path);
-
-    commands.spawn((
-        SpriteBundle {
-            texture: background_texture,
-            transform: Transform::from_xyz(0.0, 0.0, -1.0), // Behind everything
-            sprite: Sprite {
-                // Scale to fit screen - adjust as needed
-                custom_size: Some(Vec2::new(1200.0, 800.0)),
-                ..default()
-            },
-            ..default()
-        },
-        Background,
-    ));
-
-    // Load the sprite sheet texture
-    let texture = asset_server.load("cat_black/cat_spritesheet.png");
+/// Spawns the cat and the counter when a profile is loaded. Both are removed
+/// again when returning to the main menu.
+pub fn spawn_game_entities(
+    mut commands: Commands,
+    textures: Res<GameTextures>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+    animation_library: Res<AnimationLibrary>,
+    resting: Res<RestingAnimation>,
+) {
+    // Loaded up front by the loading screen
+    let texture = textures.cat.clone();
 
     // Create texture atlas layout
     let texture_atlas_layout = TextureAtlasLayout::from_grid(
@@ -142,6 +175,8 @@ pub fn setup_game_entities(
 
     let layout_handle = layouts.add(texture_atlas_layout);
 
+    let animation = animation_library.get(&resting.0).map_or_else(AnimationState::default, Animation::start);
+
     // Spawn the animated cat as a sprite
     commands.spawn((
         SpriteBundle {
@@ -159,21 +194,26 @@ pub fn setup_game_entities(
             index: 0, // Start with the first frame
         },
         AnimatedCat,
-        AnimationState::default(),
+        animation,
+        CatBody::default(),
+        StateScoped(InGame),
     ));
 
     // Spawn the counter text (UI)
     commands
-        .spawn(NodeBundle {
-            style: Style {
-                width: Val::Percent(100.0),
-                height: Val::Percent(100.0),
-                justify_content: JustifyContent::Center,
-                align_items: AlignItems::FlexEnd,
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::FlexEnd,
+                    ..default()
+                },
                 ..default()
             },
-            ..default()
-        })
+            StateScoped(InGame),
+        ))
         .with_children(|parent| {
             parent
                 .spawn(TextBundle::from_section(
@@ -187,24 +227,3 @@ pub fn setup_game_entities(
                 .insert(CounterText);
         });
 }
-
-pub fn change_background_on_click_system(
-    counter: Res<ClickCounter>,
-    background_query: Query<(Entity, &Handle<Image>), With<Background>>,
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    background_config: Res<BackgroundConfig>,
-) {
-    // Change background every 5 clicks
-    if counter.0 > 0 && counter.0 % 5 == 0 && counter.is_changed() {
-        if let Ok((background_entity, _)) = background_query.get_single() {
-            // Get a new random background
-            let new_background_path = background_config.get_random_background();
-            let new_background_texture: Handle<Image> = asset_server.load(new_background_path.clone());
-            println!("Changing background to: {}", new_background_path);
-
-            // Update the background sprite
-            commands.entity(background_entity).insert(new_background_texture);
-        }
-    }
-}
@@ -0,0 +1,314 @@
+use bevy::asset::io::file::FileAssetReader;
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::fs;
+
+use crate::animation::{AnimationLibrary, AnimationState};
+use crate::audio::PlaySound;
+use crate::controls::{Action, ActionState};
+use crate::game::{AnimatedCat, RestingAnimation, cat_contains, cursor_world_position};
+use crate::particles::ParticleBurst;
+use crate::state::{AppState, Minigame};
+
+const GESTURES_FILE: &str = "input/gestures.ron";
+// Length of audio/purr.wav, so the purr repeats seamlessly while stroking
+const PURR_SECONDS: f32 = 1.6;
+
+/// Thresholds for telling gestures apart, from `assets/input/gestures.ron`.
+/// Distances are in world units and times in seconds.
+#[derive(Resource, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct GestureConfig {
+    /// How far the cursor may wander during a tap or a long press
+    pub tap_slop: f32,
+    /// How far the cursor has to move across the cat to start a stroke
+    pub stroke_distance: f32,
+    pub long_press_seconds: f32,
+    /// Longest gap between taps that still belong to the same double-click
+    /// or burst of rapid taps
+    pub double_click_seconds: f32,
+    /// Taps in a row that count as rapid tapping
+    pub rapid_taps: u32,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        Self {
+            tap_slop: 6.0,
+            stroke_distance: 40.0,
+            long_press_seconds: 0.6,
+            double_click_seconds: 0.3,
+            rapid_taps: 5,
+        }
+    }
+}
+
+impl GestureConfig {
+    pub fn load() -> Self {
+        let path = FileAssetReader::get_base_path().join("assets").join(GESTURES_FILE);
+        let parsed = fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|contents| ron::from_str(&contents).map_err(|err| err.to_string()));
+        parsed.unwrap_or_else(|err| {
+            println!("Could not load {}: {}", GESTURES_FILE, err);
+            Self::default()
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Gesture {
+    /// A quick press and release that isn't part of a double-click or rapid tapping
+    Tap,
+    /// The cursor started moving across the cat while held
+    StrokeStart,
+    /// The button was let go after stroking
+    StrokeEnd,
+    /// Held still on the cat
+    LongPress,
+    /// The second of two quick taps
+    DoubleClick,
+    /// Every `rapid_taps`-th tap in a row, with how many there have been
+    RapidTaps(u32),
+}
+
+impl Gesture {
+    /// Whether this gesture was a tap on the cat, which is what earns clicks.
+    pub fn is_tap(self) -> bool {
+        matches!(self, Gesture::Tap | Gesture::DoubleClick | Gesture::RapidTaps(_))
+    }
+}
+
+/// Sent when the player makes a gesture on the cat, with the world position
+/// of the cursor.
+#[derive(Event, Clone, Copy, Debug, PartialEq)]
+pub struct CatGesture {
+    pub gesture: Gesture,
+    pub position: Vec2,
+}
+
+/// What the mouse was doing on one frame.
+#[derive(Clone, Copy, Debug)]
+pub struct PointerSample {
+    pub time: f32,
+    /// Cursor position in the world, if it is over the window
+    pub position: Option<Vec2>,
+    pub held: bool,
+    pub on_cat: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Press {
+    started: f32,
+    last_position: Option<Vec2>,
+    /// Total distance moved since the press
+    wandered: f32,
+    /// Distance moved while over the cat
+    stroked: f32,
+    stroking: bool,
+    long_pressed: bool,
+}
+
+/// Turns a stream of pointer samples into gestures. Only presses that start
+/// on the cat are followed.
+#[derive(Resource, Default)]
+pub struct GestureRecognizer {
+    pub config: GestureConfig,
+    press: Option<Press>,
+    /// Time of the last tap and how many taps in a row led up to it
+    tap_streak: Option<(f32, u32)>,
+    /// Something else took the current press, so it is ignored until let go
+    claimed: bool,
+}
+
+impl GestureRecognizer {
+    pub fn new(config: GestureConfig) -> Self {
+        Self { config, ..default() }
+    }
+
+    pub fn is_stroking(&self) -> bool {
+        self.press.is_some_and(|press| press.stroking)
+    }
+
+    /// Forgets any press in progress, for when something else takes over the cat.
+    pub fn cancel(&mut self) {
+        self.press = None;
+    }
+
+    /// Hands the current press to something in front of the cat, such as a
+    /// golden cat or a toy, so it doesn't also count as a gesture on the cat.
+    pub fn claim_press(&mut self) {
+        self.press = None;
+        self.claimed = true;
+    }
+
+    pub fn step(&mut self, sample: PointerSample) -> Option<Gesture> {
+        if self.claimed {
+            self.claimed = sample.held;
+            return None;
+        }
+        let Some(mut press) = self.press else {
+            if sample.held && sample.on_cat {
+                self.press = Some(Press {
+                    started: sample.time,
+                    last_position: sample.position,
+                    wandered: 0.0,
+                    stroked: 0.0,
+                    stroking: false,
+                    long_pressed: false,
+                });
+            }
+            return None;
+        };
+
+        if let (Some(last), Some(position)) = (press.last_position, sample.position) {
+            let distance = last.distance(position);
+            press.wandered += distance;
+            if sample.on_cat {
+                press.stroked += distance;
+            }
+        }
+        press.last_position = sample.position.or(press.last_position);
+
+        if !sample.held {
+            self.press = None;
+            return if press.stroking {
+                Some(Gesture::StrokeEnd)
+            } else if press.long_pressed || press.wandered > self.config.tap_slop {
+                None
+            } else {
+                self.tap(sample.time)
+            };
+        }
+
+        let gesture = if !press.stroking && !press.long_pressed && press.stroked >= self.config.stroke_distance {
+            press.stroking = true;
+            Some(Gesture::StrokeStart)
+        } else if !press.stroking
+            && !press.long_pressed
+            && press.wandered <= self.config.tap_slop
+            && sample.time - press.started >= self.config.long_press_seconds
+        {
+            press.long_pressed = true;
+            Some(Gesture::LongPress)
+        } else {
+            None
+        };
+        self.press = Some(press);
+        gesture
+    }
+
+    fn tap(&mut self, time: f32) -> Option<Gesture> {
+        let streak = match self.tap_streak {
+            Some((last, count)) if time - last <= self.config.double_click_seconds => count + 1,
+            _ => 1,
+        };
+        self.tap_streak = Some((time, streak));
+        let rapid_taps = self.config.rapid_taps.max(2);
+        if streak.is_multiple_of(rapid_taps) {
+            Some(Gesture::RapidTaps(streak))
+        } else if streak == 2 {
+            Some(Gesture::DoubleClick)
+        } else {
+            Some(Gesture::Tap)
+        }
+    }
+}
+
+pub struct GesturePlugin;
+
+impl Plugin for GesturePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GestureRecognizer::new(GestureConfig::load()))
+            .add_event::<CatGesture>()
+            .add_systems(OnExit(Minigame::None), cancel_gesture)
+            .add_systems(Update, (
+                recognize_gestures_system,
+                gesture_reaction_system,
+            ).chain().run_if(in_state(AppState::Playing).and_then(in_state(Minigame::None))));
+    }
+}
+
+fn cancel_gesture(mut recognizer: ResMut<GestureRecognizer>) {
+    recognizer.cancel();
+}
+
+pub fn recognize_gestures_system(
+    time: Res<Time>,
+    actions: Res<ActionState>,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    cats: Query<(&GlobalTransform, &Sprite), With<AnimatedCat>>,
+    mut recognizer: ResMut<GestureRecognizer>,
+    mut gestures: EventWriter<CatGesture>,
+) {
+    let (camera, camera_transform) = cameras.single();
+    let position = cursor_world_position(windows.single(), camera, camera_transform);
+    let on_cat = position.is_some_and(|position| {
+        cats.get_single().is_ok_and(|(cat_transform, sprite)| cat_contains(cat_transform, sprite, position))
+    });
+    let sample = PointerSample {
+        time: time.elapsed_seconds(),
+        position,
+        held: actions.pressed(Action::Pet),
+        on_cat,
+    };
+    if let Some(gesture) = recognizer.step(sample) {
+        gestures.send(CatGesture { gesture, position: position.unwrap_or_default() });
+    }
+}
+
+/// Stroking makes the cat flop over and purr until let go, a long press sends
+/// it to sleep for a moment, a double-click makes it jump and rapid tapping
+/// makes it play.
+#[allow(clippy::too_many_arguments)]
+pub fn gesture_reaction_system(
+    time: Res<Time>,
+    mut gestures: EventReader<CatGesture>,
+    recognizer: Res<GestureRecognizer>,
+    mut cats: Query<&mut AnimationState, With<AnimatedCat>>,
+    animation_library: Res<AnimationLibrary>,
+    resting: Res<RestingAnimation>,
+    mut sounds: EventWriter<PlaySound>,
+    mut bursts: EventWriter<ParticleBurst>,
+    mut purr_timer: Local<Option<Timer>>,
+) {
+    let Ok(mut state) = cats.get_single_mut() else {
+        return;
+    };
+    for event in gestures.read() {
+        match event.gesture {
+            // The click itself is handled by the game
+            Gesture::Tap => {}
+            Gesture::StrokeStart => {
+                state.play_animation("pancake", &animation_library);
+                sounds.send(PlaySound("purr".to_string()));
+                *purr_timer = Some(Timer::from_seconds(PURR_SECONDS, TimerMode::Repeating));
+            }
+            Gesture::StrokeEnd => {
+                state.play_animation_then_return("cute", &resting.0, 1.0, &animation_library);
+                *purr_timer = None;
+            }
+            Gesture::LongPress => {
+                state.play_animation_then_return("sleep", &resting.0, 3.0, &animation_library);
+            }
+            Gesture::DoubleClick => {
+                state.play_animation_then_return("jump", &resting.0, 0.8, &animation_library);
+            }
+            Gesture::RapidTaps(_) => {
+                state.play_animation_then_return("play", &resting.0, 2.0, &animation_library);
+                bursts.send(ParticleBurst { effect: "sparkles".to_string(), position: event.position.extend(0.0) });
+            }
+        }
+    }
+
+    // Keeps purring for as long as the stroke lasts
+    if !recognizer.is_stroking() {
+        *purr_timer = None;
+    }
+    if let Some(timer) = purr_timer.as_mut()
+        && timer.tick(time.delta()).just_finished()
+    {
+        sounds.send(PlaySound("purr".to_string()));
+    }
+}
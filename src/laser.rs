@@ -0,0 +1,395 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+use crate::animation::{AnimationLibrary, AnimationState};
+use crate::controls::{Action, ActionState};
+use crate::game::{AnimatedCat, ClickCounter, ClickMultiplier, ClicksEarned, RestingAnimation, Statistics};
+use crate::particles::ParticleBurst;
+use crate::settings::UserConfig;
+use crate::state::{AppState, Minigame};
+
+pub const LASER_DOT_IMAGE: &str = "minigames/laser_dot.png";
+const ROUND_SECONDS: f32 = 30.0;
+const RESULTS_SECONDS: f32 = 3.0;
+/// Clicks earned for each catch, before the click multiplier
+pub const CATCH_REWARD: u32 = 10;
+
+pub const WALK_SPEED: f32 = 120.0;
+pub const RUN_SPEED: f32 = 320.0;
+/// Further away than this the cat runs rather than walks
+pub const RUN_DISTANCE: f32 = 250.0;
+/// Closer than this the cat pounces
+pub const POUNCE_DISTANCE: f32 = 70.0;
+/// How high above the cat the dot can be and still be pounced on
+pub const POUNCE_HEIGHT: f32 = 140.0;
+/// How close the dot has to be when the cat lands to count as caught
+pub const CATCH_DISTANCE: f32 = 40.0;
+/// The length of the `jump` clip
+const POUNCE_SECONDS: f32 = 0.8;
+const POUNCE_ARC: f32 = 60.0;
+const CATCH_COOLDOWN_SECONDS: f32 = 0.6;
+
+/// What the cat does about the dot, given how far it is from the cat.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChaseMove {
+    Run,
+    Walk,
+    Pounce,
+    /// Right underneath a dot that is out of reach
+    Wait,
+}
+
+impl ChaseMove {
+    pub fn choose(dx: f32, dy: f32) -> Self {
+        let distance = dx.abs();
+        if distance > RUN_DISTANCE {
+            ChaseMove::Run
+        } else if distance > POUNCE_DISTANCE {
+            ChaseMove::Walk
+        } else if dy.abs() <= POUNCE_HEIGHT {
+            ChaseMove::Pounce
+        } else {
+            ChaseMove::Wait
+        }
+    }
+
+    pub fn animation(self) -> Option<&'static str> {
+        match self {
+            ChaseMove::Run => Some("run"),
+            ChaseMove::Walk => Some("walk"),
+            ChaseMove::Pounce => Some("jump"),
+            ChaseMove::Wait => None,
+        }
+    }
+
+    pub fn speed(self) -> f32 {
+        match self {
+            ChaseMove::Run => RUN_SPEED,
+            ChaseMove::Walk => WALK_SPEED,
+            ChaseMove::Pounce | ChaseMove::Wait => 0.0,
+        }
+    }
+}
+
+struct Pounce {
+    elapsed: f32,
+    from_x: f32,
+    to_x: f32,
+    base_y: f32,
+}
+
+/// One round of the laser chase.
+#[derive(Resource)]
+pub struct LaserRound {
+    pub time_left: f32,
+    pub catches: u32,
+    pub earned: u32,
+    pounce: Option<Pounce>,
+    cooldown: f32,
+    /// Counts down while the score is shown, once the round is over
+    results: Option<f32>,
+    start: Vec3,
+}
+
+impl LaserRound {
+    pub fn new(start: Vec3) -> Self {
+        Self {
+            time_left: ROUND_SECONDS,
+            catches: 0,
+            earned: 0,
+            pounce: None,
+            cooldown: 0.0,
+            results: None,
+            start,
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.results.is_some()
+    }
+}
+
+#[derive(Component)]
+pub struct LaserDot;
+
+#[derive(Component)]
+pub struct LaserHud;
+
+pub struct LaserChasePlugin;
+
+impl Plugin for LaserChasePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, start_laser_chase_system.run_if(in_state(AppState::Playing).and_then(in_state(Minigame::None))))
+            .add_systems(OnEnter(Minigame::LaserChase), start_laser_round)
+            .add_systems(OnExit(Minigame::LaserChase), end_laser_round)
+            .add_systems(Update, (
+                move_laser_dot_system,
+                chase_laser_system,
+                laser_round_system,
+            ).chain().run_if(in_state(AppState::Playing).and_then(in_state(Minigame::LaserChase))))
+            .add_systems(Update, laser_cursor_system);
+    }
+}
+
+/// The laser chase action, `L` by default, starts a round.
+pub fn start_laser_chase_system(
+    actions: Res<ActionState>,
+    mut next_minigame: ResMut<NextState<Minigame>>,
+) {
+    if actions.just_pressed(Action::LaserChase) {
+        println!("Starting laser chase");
+        next_minigame.set(Minigame::LaserChase);
+    }
+}
+
+fn start_laser_round(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<UserConfig>,
+    cats: Query<&Transform, With<AnimatedCat>>,
+) {
+    let start = cats.get_single().map(|transform| transform.translation).unwrap_or_default();
+    commands.insert_resource(LaserRound::new(start));
+
+    commands.spawn((
+        SpriteBundle {
+            texture: asset_server.load(LASER_DOT_IMAGE),
+            transform: Transform::from_xyz(0.0, 0.0, 50.0),
+            sprite: Sprite {
+                custom_size: Some(Vec2::splat(24.0)),
+                ..default()
+            },
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        LaserDot,
+        StateScoped(Minigame::LaserChase),
+    ));
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    padding: UiRect::top(Val::Px(16.0)),
+                    ..default()
+                },
+                ..default()
+            },
+            StateScoped(Minigame::LaserChase),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    config.language.translate("Laser chase"),
+                    TextStyle {
+                        font_size: 32.0,
+                        color: Color::srgb(1.0, 0.3, 0.3),
+                        ..default()
+                    },
+                ),
+                LaserHud,
+            ));
+        });
+}
+
+/// Puts the cat back where it was before the round.
+fn end_laser_round(
+    mut commands: Commands,
+    round: Option<Res<LaserRound>>,
+    animation_library: Res<AnimationLibrary>,
+    resting: Res<RestingAnimation>,
+    mut cats: Query<(&mut Transform, &mut Sprite, &mut AnimationState), With<AnimatedCat>>,
+) {
+    if let Some(round) = round
+        && let Ok((mut transform, mut sprite, mut state)) = cats.get_single_mut()
+    {
+        transform.translation = round.start;
+        sprite.flip_x = false;
+        state.play_animation(&resting.0, &animation_library);
+    }
+    commands.remove_resource::<LaserRound>();
+}
+
+/// The dot follows the mouse while it is over the window.
+pub fn move_laser_dot_system(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut dots: Query<(&mut Transform, &mut Visibility), With<LaserDot>>,
+) {
+    let Ok((mut transform, mut visibility)) = dots.get_single_mut() else {
+        return;
+    };
+    let world_pos = windows.get_single().ok().and_then(|window| window.cursor_position()).and_then(|cursor| {
+        let (camera, camera_transform) = cameras.get_single().ok()?;
+        camera.viewport_to_world_2d(camera_transform, cursor)
+    });
+    match world_pos {
+        Some(position) => {
+            transform.translation = position.extend(transform.translation.z);
+            *visibility = Visibility::Inherited;
+        }
+        None => *visibility = Visibility::Hidden,
+    }
+}
+
+pub fn play_if_different(state: &mut AnimationState, name: &str, library: &AnimationLibrary) {
+    if state.current_animation != name {
+        state.play_animation(name, library);
+    }
+}
+
+/// Walks or runs the cat towards the dot, turning to face it, and pounces when close.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn chase_laser_system(
+    time: Res<Time>,
+    animation_library: Res<AnimationLibrary>,
+    resting: Res<RestingAnimation>,
+    multiplier: Res<ClickMultiplier>,
+    mut round: ResMut<LaserRound>,
+    mut counter: ResMut<ClickCounter>,
+    dots: Query<(&Transform, &Visibility), (With<LaserDot>, Without<AnimatedCat>)>,
+    mut cats: Query<(&mut Transform, &mut Sprite, &mut AnimationState), With<AnimatedCat>>,
+    mut earned: EventWriter<ClicksEarned>,
+    mut bursts: EventWriter<ParticleBurst>,
+) {
+    let (Ok((dot, dot_visibility)), Ok((mut cat, mut sprite, mut state))) = (dots.get_single(), cats.get_single_mut()) else {
+        return;
+    };
+    let delta = time.delta_seconds();
+    let dot_visible = *dot_visibility != Visibility::Hidden;
+
+    if let Some(pounce) = &mut round.pounce {
+        pounce.elapsed += delta;
+        let t = (pounce.elapsed / POUNCE_SECONDS).min(1.0);
+        cat.translation.x = pounce.from_x + (pounce.to_x - pounce.from_x) * t;
+        cat.translation.y = pounce.base_y + POUNCE_ARC * 4.0 * t * (1.0 - t);
+        if t < 1.0 {
+            return;
+        }
+
+        // Landed: did the dot stay put long enough?
+        let landed = Vec2::new(cat.translation.x, pounce.base_y);
+        round.pounce = None;
+        round.cooldown = CATCH_COOLDOWN_SECONDS;
+        let offset = dot.translation.truncate() - landed;
+        if dot_visible && !round.is_over() && offset.x.abs() <= CATCH_DISTANCE && offset.y.abs() <= POUNCE_HEIGHT {
+            let amount = CATCH_REWARD * multiplier.0.max(1);
+            counter.0 += amount;
+            round.catches += 1;
+            round.earned += amount;
+            earned.send(ClicksEarned { amount, position: landed });
+            bursts.send(ParticleBurst { effect: "hearts".to_string(), position: cat.translation });
+        }
+        play_if_different(&mut state, &resting.0, &animation_library);
+        return;
+    }
+
+    if round.cooldown > 0.0 {
+        round.cooldown -= delta;
+        return;
+    }
+    if !dot_visible || round.is_over() {
+        play_if_different(&mut state, &resting.0, &animation_library);
+        return;
+    }
+
+    let dx = dot.translation.x - cat.translation.x;
+    let dy = dot.translation.y - cat.translation.y;
+    let chase = ChaseMove::choose(dx, dy);
+    if dx.abs() > 1.0 {
+        // The sprite sheet faces right
+        sprite.flip_x = dx < 0.0;
+    }
+    match chase.animation() {
+        Some(animation) => play_if_different(&mut state, animation, &animation_library),
+        None => play_if_different(&mut state, &resting.0, &animation_library),
+    }
+    match chase {
+        ChaseMove::Pounce => {
+            round.pounce = Some(Pounce {
+                elapsed: 0.0,
+                from_x: cat.translation.x,
+                to_x: dot.translation.x,
+                base_y: cat.translation.y,
+            });
+        }
+        ChaseMove::Run | ChaseMove::Walk => {
+            // Don't overshoot the dot
+            let step = (chase.speed() * delta).min(dx.abs());
+            cat.translation.x += step * dx.signum();
+        }
+        ChaseMove::Wait => {}
+    }
+}
+
+/// Counts down the round, then shows the score for a moment before returning
+/// to normal play.
+pub fn laser_round_system(
+    time: Res<Time>,
+    config: Res<UserConfig>,
+    mut round: ResMut<LaserRound>,
+    mut stats: ResMut<Statistics>,
+    mut huds: Query<&mut Text, With<LaserHud>>,
+    mut next_minigame: ResMut<NextState<Minigame>>,
+) {
+    let language = config.language;
+    let delta = time.delta_seconds();
+    let text = match round.results {
+        None => {
+            round.time_left = (round.time_left - delta).max(0.0);
+            if round.time_left <= 0.0 {
+                let best = stats.best_laser_catches.max(round.catches);
+                if best != stats.best_laser_catches {
+                    stats.best_laser_catches = best;
+                }
+                println!("Laser chase over: {} catches, +{} clicks", round.catches, round.earned);
+                round.results = Some(RESULTS_SECONDS);
+            }
+            format!(
+                "{}: {}   {}: {}",
+                language.translate("Catches"),
+                round.catches,
+                language.translate("Time"),
+                round.time_left.ceil()
+            )
+        }
+        Some(remaining) => {
+            let remaining = remaining - delta;
+            round.results = Some(remaining);
+            if remaining <= 0.0 {
+                next_minigame.set(Minigame::None);
+            }
+            format!(
+                "{}! {}: {} (+{})   {}: {}",
+                language.translate("Round over"),
+                language.translate("Catches"),
+                round.catches,
+                round.earned,
+                language.translate("Best"),
+                stats.best_laser_catches
+            )
+        }
+    };
+    for mut hud in &mut huds {
+        if hud.sections[0].value != text {
+            hud.sections[0].value = text.clone();
+        }
+    }
+}
+
+/// Hides the mouse cursor while the dot stands in for it. It comes back in
+/// the pause menu.
+pub fn laser_cursor_system(
+    state: Res<State<AppState>>,
+    minigame: Res<State<Minigame>>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let visible = !(*state.get() == AppState::Playing && *minigame.get() == Minigame::LaserChase);
+    for mut window in &mut windows {
+        if window.cursor.visible != visible {
+            window.cursor.visible = visible;
+        }
+    }
+}
@@ -0,0 +1,294 @@
+use bevy::asset::{AssetLoadFailedEvent, LoadState};
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use std::collections::HashSet;
+
+use crate::feeding::FoodLibrary;
+use crate::laser::LASER_DOT_IMAGE;
+use crate::particles::ParticleLibrary;
+use crate::runner::CRATE_IMAGE;
+use crate::settings::UserConfig;
+use crate::state::AppState;
+use crate::theme::ActiveTheme;
+use crate::toys::ToyKind;
+
+pub const CAT_SPRITESHEET: &str = "cat_black/cat_spritesheet.png";
+const PLACEHOLDER_SIZE: u32 = 64;
+const PLACEHOLDER_CHECK: u32 = 8;
+const PROGRESS_BAR_WIDTH: f32 = 320.0;
+
+/// Every asset the game waits for before leaving the loading screen, with
+/// the path each was requested from.
+#[derive(Resource, Default)]
+pub struct LoadingAssets {
+    handles: Vec<(String, UntypedHandle)>,
+}
+
+impl LoadingAssets {
+    pub fn load<A: Asset>(&mut self, asset_server: &AssetServer, path: &str) -> Handle<A> {
+        let handle: Handle<A> = asset_server.load(path.to_string());
+        self.track(path, handle.clone());
+        handle
+    }
+
+    /// Waits for a handle that was loaded elsewhere.
+    pub fn track(&mut self, path: &str, handle: impl Into<UntypedHandle>) {
+        self.handles.push((path.to_string(), handle.into()));
+    }
+
+    /// Paths of the assets that could not be loaded.
+    pub fn failed_paths(&self, asset_server: &AssetServer) -> Vec<&str> {
+        self.handles
+            .iter()
+            .filter(|(_, handle)| matches!(asset_server.load_state(handle.id()), LoadState::Failed(_)))
+            .map(|(path, _)| path.as_str())
+            .collect()
+    }
+
+    pub fn progress(&self, asset_server: &AssetServer) -> LoadingProgress {
+        LoadingProgress::count(self.handles.iter().map(|(_, handle)| asset_server.load_state(handle.id())))
+    }
+}
+
+/// How far the loading screen has got. Failed assets count as done: they
+/// are replaced by the placeholder rather than holding up the game.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LoadingProgress {
+    pub loaded: usize,
+    pub failed: usize,
+    pub total: usize,
+}
+
+impl LoadingProgress {
+    pub fn count(states: impl IntoIterator<Item = LoadState>) -> Self {
+        let mut progress = Self::default();
+        for state in states {
+            progress.total += 1;
+            match state {
+                LoadState::Loaded => progress.loaded += 1,
+                LoadState::Failed(_) => progress.failed += 1,
+                LoadState::NotLoaded | LoadState::Loading => {}
+            }
+        }
+        progress
+    }
+
+    pub fn done(&self) -> usize {
+        self.loaded + self.failed
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.done() == self.total
+    }
+
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 { 1.0 } else { self.done() as f32 / self.total as f32 }
+    }
+}
+
+/// Textures shared by the rest of the game.
+#[derive(Resource)]
+pub struct GameTextures {
+    pub cat: Handle<Image>,
+    /// Shown in place of any image that failed to load
+    pub placeholder: Handle<Image>,
+}
+
+/// Images that failed to load, so later uses of them can be swapped too.
+#[derive(Resource, Default)]
+pub struct FailedImages(HashSet<AssetId<Image>>);
+
+#[derive(Component)]
+pub struct LoadingText;
+
+#[derive(Component)]
+pub struct LoadingBar;
+
+pub struct LoadingPlugin;
+
+impl Plugin for LoadingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LoadingAssets>()
+            .init_resource::<FailedImages>()
+            .add_systems(Startup, start_loading)
+            .add_systems(OnEnter(AppState::Loading), spawn_loading_screen)
+            .add_systems(Update, update_loading_screen_system.run_if(in_state(AppState::Loading)))
+            .add_systems(Update, replace_failed_images_system);
+    }
+}
+
+/// A magenta and black checkerboard that is hard to miss.
+pub fn placeholder_image() -> Image {
+    let mut data = Vec::with_capacity((PLACEHOLDER_SIZE * PLACEHOLDER_SIZE * 4) as usize);
+    for y in 0..PLACEHOLDER_SIZE {
+        for x in 0..PLACEHOLDER_SIZE {
+            let magenta = (x / PLACEHOLDER_CHECK + y / PLACEHOLDER_CHECK).is_multiple_of(2);
+            data.extend_from_slice(if magenta { &[255, 0, 255, 255] } else { &[0, 0, 0, 255] });
+        }
+    }
+    Image::new(
+        Extent3d { width: PLACEHOLDER_SIZE, height: PLACEHOLDER_SIZE, depth_or_array_layers: 1 },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    )
+}
+
+/// Queues the cat, mini-game, particle, food, toy and theme images. The background plugin adds
+/// the first background's layers itself.
+pub fn start_loading(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut images: ResMut<Assets<Image>>,
+    mut loading: ResMut<LoadingAssets>,
+    particles: Res<ParticleLibrary>,
+    foods: Res<FoodLibrary>,
+    theme: Res<ActiveTheme>,
+) {
+    let cat = loading.load(&asset_server, CAT_SPRITESHEET);
+    loading.load::<Image>(&asset_server, LASER_DOT_IMAGE);
+    loading.load::<Image>(&asset_server, CRATE_IMAGE);
+    for path in particles.images() {
+        loading.load::<Image>(&asset_server, path);
+    }
+    for (_, food) in foods.iter() {
+        loading.load::<Image>(&asset_server, &food.image);
+    }
+    for toy in ToyKind::ALL {
+        loading.load::<Image>(&asset_server, toy.image());
+    }
+    if let Some(accessory) = &theme.0.accessory {
+        loading.load::<Image>(&asset_server, &accessory.image);
+    }
+    commands.insert_resource(GameTextures { cat, placeholder: images.add(placeholder_image()) });
+}
+
+fn spawn_loading_screen(mut commands: Commands, config: Res<UserConfig>) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(12.0),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgb(0.1, 0.1, 0.12)),
+                z_index: ZIndex::Global(110),
+                ..default()
+            },
+            StateScoped(AppState::Loading),
+        ))
+        .with_children(|root| {
+            root.spawn((
+                TextBundle::from_section(
+                    config.language.translate("Loading"),
+                    TextStyle {
+                        font_size: 28.0,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ),
+                LoadingText,
+            ));
+            root.spawn(NodeBundle {
+                style: Style {
+                    width: Val::Px(PROGRESS_BAR_WIDTH),
+                    height: Val::Px(12.0),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgb(0.3, 0.3, 0.3)),
+                ..default()
+            })
+            .with_children(|bar| {
+                bar.spawn((
+                    NodeBundle {
+                        style: Style {
+                            width: Val::Px(0.0),
+                            height: Val::Percent(100.0),
+                            ..default()
+                        },
+                        background_color: BackgroundColor(Color::srgb(0.95, 0.75, 0.3)),
+                        ..default()
+                    },
+                    LoadingBar,
+                ));
+            });
+        });
+}
+
+/// Shows how many assets are ready and opens the main menu once they all are.
+pub fn update_loading_screen_system(
+    asset_server: Res<AssetServer>,
+    loading: Res<LoadingAssets>,
+    config: Res<UserConfig>,
+    mut texts: Query<&mut Text, With<LoadingText>>,
+    mut bars: Query<&mut Style, With<LoadingBar>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    let progress = loading.progress(&asset_server);
+    for mut text in &mut texts {
+        text.sections[0].value = format!(
+            "{} {}/{}",
+            config.language.translate("Loading"),
+            progress.done(),
+            progress.total
+        );
+    }
+    for mut style in &mut bars {
+        style.width = Val::Px(PROGRESS_BAR_WIDTH * progress.fraction());
+    }
+
+    if progress.is_finished() {
+        if progress.failed > 0 {
+            println!(
+                "Loaded {} assets, {} missing: {}",
+                progress.loaded,
+                progress.failed,
+                loading.failed_paths(&asset_server).join(", ")
+            );
+        } else {
+            println!("Loaded {} assets", progress.loaded);
+        }
+        next_state.set(AppState::MainMenu);
+    }
+}
+
+/// Names every image that fails to load and shows the placeholder instead,
+/// both where it is already used and wherever it is used later.
+pub fn replace_failed_images_system(
+    mut events: EventReader<AssetLoadFailedEvent<Image>>,
+    textures: Option<Res<GameTextures>>,
+    mut failed: ResMut<FailedImages>,
+    mut sprites: Query<&mut Handle<Image>>,
+    mut ui_images: Query<&mut UiImage>,
+) {
+    let mut new_failures = false;
+    for event in events.read() {
+        println!("Missing asset: could not load 'assets/{}': {}", event.path, event.error);
+        failed.0.insert(event.id);
+        new_failures = true;
+    }
+    let Some(textures) = textures else {
+        return;
+    };
+    if failed.0.is_empty() {
+        return;
+    }
+    for mut texture in &mut sprites {
+        if (new_failures || texture.is_changed()) && failed.0.contains(&texture.id()) {
+            *texture = textures.placeholder.clone();
+        }
+    }
+    // UI images, such as the food tray icons, keep their handle inside `UiImage`
+    for mut image in &mut ui_images {
+        if (new_failures || image.is_changed()) && failed.0.contains(&image.texture.id()) {
+            image.texture = textures.placeholder.clone();
+        }
+    }
+}
@@ -1,25 +1,152 @@
 use bevy::prelude::*;
 
 mod animation;
+mod audio;
+mod background;
+mod controls;
+mod daynight;
 mod debug;
+mod feeding;
+mod floating_text;
 mod game;
+mod gesture;
+mod laser;
+mod loading;
+mod particles;
+mod pickup;
+mod profile;
+mod random_events;
+mod rhythm;
+mod rng;
+mod rotation;
+mod runner;
+mod scaling;
+mod settings;
+mod share_code;
+mod state;
+mod theme;
+mod toys;
+mod transition;
 
 use animation::{AnimationLibrary, animate_sprite_system};
+use audio::GameAudioPlugin;
+use background::BackgroundPlugin;
+use controls::ControlsPlugin;
+use daynight::{ClockMode, DayNightPlugin, GameClock};
 use debug::DebugPlugin;
+use feeding::FeedingPlugin;
+use floating_text::FloatingTextPlugin;
 use game::{GamePlugin, setup_game_entities};
+use gesture::GesturePlugin;
+use laser::LaserChasePlugin;
+use loading::LoadingPlugin;
+use particles::ParticlePlugin;
+use pickup::PickupPlugin;
+use profile::{ProfilePlugin, ProfileStore};
+use random_events::RandomEventsPlugin;
+use rhythm::RhythmPlugin;
+use rng::{GameRng, random_seed};
+use runner::RunnerPlugin;
+use scaling::ScalingPlugin;
+use settings::{SettingsPlugin, UserConfig};
+use state::{AppState, StatePlugin};
+use theme::ThemePlugin;
+use toys::ToyPlugin;
+use transition::TransitionPlugin;
 
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins.set(AssetPlugin {
-            file_path: "assets".into(),
-            ..default()
-        }))
+    let options = match handle_command_line() {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("Error: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    // Read before the window is created so it opens with the configured mode and size
+    let config_root = ProfileStore::default().root().to_path_buf();
+    let config = UserConfig::load(&config_root);
+    let seed = options.seed.or(config.seed).unwrap_or_else(random_seed);
+    println!("Random seed: {} (replay with --seed {})", seed, seed);
+
+    let mut app = App::new();
+    app
+        .add_plugins(
+            DefaultPlugins
+                .set(AssetPlugin {
+                    file_path: "assets".into(),
+                    ..default()
+                })
+                .set(WindowPlugin {
+                    primary_window: Some(config.window()),
+                    ..default()
+                }),
+        )
+        .add_plugins(StatePlugin)
+        .add_plugins(SettingsPlugin { config, root: config_root })
+        .add_plugins(ControlsPlugin)
         .insert_resource(AnimationLibrary::new())
+        .insert_resource(GameRng::new(seed))
         .add_plugins(GamePlugin)
+        .add_plugins(ThemePlugin)
+        .add_plugins(BackgroundPlugin)
+        .add_plugins(TransitionPlugin)
+        .add_plugins(ProfilePlugin)
+        .add_plugins(DayNightPlugin)
+        .add_plugins(ScalingPlugin)
+        .add_plugins(ParticlePlugin)
+        .add_plugins(FeedingPlugin)
+        .add_plugins(GesturePlugin)
+        .add_plugins(PickupPlugin)
+        .add_plugins(ToyPlugin)
+        .add_plugins(RandomEventsPlugin)
+        .add_plugins(LoadingPlugin)
+        .add_plugins(LaserChasePlugin)
+        .add_plugins(RunnerPlugin)
+        .add_plugins(RhythmPlugin)
+        .add_plugins(FloatingTextPlugin)
+        .add_plugins(GameAudioPlugin)
         .add_plugins(DebugPlugin)
         .add_systems(Startup, setup_game_entities)
-        .add_systems(Update, animate_sprite_system)
-        .run();
+        .add_systems(Update, animate_sprite_system.run_if(in_state(AppState::Playing)));
+
+    if let Some(hours) = options.fixed_time {
+        app.insert_resource(GameClock::new(ClockMode::Fixed(hours)));
+    }
+    app.run();
+}
+
+/// Options picked up from the command line.
+#[derive(Default)]
+struct CommandLineOptions {
+    /// `--time HH:MM` pins the day/night cycle to that time
+    fixed_time: Option<f32>,
+    /// `--seed N` replays the random choices of an earlier run
+    seed: Option<u64>,
+}
+
+/// Handles `--import <CODE>`, which adds a shared profile before the game starts
+/// and preselects it in the profile picker, `--time HH:MM` and `--seed N`.
+fn handle_command_line() -> Result<CommandLineOptions, Box<dyn std::error::Error>> {
+    let mut options = CommandLineOptions::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--import" {
+            let code = args.next().ok_or("--import needs a share code")?;
+            let store = ProfileStore::default();
+            let name = share_code::import_profile(&store, &code)?;
+            store.set_last_active(Some(&name))?;
+            println!("Imported share code as profile '{}'", name);
+        } else if arg == "--time" {
+            let time = args.next().ok_or("--time needs a time such as 21:30")?;
+            let hours = GameClock::parse_time(&time).ok_or("--time expects HH:MM, e.g. 21:30")?;
+            options.fixed_time = Some(hours);
+        } else if arg == "--seed" {
+            let seed = args.next().ok_or("--seed needs a number")?;
+            options.seed = Some(seed.parse().map_err(|_| "--seed expects a whole number, e.g. 42")?);
+        }
+    }
+    Ok(options)
 }
 
 #[cfg(test)]
@@ -53,4 +180,1172 @@ mod tests {
         assert_eq!(state.current_animation, "walk");
         assert_eq!(state.current_frame, 0);
     }
+
+    /// A profile store in a temporary directory, removed again when the test ends.
+    struct TempProfileStore(profile::ProfileStore);
+
+    impl std::ops::Deref for TempProfileStore {
+        type Target = profile::ProfileStore;
+
+        fn deref(&self) -> &Self::Target {
+            &self.0
+        }
+    }
+
+    impl Drop for TempProfileStore {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(self.0.root());
+        }
+    }
+
+    fn temp_profile_store(test_name: &str) -> TempProfileStore {
+        let root = std::env::temp_dir().join(format!("rust_game_{}_{}", test_name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        TempProfileStore(profile::ProfileStore::new(root))
+    }
+
+    #[test]
+    fn test_profile_create_and_load() {
+        let store = temp_profile_store("create");
+        store.create("Alice").unwrap();
+        assert_eq!(store.list().unwrap(), vec!["Alice".to_string()]);
+        assert_eq!(store.load("Alice").unwrap(), profile::Profile::default());
+        assert!(store.create("Alice").is_err());
+        assert!(store.create("../escape").is_err());
+    }
+
+    #[test]
+    fn test_profile_save_rename_duplicate_delete() {
+        let store = temp_profile_store("lifecycle");
+        store.create("Bob").unwrap();
+        let mut saved = profile::Profile::default();
+        saved.save.clicks = 42;
+        saved.stats.total_clicks = 42;
+        store.save("Bob", &saved).unwrap();
+        store.set_last_active(Some("Bob")).unwrap();
+
+        store.rename("Bob", "Robert").unwrap();
+        assert_eq!(store.last_active().as_deref(), Some("Robert"));
+        store.duplicate("Robert", "Robert copy").unwrap();
+        assert_eq!(store.load("Robert copy").unwrap(), saved);
+
+        store.delete("Robert").unwrap();
+        assert_eq!(store.list().unwrap(), vec!["Robert copy".to_string()]);
+        assert_eq!(store.last_active(), None);
+    }
+
+    #[test]
+    fn test_share_code_round_trip() {
+        let mut shared = share_code::SharedProfile {
+            name: "Carol".to_string(),
+            profile: profile::Profile::default(),
+        };
+        shared.profile.save.clicks = 1234;
+        shared.profile.stats.background_changes = 7;
+
+        let code = share_code::encode(&shared);
+        assert!(code.starts_with("CAT1:"));
+        assert_eq!(share_code::decode(&code).unwrap(), shared);
+    }
+
+    #[test]
+    fn test_share_code_rejects_bad_codes() {
+        let shared = share_code::SharedProfile {
+            name: "Dave".to_string(),
+            profile: profile::Profile::default(),
+        };
+        let code = share_code::encode(&shared);
+
+        assert_eq!(share_code::decode("hello"), Err(share_code::ShareCodeError::Malformed));
+        let newer = code.replacen("CAT1:", "CAT99:", 1);
+        assert_eq!(share_code::decode(&newer), Err(share_code::ShareCodeError::NewerVersion(99)));
+        let (header, payload) = code.rsplit_once(':').unwrap();
+        let checksum = u32::from_str_radix(&header[header.len() - 8..], 16).unwrap();
+        let mistyped = format!("CAT1:{:08x}:{}", checksum ^ 1, payload);
+        assert_eq!(share_code::decode(&mistyped), Err(share_code::ShareCodeError::ChecksumMismatch));
+        let garbled = format!("{}:{}!?", header, &payload[..payload.len() - 2]);
+        assert_eq!(share_code::decode(&garbled), Err(share_code::ShareCodeError::InvalidEncoding));
+    }
+
+    #[test]
+    fn test_share_code_import_avoids_name_clash() {
+        let store = temp_profile_store("import");
+        store.create("Erin").unwrap();
+        let code = share_code::export_profile(&store, "Erin").unwrap();
+        assert_eq!(share_code::import_profile(&store, &code).unwrap(), "Erin 2");
+        assert_eq!(share_code::import_profile(&store, &code).unwrap(), "Erin 3");
+    }
+
+    #[test]
+    fn test_background_layers_discovered_per_folder() {
+        let set = background::BackgroundSet::discover("backgrounds/summer6").unwrap();
+        assert_eq!(set.name, "Summer 6");
+        assert_eq!(set.layers.len(), 5);
+        assert_eq!(set.layers[0], "backgrounds/summer6/1.png");
+        assert_eq!(set.layers[4], "backgrounds/summer6/5.png");
+        assert_eq!(set.parallax[0], 0.0);
+        assert!(set.parallax.windows(2).all(|pair| pair[0] < pair[1]));
+
+        assert_eq!(background::BackgroundSet::discover("backgrounds/summer 4").unwrap().layers.len(), 3);
+        assert!(background::BackgroundSet::discover("backgrounds/missing").is_err());
+    }
+
+    #[test]
+    fn test_background_folders_discovered_with_metadata() {
+        let config = background::BackgroundConfig::discover("backgrounds");
+        let names: Vec<&str> = config.backgrounds().iter().map(|set| set.name.as_str()).collect();
+        assert!(names.contains(&"Summer 1"));
+        assert!(names.contains(&"Summer 5"));
+
+        let meadow = config.backgrounds().iter().find(|set| set.folder == "backgrounds/summer8").unwrap();
+        assert_eq!(meadow.name, "Cloudy Meadow");
+        assert!(meadow.floor_height < 0.0);
+    }
+
+    #[test]
+    fn test_night_backgrounds_shipped() {
+        use daynight::DayPhase;
+        use rand::SeedableRng;
+
+        let mut config = background::BackgroundConfig::discover("backgrounds");
+        assert!(config.backgrounds().iter().any(|set| set.night && set.unlock.is_met(&game::Statistics::default())));
+
+        let stats = game::Statistics::default();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+        for _ in 0..config.backgrounds().len() {
+            assert!(config.next_background(&stats, DayPhase::Night, &mut rng).unwrap().night);
+            assert!(!config.next_background(&stats, DayPhase::Day, &mut rng).unwrap().night);
+        }
+    }
+
+    #[test]
+    fn test_background_unlock_requirements() {
+        let mut stats = game::Statistics::default();
+        let requirement = background::UnlockRequirement::TotalClicks(100);
+        assert!(!requirement.is_met(&stats));
+        stats.total_clicks = 100;
+        assert!(requirement.is_met(&stats));
+        assert!(background::UnlockRequirement::Always.is_met(&game::Statistics::default()));
+    }
+
+    #[test]
+    fn test_parallax_offset_wraps_around_camera() {
+        let width = 1000.0;
+        assert_eq!(background::parallax_offset(0.0, 0.0, 0.5, width), 0.0);
+        assert_eq!(background::parallax_offset(0.0, 200.0, 0.5, width), -100.0);
+        // Far travel wraps back within half a tile of the camera
+        let offset = background::parallax_offset(50.0, 5000.0, 0.5, width);
+        assert!((offset - 50.0).abs() <= width / 2.0);
+
+        // Layers live under the scaled background root, so their local offset
+        // shrinks by the same factor
+        let mut app = App::new();
+        app.insert_resource(background::ParallaxAnchor::Camera)
+            .insert_resource(background::ParallaxScroll(200.0))
+            .insert_resource(background::BackgroundScale(2.0))
+            .add_systems(Update, background::parallax_system);
+        app.world_mut().spawn((Camera::default(), GlobalTransform::default()));
+        let layer = app
+            .world_mut()
+            .spawn((Transform::default(), background::BackgroundLayer { depth: 0.0, parallax: 0.5 }))
+            .id();
+        app.update();
+        assert_eq!(app.world().get::<Transform>(layer).unwrap().translation.x, -50.0);
+    }
+
+    #[test]
+    fn test_shuffle_bag_shows_everything_before_repeating() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let mut bag = background::ShuffleBag::default();
+        let candidates = [0, 1, 2, 3, 4];
+
+        let mut previous = None;
+        for _ in 0..20 {
+            let mut seen: Vec<usize> = (0..candidates.len())
+                .map(|_| bag.next(&candidates, &mut rng).unwrap())
+                .collect();
+            assert_ne!(Some(seen[0]), previous);
+            previous = seen.last().copied();
+            seen.sort();
+            assert_eq!(seen, candidates);
+        }
+        assert_eq!(bag.next(&[], &mut rng), None);
+    }
+
+    #[test]
+    fn test_transition_frames() {
+        use transition::TransitionStyle;
+
+        let start = TransitionStyle::Crossfade.frame(0.0);
+        assert_eq!((start.incoming_opacity, start.outgoing_opacity), (0.0, 1.0));
+        assert_eq!(TransitionStyle::Crossfade.frame(1.0).incoming_opacity, 1.0);
+
+        let slide = TransitionStyle::Slide.frame(1.0);
+        assert_eq!(slide.incoming_x, 0.0);
+        assert!(slide.outgoing_x < 0.0);
+
+        assert_eq!(TransitionStyle::FadeThroughBlack.frame(0.5).black, 1.0);
+        assert_eq!(TransitionStyle::FadeThroughBlack.frame(0.25).incoming_opacity, 0.0);
+        assert_eq!(TransitionStyle::FadeThroughBlack.frame(1.0).black, 0.0);
+    }
+
+    fn rotation_context(previous_clicks: u32, clicks: u32, delta_seconds: f32, local_hour: u32) -> rotation::RotationContext {
+        rotation::RotationContext { previous_clicks, clicks, delta_seconds, local_hour }
+    }
+
+    #[test]
+    fn test_rotation_policies() {
+        use rotation::RotationPolicy;
+
+        let mut every_five = rotation::EveryClicks(5);
+        assert!(!every_five.should_rotate(&rotation_context(3, 4, 0.0, 0)));
+        assert!(every_five.should_rotate(&rotation_context(4, 5, 0.0, 0)));
+        assert!(every_five.should_rotate(&rotation_context(8, 11, 0.0, 0)));
+
+        let mut every_second = rotation::EverySeconds::new(1.0);
+        assert!(!every_second.should_rotate(&rotation_context(0, 0, 0.6, 0)));
+        assert!(every_second.should_rotate(&rotation_context(0, 0, 0.6, 0)));
+
+        let mut on_hour = rotation::OnHour::default();
+        assert!(!on_hour.should_rotate(&rotation_context(0, 0, 0.0, 9)));
+        assert!(!on_hour.should_rotate(&rotation_context(0, 0, 0.0, 9)));
+        assert!(on_hour.should_rotate(&rotation_context(0, 0, 0.0, 10)));
+
+        let mut milestone = rotation::OnMilestone;
+        assert!(milestone.should_rotate(&rotation_context(9, 10, 0.0, 0)));
+        assert!(!milestone.should_rotate(&rotation_context(10, 11, 0.0, 0)));
+        assert!(milestone.should_rotate(&rotation_context(1999, 2000, 0.0, 0)));
+
+        assert!(!rotation::Manual.should_rotate(&rotation_context(4, 5, 100.0, 0)));
+    }
+
+    #[test]
+    fn test_custom_rotation_policy_registration() {
+        use rotation::{RotationAppExt, RotationPolicies, RotationPolicy, RotationSetting};
+
+        struct Always;
+        impl RotationPolicy for Always {
+            fn should_rotate(&mut self, _context: &rotation::RotationContext) -> bool {
+                true
+            }
+        }
+
+        let mut app = App::new();
+        app.register_rotation_policy("always", || Box::new(Always));
+        let policies = app.world().resource::<RotationPolicies>();
+        let mut policy = policies.build(&RotationSetting::Custom("always".to_string()));
+        assert!(policy.should_rotate(&rotation_context(0, 0, 0.0, 0)));
+
+        let mut fallback = policies.build(&RotationSetting::Custom("missing".to_string()));
+        assert!(!fallback.should_rotate(&rotation_context(0, 1, 0.0, 0)));
+
+        // Built-in policies go through the same registry, so a game can replace them
+        rotation::register_builtin_policies(&mut app);
+        let policies = app.world().resource::<RotationPolicies>();
+        assert!(!policies.build(&RotationSetting::Manual).should_rotate(&rotation_context(4, 5, 100.0, 0)));
+        app.register_rotation_policy(rotation::MANUAL, || Box::new(Always));
+        let policies = app.world().resource::<RotationPolicies>();
+        assert!(policies.build(&RotationSetting::Manual).should_rotate(&rotation_context(0, 0, 0.0, 0)));
+
+        // A replacement registered before the plugins are added survives them
+        let mut app = App::new();
+        app.register_rotation_policy(rotation::ON_HOUR, || Box::new(Always));
+        rotation::register_builtin_policies(&mut app);
+        let policies = app.world().resource::<RotationPolicies>();
+        assert!(policies.build(&RotationSetting::OnHour).should_rotate(&rotation_context(0, 0, 0.0, 0)));
+        assert!(!policies.build(&RotationSetting::Manual).should_rotate(&rotation_context(4, 5, 100.0, 0)));
+    }
+
+    #[test]
+    fn test_old_profile_settings_keep_click_interval() {
+        use rotation::RotationSetting;
+
+        let store = temp_profile_store("old_settings");
+        store.create("Gus").unwrap();
+        std::fs::write(
+            store.root().join("Gus").join("settings.ron"),
+            "(background_every_clicks: 12, background_transition: Slide)",
+        )
+        .unwrap();
+        let settings = store.load("Gus").unwrap().settings;
+        assert_eq!(settings.background_rotation, RotationSetting::EveryClicks(12));
+
+        // Saving writes the new format, which loads back the same
+        let mut profile = store.load("Gus").unwrap();
+        profile.settings.background_rotation = RotationSetting::OnHour;
+        store.save("Gus", &profile).unwrap();
+        assert_eq!(store.load("Gus").unwrap().settings.background_rotation, RotationSetting::OnHour);
+    }
+
+    #[test]
+    fn test_day_phases_and_tint() {
+        use daynight::{DayPhase, daylight_tint};
+
+        assert_eq!(DayPhase::from_hour(6.0), DayPhase::Morning);
+        assert_eq!(DayPhase::from_hour(12.0), DayPhase::Day);
+        assert_eq!(DayPhase::from_hour(18.5), DayPhase::Dusk);
+        assert_eq!(DayPhase::from_hour(23.0), DayPhase::Night);
+        assert_eq!(DayPhase::from_hour(2.0), DayPhase::Night);
+
+        assert_eq!(daylight_tint(12.0), Color::srgb(1.0, 1.0, 1.0));
+        let night = daylight_tint(1.0).to_srgba();
+        assert!(night.red < 0.5 && night.blue > night.red);
+    }
+
+    #[test]
+    fn test_game_clock_modes() {
+        let mut fixed = GameClock::new(ClockMode::Fixed(21.5));
+        fixed.advance(1000.0);
+        assert_eq!(fixed.hours, 21.5);
+        assert_eq!(fixed.phase(), daynight::DayPhase::Night);
+
+        let mut fast = GameClock { mode: ClockMode::Accelerated, hours: 23.0 };
+        fast.advance(10.0);
+        assert!((fast.hours - 1.0).abs() < 0.001);
+
+        assert_eq!(GameClock::parse_time("21:30"), Some(21.5));
+        assert_eq!(GameClock::parse_time("7"), Some(7.0));
+        assert_eq!(GameClock::parse_time("24:00"), None);
+        assert_eq!(GameClock::parse_time("noon"), None);
+    }
+
+    #[test]
+    fn test_theme_packs_selected_by_date() {
+        use theme::{DateRange, ThemeChoice, ThemeLibrary};
+
+        let new_year = DateRange { from: (12, 1), to: (2, 28) };
+        assert!(new_year.contains(12, 31) && new_year.contains(1, 15));
+        assert!(!new_year.contains(6, 1));
+
+        let library = ThemeLibrary::discover();
+        assert_eq!(library.for_date(7, 1).id, theme::DEFAULT_THEME);
+        assert_eq!(library.for_date(1, 15).id, "winter");
+        // Leap day is still winter, the first of March isn't
+        assert_eq!(library.for_date(2, 29).id, "winter");
+        assert_eq!(library.for_date(3, 1).id, theme::DEFAULT_THEME);
+        assert_eq!(library.for_date(10, 31).id, "autumn");
+        // The holiday's shorter range wins over the season it falls in
+        let christmas = library.for_date(12, 24);
+        assert_eq!(christmas.id, "christmas");
+        assert_eq!(christmas.accessory.as_ref().unwrap().image, "themes/christmas/santa_hat.png");
+
+        let named = ThemeChoice::Named("autumn".to_string());
+        assert_eq!(library.resolve(&named, 7, 1).id, "autumn");
+        assert_eq!(library.resolve(&ThemeChoice::Named("gone".to_string()), 7, 1).id, theme::DEFAULT_THEME);
+
+        let mut choice = ThemeChoice::Auto;
+        for _ in 0..library.themes().len() {
+            choice = library.next_choice(&choice);
+            assert!(matches!(choice, ThemeChoice::Named(_)));
+        }
+        assert_eq!(library.next_choice(&choice), ThemeChoice::Auto);
+    }
+
+    #[test]
+    fn test_scaling_modes() {
+        use scaling::{DESIGN_SIZE, ScalingMode, background_cover_scale};
+
+        // Same aspect ratio as the design: every mode shows exactly the design area
+        let window = DESIGN_SIZE * 1.5;
+        assert_eq!(ScalingMode::Fit.visible_area(window, 1.0), DESIGN_SIZE);
+        assert_eq!(ScalingMode::Fill.visible_area(window, 1.0), DESIGN_SIZE);
+        assert_eq!(ScalingMode::Integer.pixels_per_unit(window, 1.0), 1.0);
+        assert_eq!(ScalingMode::Integer.pixels_per_unit(window, 2.0), 1.5);
+
+        // A tall window shows more height in fit mode and crops the sides in fill mode
+        let tall = Vec2::new(720.0, 1280.0);
+        let fit = ScalingMode::Fit.visible_area(tall, 1.0);
+        assert_eq!(fit.x, DESIGN_SIZE.x);
+        assert!(fit.y > DESIGN_SIZE.y);
+        let fill = ScalingMode::Fill.visible_area(tall, 1.0);
+        assert_eq!(fill.y, DESIGN_SIZE.y);
+        assert!(fill.x < DESIGN_SIZE.x);
+
+        // The background grows to cover the extra height, but never shrinks
+        assert!(background_cover_scale(fit) * background::LAYER_SIZE.y >= fit.y);
+        assert_eq!(background_cover_scale(DESIGN_SIZE), 1.0);
+    }
+
+    #[test]
+    fn test_particle_curves_and_effects() {
+        use particles::{ParticleLibrary, sample_curve};
+
+        assert_eq!(sample_curve(&[], 0.5), 1.0);
+        assert_eq!(sample_curve(&[0.3], 0.9), 0.3);
+        assert_eq!(sample_curve(&[1.0, 0.0], 0.25), 0.75);
+        assert_eq!(sample_curve(&[0.0, 1.0, 0.0], 0.5), 1.0);
+        assert_eq!(sample_curve(&[0.0, 1.0, 0.0], 2.0), 0.0);
+
+        // Everything the game fires by name, plus the theme packs' ambient effects
+        let library = ParticleLibrary::load();
+        for effect in ["hearts", "sparkles", "golden_trail", "dust", "zzz", "snow", "leaves"] {
+            assert!(library.get(effect).is_some(), "missing effect {}", effect);
+        }
+        assert!(library.get("hearts").unwrap().burst > 0);
+        assert!(library.get("zzz").unwrap().rate > 0.0);
+    }
+
+    #[test]
+    fn test_floating_text_is_pooled() {
+        use floating_text::{FloatingTextPlugin, POOL_SIZE};
+        use game::ClicksEarned;
+
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .add_event::<ClicksEarned>()
+            .insert_resource(UserConfig::default())
+            .add_plugins(FloatingTextPlugin);
+        app.update();
+
+        // Far more clicks than the pool holds still only uses the pooled texts
+        for _ in 0..POOL_SIZE * 3 {
+            app.world_mut().send_event(ClicksEarned { amount: 2, position: Vec2::ZERO });
+        }
+        app.update();
+        let mut query = app.world_mut().query::<&Text>();
+        let texts: Vec<String> = query.iter(app.world()).map(|text| text.sections[0].value.clone()).collect();
+        assert_eq!(texts.len(), POOL_SIZE);
+        assert!(texts.iter().all(|text| text == "+2"));
+    }
+
+    #[test]
+    fn test_sound_library_and_headless_audio() {
+        use audio::{AudioCategory, AudioVolumes, GameAudioPlugin, PlaySound, SoundLibrary};
+        use rand::SeedableRng;
+
+        let library = SoundLibrary::load();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+        let mut files = std::collections::HashSet::new();
+        for _ in 0..50 {
+            let (def, file, speed) = library.pick_variation("meow", &mut rng).unwrap();
+            assert_eq!(def.category, AudioCategory::Voice);
+            assert!((def.pitch.0..=def.pitch.1).contains(&speed));
+            files.insert(file.to_string());
+        }
+        assert!(files.len() > 1, "meows should vary");
+        assert!(library.pick_variation("missing", &mut rng).is_none());
+        for sound in library.animations.values().chain(library.frames.iter().map(|frame| &frame.sound)) {
+            assert!(library.sounds.contains_key(sound), "unknown sound {}", sound);
+        }
+
+        let day = daynight::DayPhase::Day;
+        let night = daynight::DayPhase::Night;
+        assert_eq!(library.music_for(Some("own.wav"), night).as_deref(), Some("own.wav"));
+        assert_ne!(library.music_for(None, day), library.music_for(None, night));
+
+        let volumes = AudioVolumes { master: 0.5, music: 0.5, ..default() };
+        assert_eq!(volumes.effective(AudioCategory::Music), 0.25);
+
+        // Without Bevy's audio plugin every request is a no-op
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default()))
+            .insert_resource(background::ActiveBackground::default())
+            .init_resource::<GameClock>()
+            .init_resource::<GameRng>()
+            .add_plugins(GameAudioPlugin);
+        app.world_mut().send_event(PlaySound("meow".to_string()));
+        app.update();
+        let mut sounds = app.world_mut().query::<&Handle<AudioSource>>();
+        assert_eq!(sounds.iter(app.world()).count(), 0);
+    }
+
+    #[test]
+    fn test_user_config_round_trip() {
+        use settings::{Language, WindowModeSetting};
+
+        let store = temp_profile_store("config");
+        assert_eq!(UserConfig::load(store.root()), UserConfig::default());
+
+        let config = UserConfig {
+            window_mode: WindowModeSetting::Borderless,
+            ui_scale: 1.5,
+            reduce_motion: true,
+            language: Language::German,
+            ..default()
+        };
+        config.save(store.root()).unwrap();
+        assert_eq!(UserConfig::load(store.root()), config);
+        assert_eq!(config.window().mode, bevy::window::WindowMode::BorderlessFullscreen);
+
+        // The config sits beside the profiles without being mistaken for one
+        assert!(store.list().unwrap().is_empty());
+
+        std::fs::write(store.root().join(settings::CONFIG_FILE), "not ron").unwrap();
+        assert_eq!(UserConfig::load(store.root()), UserConfig::default());
+
+        assert_eq!(Language::German.translate("Clicks"), "Klicks");
+        assert_eq!(Language::Spanish.translate("Untranslated"), "Untranslated");
+        let rows = settings::settings_rows(&config, &profile::ProfileSettings::default());
+        assert!(rows.iter().any(|(_, label, value)| *label == "Reduce motion" && value == "An"));
+    }
+
+    #[test]
+    fn test_window_size_remembered_after_resizing() {
+        use bevy::window::{PrimaryWindow, WindowResized};
+        use std::time::Duration;
+
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .insert_resource(UserConfig::default())
+            .add_event::<WindowResized>()
+            .add_systems(Update, settings::remember_window_size_system);
+        let window = app.world_mut().spawn((Window::default(), PrimaryWindow)).id();
+        let resize = |app: &mut App, width: f32, seconds: f32| {
+            app.world_mut().send_event(WindowResized { window, width, height: 600.0 });
+            app.world_mut().resource_mut::<Time>().advance_by(Duration::from_secs_f32(seconds));
+            app.update();
+        };
+
+        // Still dragging, so nothing is kept yet
+        resize(&mut app, 900.0, 0.1);
+        resize(&mut app, 1000.0, 0.1);
+        assert_eq!(app.world().resource::<UserConfig>().window_size, (1280.0, 720.0));
+
+        app.world_mut().resource_mut::<Time>().advance_by(Duration::from_secs(1));
+        app.update();
+        assert_eq!(app.world().resource::<UserConfig>().window_size, (1000.0, 600.0));
+    }
+
+    #[test]
+    fn test_pause_freezes_game_time() {
+        use bevy::state::app::StatesPlugin;
+        use state::{AppState, InGame};
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin, StatePlugin, ControlsPlugin))
+            .init_resource::<ButtonInput<KeyCode>>()
+            .insert_resource(UserConfig::default())
+            .init_resource::<profile::ProfileManager>()
+            .init_resource::<profile::ProfilePickerState>()
+            .init_resource::<profile::ProfileSettings>()
+            .init_resource::<game::ClickCounter>()
+            .init_resource::<game::Statistics>()
+            .init_resource::<feeding::Inventory>()
+            .init_resource::<feeding::CatNeeds>()
+            .add_event::<audio::PlaySound>();
+        let state = |app: &App| *app.world().resource::<State<AppState>>().get();
+        let press_escape = |app: &mut App| {
+            app.world_mut().resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::Escape);
+            app.update();
+            app.world_mut().resource_mut::<ButtonInput<KeyCode>>().reset_all();
+            app.update();
+        };
+
+        // Esc does nothing outside the game
+        app.update();
+        assert_eq!(state(&app), AppState::Loading);
+        app.world_mut().resource_mut::<NextState<AppState>>().set(AppState::MainMenu);
+        app.update();
+        assert_eq!(state(&app), AppState::MainMenu);
+        press_escape(&mut app);
+        assert_eq!(state(&app), AppState::MainMenu);
+        assert!(app.world().get_resource::<State<InGame>>().is_none());
+
+        app.world_mut().resource_mut::<NextState<AppState>>().set(AppState::Playing);
+        app.update();
+        assert!(app.world().get_resource::<State<InGame>>().is_some());
+        assert!(!app.world().resource::<Time<Virtual>>().is_paused());
+
+        press_escape(&mut app);
+        assert_eq!(state(&app), AppState::Paused);
+        assert!(app.world().resource::<Time<Virtual>>().is_paused());
+        assert!(app.world().get_resource::<State<InGame>>().is_some());
+
+        // The settings back out to the pause menu, still frozen
+        app.world_mut().resource_mut::<NextState<AppState>>().set(AppState::Settings);
+        app.update();
+        press_escape(&mut app);
+        assert_eq!(state(&app), AppState::Paused);
+        assert!(app.world().resource::<Time<Virtual>>().is_paused());
+
+        press_escape(&mut app);
+        assert_eq!(state(&app), AppState::Playing);
+        assert!(!app.world().resource::<Time<Virtual>>().is_paused());
+    }
+
+    #[test]
+    fn test_loading_progress_and_placeholder() {
+        use bevy::asset::{AssetLoadError, LoadState};
+        use loading::LoadingProgress;
+
+        let failed = || LoadState::Failed(Box::new(AssetLoadError::CannotLoadIgnoredAsset { path: "missing.png".into() }));
+        let progress = LoadingProgress::count([LoadState::Loaded, LoadState::Loading, failed(), LoadState::NotLoaded]);
+        assert_eq!(progress, LoadingProgress { loaded: 1, failed: 1, total: 4 });
+        assert!(!progress.is_finished());
+        assert_eq!(progress.fraction(), 0.5);
+
+        // A missing file still lets the game start
+        let progress = LoadingProgress::count([LoadState::Loaded, failed()]);
+        assert!(progress.is_finished());
+        assert!(LoadingProgress::count([]).is_finished());
+
+        let placeholder = loading::placeholder_image();
+        assert_eq!(placeholder.width(), 64);
+        assert_eq!(&placeholder.data[0..4], &[255, 0, 255, 255]);
+        assert_eq!(&placeholder.data[8 * 4..8 * 4 + 4], &[0, 0, 0, 255]);
+
+        // Sprites and UI images using a failed image both get the placeholder
+        let mut app = App::new();
+        let textures = loading::GameTextures {
+            cat: Handle::weak_from_u128(1),
+            placeholder: Handle::weak_from_u128(2),
+        };
+        let placeholder = textures.placeholder.clone();
+        let missing: Handle<Image> = Handle::weak_from_u128(3);
+        app.insert_resource(textures)
+            .init_resource::<loading::FailedImages>()
+            .add_event::<bevy::asset::AssetLoadFailedEvent<Image>>()
+            .add_systems(Update, loading::replace_failed_images_system);
+        let sprite = app.world_mut().spawn(missing.clone()).id();
+        let icon = app.world_mut().spawn(UiImage::new(missing.clone())).id();
+        app.world_mut().send_event(bevy::asset::AssetLoadFailedEvent::<Image> {
+            id: missing.id(),
+            path: "food/missing.png".into(),
+            error: AssetLoadError::CannotLoadIgnoredAsset { path: "food/missing.png".into() },
+        });
+        app.update();
+        assert_eq!(app.world().get::<Handle<Image>>(sprite), Some(&placeholder));
+        assert_eq!(app.world().get::<UiImage>(icon).unwrap().texture, placeholder);
+
+        // An icon spawned later with the same image is swapped too
+        let later = app.world_mut().spawn(UiImage::new(missing)).id();
+        app.update();
+        assert_eq!(app.world().get::<UiImage>(later).unwrap().texture, placeholder);
+    }
+
+    #[test]
+    fn test_laser_chase_moves() {
+        use laser::{ChaseMove, POUNCE_DISTANCE, POUNCE_HEIGHT, RUN_DISTANCE};
+
+        assert_eq!(ChaseMove::choose(-(RUN_DISTANCE + 1.0), 0.0), ChaseMove::Run);
+        assert_eq!(ChaseMove::choose(RUN_DISTANCE, 0.0), ChaseMove::Walk);
+        assert_eq!(ChaseMove::choose(POUNCE_DISTANCE - 1.0, POUNCE_HEIGHT), ChaseMove::Pounce);
+        // Too high to reach, so the cat waits underneath
+        assert_eq!(ChaseMove::choose(0.0, POUNCE_HEIGHT + 1.0), ChaseMove::Wait);
+
+        let library = AnimationLibrary::new();
+        for chase in [ChaseMove::Run, ChaseMove::Walk, ChaseMove::Pounce] {
+            assert!(library.get(chase.animation().unwrap()).is_some());
+        }
+        assert!(ChaseMove::Run.speed() > ChaseMove::Walk.speed());
+        assert_eq!(ChaseMove::Wait.speed(), 0.0);
+    }
+
+    #[test]
+    fn test_runner_difficulty_and_collision() {
+        use runner::{CAT_HITBOX, MAX_SPEED, START_SPEED, boxes_overlap, obstacle_gap, runner_speed};
+        use rand::SeedableRng;
+
+        assert_eq!(runner_speed(0.0), START_SPEED);
+        assert!(runner_speed(20.0) > runner_speed(10.0));
+        assert_eq!(runner_speed(1000.0), MAX_SPEED);
+
+        // Obstacles come closer together, in time, as the game speeds up
+        let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+        let average_seconds = |speed: f32, rng: &mut rand::rngs::StdRng| {
+            (0..200).map(|_| obstacle_gap(speed, rng) / speed).sum::<f32>() / 200.0
+        };
+        assert!(average_seconds(MAX_SPEED, &mut rng) < average_seconds(START_SPEED, &mut rng));
+
+        let crate_size = Vec2::new(40.0, 40.0);
+        assert!(boxes_overlap(Vec2::ZERO, CAT_HITBOX, Vec2::new(30.0, 0.0), crate_size));
+        // Jumping clears the crate
+        assert!(!boxes_overlap(Vec2::new(0.0, 60.0), CAT_HITBOX, Vec2::ZERO, crate_size));
+        assert!(!boxes_overlap(Vec2::ZERO, CAT_HITBOX, Vec2::new(80.0, 0.0), crate_size));
+    }
+
+    #[test]
+    fn test_beatmap_parsing_and_judgement() {
+        use rhythm::{Beatmap, Judgement, RhythmJudge, dance_frame_duration};
+
+        let beatmap = Beatmap::parse(
+            "# test song\ntitle: Test\nbpm: 120\noffset: 1.0\nnotes:\n2 0 1 # out of order\n3\n",
+        )
+        .unwrap();
+        assert_eq!(beatmap.title, "Test");
+        assert_eq!(beatmap.notes, vec![0.0, 1.0, 2.0, 3.0]);
+        assert_eq!(beatmap.note_times(), vec![1.0, 1.5, 2.0, 2.5]);
+
+        assert!(Beatmap::parse("title: No tempo\nnotes:\n1").unwrap_err().contains("bpm"));
+        assert!(Beatmap::parse("bpm: 90\nnotes:\n1 x").unwrap_err().starts_with("line 3"));
+        assert!(Beatmap::parse("bpm: 90\nnotes:\n").is_err());
+
+        // Simulated presses against the note times
+        let mut judge = RhythmJudge::new(beatmap.note_times());
+        assert_eq!(judge.press(1.02), Some((0, Judgement::Perfect)));
+        assert_eq!(judge.press(1.59), Some((1, Judgement::Good)));
+        // Too early for the next note
+        assert_eq!(judge.press(1.7), None);
+        assert_eq!(judge.advance(2.2), vec![2]);
+        assert_eq!(judge.combo, 0);
+        assert_eq!(judge.press(2.5), Some((3, Judgement::Perfect)));
+        assert!(judge.is_finished());
+        assert_eq!(judge.score, 250);
+        assert_eq!(judge.best_combo, 2);
+        assert_eq!(judge.accuracy(), Some(2.5 / 4.0));
+        assert_eq!(judge.recent_accuracy(1), Some(1.0));
+
+        assert!(dance_frame_duration(1.0) < dance_frame_duration(0.3));
+
+        for entry in std::fs::read_dir("assets/rhythm").unwrap() {
+            let path = entry.unwrap().path();
+            let text = std::fs::read_to_string(&path).unwrap();
+            assert!(Beatmap::parse(&text).is_ok(), "{} should parse", path.display());
+        }
+    }
+
+    #[test]
+    fn test_feeding_inventory_and_needs() {
+        use feeding::{CatNeeds, FoodDef, FoodLibrary, Inventory};
+
+        let foods: std::collections::BTreeMap<String, FoodDef> =
+            ron::from_str(&std::fs::read_to_string("assets/food/foods.ron").unwrap()).unwrap();
+        let animations = AnimationLibrary::new();
+        for (id, food) in &foods {
+            for (clip, _) in &food.reaction {
+                assert!(animations.get(clip).is_some(), "{} reacts with missing animation {}", id, clip);
+            }
+        }
+        let mut broken = foods.clone();
+        broken.get_mut("fish").unwrap().reaction.push(("missing".to_string(), 1.0));
+        assert!(FoodLibrary::new(broken, &animations).is_err());
+        let library = FoodLibrary::new(foods, &animations).unwrap();
+        let fish = library.get("fish").unwrap().clone();
+
+        let mut counter = game::ClickCounter(fish.price + 5);
+        let mut inventory = Inventory::default();
+        assert!(inventory.buy("fish", &fish, &mut counter));
+        assert_eq!(counter.0, 5);
+        // Too few clicks left for another
+        assert!(!inventory.buy("fish", &fish, &mut counter));
+        assert_eq!(inventory.count("fish"), 1);
+
+        let mut needs = CatNeeds { fullness: 0.2, happiness: 0.9 };
+        assert!(inventory.take("fish"));
+        needs.feed(&fish);
+        assert_eq!(needs.fullness, 0.2 + fish.fullness);
+        assert_eq!(needs.happiness, 1.0);
+        assert!(!inventory.take("fish"));
+        needs.decay(100_000.0);
+        assert_eq!(needs, CatNeeds { fullness: 0.0, happiness: 0.0 });
+
+        // The inventory is part of the save, and old saves still load
+        let store = temp_profile_store("feeding");
+        store.create("Dana").unwrap();
+        let mut saved = profile::Profile::default();
+        saved.save.inventory.add("treat", 3);
+        saved.save.needs = needs;
+        store.save("Dana", &saved).unwrap();
+        assert_eq!(store.load("Dana").unwrap(), saved);
+        let old: profile::SaveData = ron::from_str("(clicks: 7)").unwrap();
+        assert_eq!(old.inventory, Inventory::default());
+        assert_eq!(old.needs, CatNeeds::default());
+
+        // Drops use the same hit test as clicks
+        let cat = GlobalTransform::from(Transform::from_xyz(100.0, 0.0, 0.0).with_scale(Vec3::splat(2.0)));
+        let sprite = Sprite { custom_size: Some(Vec2::splat(64.0)), ..default() };
+        assert!(game::cat_contains(&cat, &sprite, Vec2::new(160.0, 60.0)));
+        assert!(!game::cat_contains(&cat, &sprite, Vec2::new(0.0, 0.0)));
+
+        // The reaction plays in order, then the cat settles down again
+        let animations = AnimationLibrary::new();
+        let mut state = AnimationState::default();
+        state.play_sequence(&fish.reaction, "idle", &animations);
+        assert_eq!(state.current_animation, fish.reaction[0].0);
+        let mut order = vec![state.current_animation.clone()];
+        order.extend(state.next_animation.iter().map(|(name, _)| name.clone()));
+        order.extend(state.queued.iter().map(|(name, _)| name.clone()));
+        let mut expected: Vec<String> = fish.reaction.iter().map(|(name, _)| name.clone()).collect();
+        expected.push("idle".to_string());
+        assert_eq!(order, expected);
+    }
+
+    #[test]
+    fn test_gestures_from_cursor_paths() {
+        use gesture::{Gesture, GestureConfig, GestureRecognizer, PointerSample};
+
+        // The cat covers -50..50 on both axes
+        let on_cat = |position: Vec2| position.x.abs() <= 50.0 && position.y.abs() <= 50.0;
+        let run = |path: &[(f32, Vec2, bool)]| {
+            let mut recognizer = GestureRecognizer::new(GestureConfig::default());
+            path.iter()
+                .filter_map(|&(time, position, held)| {
+                    recognizer.step(PointerSample { time, position: Some(position), held, on_cat: on_cat(position) })
+                })
+                .collect::<Vec<_>>()
+        };
+        let tap = |time: f32| [(time, Vec2::ZERO, true), (time + 0.05, Vec2::ZERO, false)];
+
+        // A single click is just a click
+        assert_eq!(run(&tap(0.0)), vec![Gesture::Tap]);
+
+        // Dragging across the body strokes the cat until let go
+        let stroke: Vec<_> = (0..=10)
+            .map(|step| (step as f32 * 0.05, Vec2::new(-40.0 + step as f32 * 8.0, 0.0), true))
+            .chain([(0.6, Vec2::new(40.0, 0.0), false)])
+            .collect();
+        assert_eq!(run(&stroke), vec![Gesture::StrokeStart, Gesture::StrokeEnd]);
+
+        // Moving off the cat doesn't count towards a stroke
+        let off_cat = [(0.0, Vec2::new(45.0, 0.0), true), (0.1, Vec2::new(200.0, 0.0), true), (0.2, Vec2::ZERO, false)];
+        assert_eq!(run(&off_cat), vec![]);
+
+        // Holding still, with a little jitter
+        let hold = [(0.0, Vec2::ZERO, true), (0.3, Vec2::new(2.0, 1.0), true), (0.7, Vec2::new(1.0, 0.0), true), (1.5, Vec2::ZERO, false)];
+        assert_eq!(run(&hold), vec![Gesture::LongPress]);
+
+        let double: Vec<_> = [tap(0.0), tap(0.25)].concat();
+        assert_eq!(run(&double), vec![Gesture::Tap, Gesture::DoubleClick]);
+        // Too slow for a double-click
+        let slow: Vec<_> = [tap(0.0), tap(0.6)].concat();
+        assert_eq!(run(&slow), vec![Gesture::Tap, Gesture::Tap]);
+
+        let rapid: Vec<_> = (0..10).flat_map(|index| tap(index as f32 * 0.2)).collect();
+        let streak: Vec<_> = run(&rapid).into_iter().filter(|gesture| *gesture != Gesture::Tap).collect();
+        assert_eq!(streak, vec![Gesture::DoubleClick, Gesture::RapidTaps(5), Gesture::RapidTaps(10)]);
+        // Every one of those taps is still a tap
+        assert_eq!(run(&rapid).len(), 10);
+
+        // Thresholds come from the config file
+        let config: GestureConfig = ron::from_str(&std::fs::read_to_string("assets/input/gestures.ron").unwrap()).unwrap();
+        assert_eq!(config, GestureConfig::default());
+        let mut recognizer = GestureRecognizer::new(GestureConfig { long_press_seconds: 2.0, ..default() });
+        let sample = |time: f32, held: bool| PointerSample { time, position: Some(Vec2::ZERO), held, on_cat: true };
+        assert_eq!(recognizer.step(sample(0.0, true)), None);
+        assert_eq!(recognizer.step(sample(1.0, true)), None);
+        assert_eq!(recognizer.step(sample(2.0, true)), Some(Gesture::LongPress));
+    }
+
+    #[test]
+    fn test_click_on_event_over_cat_is_not_a_tap() {
+        use gesture::{Gesture, GestureConfig, GestureRecognizer, PointerSample};
+
+        // The golden cat or surprise box is in front of the cat, so the cursor
+        // is over both
+        let sample = |time: f32, held: bool| PointerSample { time, position: Some(Vec2::ZERO), held, on_cat: true };
+
+        // Claimed before the recognizer sees the press, as the event systems do
+        let mut recognizer = GestureRecognizer::new(GestureConfig::default());
+        recognizer.claim_press();
+        assert_eq!(recognizer.step(sample(0.0, true)), None);
+        assert_eq!(recognizer.step(sample(0.05, false)), None);
+        // The next click on the cat is a tap again
+        assert_eq!(recognizer.step(sample(1.0, true)), None);
+        assert_eq!(recognizer.step(sample(1.05, false)), Some(Gesture::Tap));
+
+        // Claimed after the press already started, as a toy grab may be
+        let mut recognizer = GestureRecognizer::new(GestureConfig::default());
+        assert_eq!(recognizer.step(sample(0.0, true)), None);
+        recognizer.claim_press();
+        assert_eq!(recognizer.step(sample(0.05, true)), None);
+        assert_eq!(recognizer.step(sample(1.0, true)), None);
+        assert_eq!(recognizer.step(sample(1.05, false)), None);
+    }
+
+    #[test]
+    fn test_only_taps_earn_clicks() {
+        use gesture::{CatGesture, Gesture};
+
+        let mut app = App::new();
+        app.insert_resource(game::ClickCounter::default())
+            .init_resource::<game::ClickMultiplier>()
+            .init_resource::<game::Statistics>()
+            .init_resource::<game::RestingAnimation>()
+            .insert_resource(AnimationLibrary::new())
+            .add_event::<CatGesture>()
+            .add_event::<particles::ParticleBurst>()
+            .add_event::<game::ClicksEarned>()
+            .add_systems(Update, game::handle_cat_clicks_system);
+        let gesture = |app: &mut App, gesture: Gesture| {
+            app.world_mut().send_event(CatGesture { gesture, position: Vec2::ZERO });
+            app.update();
+            app.world().resource::<game::ClickCounter>().0
+        };
+
+        // Stroking, holding still and letting go of a stroke earn nothing
+        assert_eq!(gesture(&mut app, Gesture::StrokeStart), 0);
+        assert_eq!(gesture(&mut app, Gesture::StrokeEnd), 0);
+        assert_eq!(gesture(&mut app, Gesture::LongPress), 0);
+        // Every tap does, including those making up a double-click
+        assert_eq!(gesture(&mut app, Gesture::Tap), 1);
+        assert_eq!(gesture(&mut app, Gesture::DoubleClick), 2);
+        assert_eq!(gesture(&mut app, Gesture::RapidTaps(5)), 3);
+    }
+
+    #[test]
+    fn test_cat_falls_and_lands() {
+        use pickup::{BIG_DROP_HEIGHT, BodyState, CatBody, Landing, body_bounds};
+
+        let screen = Rect::new(-640.0, -360.0, 640.0, 360.0);
+        let floor = -100.0;
+        let bounds = body_bounds(screen, Vec2::splat(40.0), floor);
+        assert_eq!(bounds, Rect::new(-600.0, floor, 600.0, 320.0));
+
+        let drop_from = |height: f32, velocity: Vec2| {
+            let mut body = CatBody::default();
+            let mut position = Vec2::new(0.0, floor + height);
+            body.pick_up(Vec2::ZERO);
+            body.velocity = velocity;
+            body.drop_at(position.y);
+            let mut landing = None;
+            for _ in 0..600 {
+                landing = body.fall(&mut position, floor, bounds, 1.0 / 60.0);
+                assert!(bounds.contains(position), "{position} left the screen");
+                if landing.is_some() {
+                    break;
+                }
+            }
+            (landing, position, body)
+        };
+
+        let (landing, position, body) = drop_from(50.0, Vec2::ZERO);
+        assert_eq!(landing, Some(Landing::Soft));
+        assert_eq!(position, Vec2::new(0.0, floor));
+        assert_eq!(body, CatBody { velocity: Vec2::ZERO, state: BodyState::Grounded });
+
+        let (landing, _, _) = drop_from(BIG_DROP_HEIGHT + 10.0, Vec2::ZERO);
+        assert_eq!(landing, Some(Landing::Hard));
+        // Thrown upwards from low down still counts as a big drop
+        let (landing, _, _) = drop_from(10.0, Vec2::new(0.0, 1200.0));
+        assert_eq!(landing, Some(Landing::Hard));
+
+        // A sideways throw bounces off the edge of the screen
+        let (landing, position, _) = drop_from(300.0, Vec2::new(1500.0, 0.0));
+        assert!(landing.is_some());
+        assert!(position.x < bounds.max.x);
+
+        let library = AnimationLibrary::new();
+        for landing in [Landing::Soft, Landing::Hard] {
+            assert!(library.get(landing.animation().0).is_some());
+        }
+        assert!(library.get("dangle").is_some());
+    }
+
+    #[test]
+    fn test_toy_physics() {
+        use toys::{Toy, ToyKind};
+
+        let bounds = Rect::new(-600.0, -100.0, 600.0, 320.0);
+        // Drops a toy and counts its bounces until it comes to rest
+        let simulate = |kind: ToyKind, velocity: Vec2| {
+            let mut toy = Toy::new(kind);
+            toy.velocity = velocity;
+            let mut position = Vec2::new(0.0, 200.0);
+            let mut bounces = 0;
+            let mut landed_after = None;
+            for frame in 0..1200 {
+                let falling = toy.velocity.y < 0.0;
+                toy.step(&mut position, bounds, 1.0 / 60.0);
+                assert!(bounds.contains(position), "{kind:?} left the screen at {position}");
+                if falling && toy.velocity.y > 0.0 {
+                    bounces += 1;
+                }
+                if position.y == bounds.min.y && landed_after.is_none() {
+                    landed_after = Some(frame);
+                }
+            }
+            (toy, position, bounces, landed_after.unwrap())
+        };
+
+        let (yarn, position, yarn_bounces, yarn_landed) = simulate(ToyKind::Yarn, Vec2::new(400.0, 0.0));
+        assert!(yarn_bounces >= 3);
+        // Rolls to a stop on the floor
+        assert_eq!(yarn.velocity, Vec2::ZERO);
+        assert_eq!(position.y, bounds.min.y);
+
+        let (_, _, box_bounces, _) = simulate(ToyKind::Box, Vec2::ZERO);
+        assert!(box_bounces < yarn_bounces);
+
+        // The feather drifts down more slowly than the ball falls
+        let (_, _, _, feather_landed) = simulate(ToyKind::Feather, Vec2::ZERO);
+        assert!(feather_landed > yarn_landed);
+
+        // The cat loses interest after a while
+        let mut toy = Toy::new(ToyKind::Feather);
+        assert!(toy.is_active());
+        toy.attention = 0.0;
+        assert!(!toy.is_active());
+
+        let library = AnimationLibrary::new();
+        for animation in ["play", "jump", "box_play", "walk", "run"] {
+            assert!(library.get(animation).is_some());
+        }
+        for kind in ToyKind::ALL {
+            assert!(std::path::Path::new("assets").join(kind.image()).exists());
+        }
+    }
+
+    #[test]
+    fn test_random_event_scheduler() {
+        use random_events::{EventEntry, EventScheduler, EventTable, RandomEventKind, golden_cat_bonus};
+        use rng::RngStream;
+
+        assert_eq!(EventTable::load(), EventTable::default());
+
+        let table = EventTable {
+            interval: (10.0, 20.0),
+            events: vec![
+                EventEntry { kind: RandomEventKind::GoldenCat, weight: 3, cooldown: 1000.0 },
+                EventEntry { kind: RandomEventKind::Zoomies, weight: 1, cooldown: 1000.0 },
+                EventEntry { kind: RandomEventKind::SurpriseBox, weight: 0, cooldown: 0.0 },
+            ],
+        };
+
+        // Each event cools down after it happens, and weight 0 never comes up
+        let mut rng = GameRng::new(7);
+        let events = rng.stream(RngStream::Events);
+        let mut scheduler = EventScheduler::new(table.clone());
+        let first = scheduler.pick(events).unwrap();
+        let second = scheduler.pick(events).unwrap();
+        assert_ne!(first, second);
+        assert_eq!(scheduler.pick(events), None);
+
+        // Nothing happens before the interval is up
+        let mut scheduler = EventScheduler::new(table.clone());
+        assert_eq!(scheduler.tick(0.0, events), None);
+        assert!(scheduler.next_in.is_some_and(|next_in| (10.0..=20.0).contains(&next_in)));
+        assert_eq!(scheduler.tick(9.0, events), None);
+
+        // Heavier events come up more often
+        let mut golden = 0;
+        for seed in 0..400 {
+            let mut rng = GameRng::new(seed);
+            if EventScheduler::new(table.clone()).pick(rng.stream(RngStream::Events)) == Some(RandomEventKind::GoldenCat) {
+                golden += 1;
+            }
+        }
+        assert!((240..=360).contains(&golden), "golden cat picked {} times", golden);
+
+        // The same seed gives the same events
+        let run = |seed| {
+            let mut rng = GameRng::new(seed);
+            let mut scheduler = EventScheduler::new(EventTable::default());
+            (0..2000).filter_map(|_| scheduler.tick(1.0, rng.stream(RngStream::Events))).collect::<Vec<_>>()
+        };
+        assert_eq!(run(42), run(42));
+        assert!(!run(42).is_empty());
+
+        assert_eq!(golden_cat_bonus(0), 50);
+        assert_eq!(golden_cat_bonus(10_000), 1000);
+    }
+
+    #[test]
+    fn test_seeded_rng_replays_backgrounds() {
+        use daynight::DayPhase;
+        use rand::Rng;
+        use rng::RngStream;
+
+        let backgrounds = |seed| {
+            let mut rng = GameRng::new(seed);
+            let mut config = background::BackgroundConfig::discover("backgrounds");
+            let stats = game::Statistics::default();
+            (0..12)
+                .filter_map(|_| {
+                    let next = config.next_background(&stats, DayPhase::Day, rng.stream(RngStream::Backgrounds));
+                    next.map(|background| background.folder.clone())
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(backgrounds(3), backgrounds(3));
+        assert!(!backgrounds(3).is_empty());
+        assert_ne!(backgrounds(3), backgrounds(4));
+
+        // Drawing from one stream leaves the others alone
+        let mut quiet = GameRng::new(3);
+        let mut busy = GameRng::new(3);
+        for _ in 0..100 {
+            busy.stream(RngStream::Effects).r#gen::<u32>();
+        }
+        let events = |rng: &mut GameRng| rng.stream(RngStream::Events).r#gen::<u64>();
+        assert_eq!(events(&mut quiet), events(&mut busy));
+        assert_eq!(quiet.seed(), 3);
+
+        // Streams from the same seed differ from each other
+        let mut rng = GameRng::new(3);
+        let first = rng.stream(RngStream::Backgrounds).r#gen::<u64>();
+        assert_ne!(first, rng.stream(RngStream::Ai).r#gen::<u64>());
+
+        // A seed in the config file
+        let config: UserConfig = ron::from_str("(seed: Some(42))").unwrap();
+        assert_eq!(config.seed, Some(42));
+        assert_eq!(UserConfig::default().seed, None);
+    }
+
+    #[test]
+    fn test_action_bindings_and_rebinding() {
+        use controls::{Action, ActionState, Binding, InputMap, Rebinding, capture_binding_system};
+
+        // The defaults don't clash, though the runner's jump shares the
+        // left mouse button with petting since they never fire together
+        let map = InputMap::default();
+        for action in Action::ALL {
+            for binding in map.bindings(action) {
+                assert_eq!(map.conflict(action, binding), None, "{:?} clashes on {:?}", action, binding);
+            }
+        }
+        assert!(map.bindings(Action::Jump).contains(&Binding::Mouse(MouseButton::Left)));
+        assert_eq!(map.describe(Action::ToggleDebug), "D / Pad Select");
+
+        // Taken bindings are refused, naming who has them
+        let mut map = InputMap::default();
+        assert_eq!(map.rebind(Action::Pause, Binding::Key(KeyCode::KeyD)), Err(Action::ToggleDebug));
+        assert_eq!(map.rebind(Action::LaserChase, Binding::Mouse(MouseButton::Right)), Err(Action::Carry));
+        // A new key replaces the keyboard bindings and keeps the gamepad one
+        map.rebind(Action::ToggleDebug, Binding::Key(KeyCode::F3)).unwrap();
+        assert_eq!(map.describe(Action::ToggleDebug), "F3 / Pad Select");
+        map.rebind(Action::Pause, Binding::Key(KeyCode::KeyD)).unwrap();
+
+        // Saved with the config, and actions left out of the file keep their defaults
+        let config: UserConfig = ron::from_str("(controls: { ToggleDebug: [Key(F3)], Pause: [] })").unwrap();
+        assert_eq!(config.controls.bindings(Action::ToggleDebug), vec![Binding::Key(KeyCode::F3)]);
+        assert!(config.controls.bindings(Action::Pause).is_empty());
+        assert_eq!(config.controls.bindings(Action::PlayAnimation(0)), vec![Binding::Key(KeyCode::Digit0)]);
+        let saved = ron::to_string(&UserConfig::default()).unwrap();
+        assert_eq!(ron::from_str::<UserConfig>(&saved).unwrap(), UserConfig::default());
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, ControlsPlugin))
+            .init_resource::<ButtonInput<KeyCode>>()
+            .init_resource::<ButtonInput<MouseButton>>()
+            .insert_resource(UserConfig::default())
+            .add_systems(Update, capture_binding_system);
+        let press = |app: &mut App, key: KeyCode| {
+            app.world_mut().resource_mut::<ButtonInput<KeyCode>>().press(key);
+            app.update();
+            app.world_mut().resource_mut::<ButtonInput<KeyCode>>().clear();
+        };
+        let actions = |app: &App, action: Action| {
+            let state = app.world().resource::<ActionState>();
+            (state.just_pressed(action), state.pressed(action))
+        };
+
+        press(&mut app, KeyCode::KeyD);
+        assert_eq!(actions(&app, Action::ToggleDebug), (true, true));
+        app.update();
+        assert_eq!(actions(&app, Action::ToggleDebug), (false, true));
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().release(KeyCode::KeyD);
+        app.update();
+        assert!(app.world().resource::<ActionState>().just_released(Action::ToggleDebug));
+
+        // While waiting for a new binding nothing fires, and the key pressed is taken
+        app.world_mut().resource_mut::<Rebinding>().action = Some(Action::ToggleDebug);
+        app.update();
+        press(&mut app, KeyCode::KeyK);
+        assert_eq!(actions(&app, Action::ToggleDebug), (false, false));
+        assert_eq!(app.world().resource::<Rebinding>().action, None);
+        assert_eq!(app.world().resource::<UserConfig>().controls.describe(Action::ToggleDebug), "K / Pad Select");
+        // Still holding the new key isn't a fresh press
+        app.update();
+        assert_eq!(actions(&app, Action::ToggleDebug), (false, true));
+
+        // A clash is reported and nothing changes
+        app.world_mut().resource_mut::<ButtonInput<KeyCode>>().release_all();
+        app.world_mut().resource_mut::<Rebinding>().action = Some(Action::ToggleDebug);
+        app.update();
+        press(&mut app, KeyCode::KeyN);
+        let rebinding = app.world().resource::<Rebinding>();
+        assert_eq!(rebinding.conflict, Some((Binding::Key(KeyCode::KeyN), Action::NextBackground)));
+        assert_eq!(app.world().resource::<UserConfig>().controls.describe(Action::ToggleDebug), "K / Pad Select");
+    }
 }
@@ -0,0 +1,377 @@
+use bevy::asset::io::file::FileAssetReader;
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::animation::AnimationState;
+use crate::audio::PlaySound;
+use crate::game::{AnimatedCat, ClickCounter, ClicksEarned};
+use crate::rng::{GameRng, RngStream};
+use crate::rotation::OnMilestone;
+use crate::settings::UserConfig;
+use crate::state::AppState;
+use crate::theme::ActiveTheme;
+
+const EFFECTS_FILE: &str = "particles/effects.ron";
+// Upper limit on live particles across every emitter
+const MAX_PARTICLES: usize = 400;
+// Ambient effects start just above the top of the design area
+const AMBIENT_POSITION: Vec3 = Vec3::new(0.0, 420.0, 0.0);
+
+/// One particle effect from `assets/particles/effects.ron`. Ranges are
+/// `(min, max)` and picked at random per particle; curves are keyframes spread
+/// evenly over a particle's life.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct EmitterDef {
+    /// Image path relative to `assets/`, or a plain square when left out
+    pub image: Option<String>,
+    pub color: [f32; 3],
+    /// Particles per second while the emitter is running
+    pub rate: f32,
+    /// Particles fired at once by a one-shot burst
+    pub burst: u32,
+    pub lifetime: (f32, f32),
+    pub velocity_x: (f32, f32),
+    pub velocity_y: (f32, f32),
+    /// Vertical acceleration, negative pulls down
+    pub gravity: f32,
+    /// Size in world units at a scale of 1
+    pub size: f32,
+    pub scale: Vec<f32>,
+    pub alpha: Vec<f32>,
+    /// Half size of the area particles appear in, around the emitter
+    pub area: (f32, f32),
+    /// Where the emitter sits relative to what it is attached to
+    pub offset: (f32, f32),
+    pub z: f32,
+}
+
+impl Default for EmitterDef {
+    fn default() -> Self {
+        Self {
+            image: None,
+            color: [1.0, 1.0, 1.0],
+            rate: 0.0,
+            burst: 0,
+            lifetime: (1.0, 1.0),
+            velocity_x: (0.0, 0.0),
+            velocity_y: (0.0, 0.0),
+            gravity: 0.0,
+            size: 8.0,
+            scale: vec![1.0],
+            alpha: vec![1.0, 0.0],
+            area: (0.0, 0.0),
+            offset: (0.0, 0.0),
+            z: 5.0,
+        }
+    }
+}
+
+/// Linear interpolation through keyframes spread evenly over `t` in 0..=1.
+pub fn sample_curve(keys: &[f32], t: f32) -> f32 {
+    match keys {
+        [] => 1.0,
+        [only] => *only,
+        _ => {
+            let position = t.clamp(0.0, 1.0) * (keys.len() - 1) as f32;
+            let index = (position.floor() as usize).min(keys.len() - 2);
+            let fraction = position - index as f32;
+            keys[index] + (keys[index + 1] - keys[index]) * fraction
+        }
+    }
+}
+
+fn pick(rng: &mut impl Rng, (min, max): (f32, f32)) -> f32 {
+    if max > min { rng.gen_range(min..=max) } else { min }
+}
+
+/// Every effect known to the game, by name.
+#[derive(Resource, Default)]
+pub struct ParticleLibrary {
+    effects: Vec<(String, EmitterDef)>,
+}
+
+impl ParticleLibrary {
+    pub fn new(effects: BTreeMap<String, EmitterDef>) -> Self {
+        Self { effects: effects.into_iter().collect() }
+    }
+
+    pub fn load() -> Self {
+        let path = FileAssetReader::get_base_path().join("assets").join(EFFECTS_FILE);
+        let parsed = fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|contents| ron::from_str(&contents).map_err(|err| err.to_string()));
+        match parsed {
+            Ok(effects) => Self::new(effects),
+            Err(err) => {
+                println!("Could not load {}: {}", EFFECTS_FILE, err);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.effects.iter().position(|(effect, _)| effect == name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&EmitterDef> {
+        self.index_of(name).map(|index| &self.effects[index].1)
+    }
+
+    /// Every image used by an effect, each listed once.
+    pub fn images(&self) -> Vec<&str> {
+        let mut images: Vec<&str> = self.effects.iter().filter_map(|(_, def)| def.image.as_deref()).collect();
+        images.sort();
+        images.dedup();
+        images
+    }
+}
+
+/// Fire a one-shot burst of an effect at a world position.
+#[derive(Event)]
+pub struct ParticleBurst {
+    pub effect: String,
+    pub position: Vec3,
+}
+
+/// Keeps emitting an effect at this entity's position while `enabled`.
+#[derive(Component)]
+pub struct ParticleEmitter {
+    pub effect: String,
+    pub enabled: bool,
+    accumulator: f32,
+}
+
+impl ParticleEmitter {
+    pub fn new(effect: &str) -> Self {
+        Self { effect: effect.to_string(), enabled: true, accumulator: 0.0 }
+    }
+}
+
+/// Only lets the emitter run while its parent cat plays this animation.
+#[derive(Component)]
+pub struct EmitWhileAnimating(pub String);
+
+/// Emitter for the active theme's ambient effect.
+#[derive(Component)]
+pub struct AmbientEmitter;
+
+#[derive(Component)]
+pub struct Particle {
+    effect: usize,
+    velocity: Vec2,
+    age: f32,
+    lifetime: f32,
+}
+
+pub struct ParticlePlugin;
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ParticleLibrary::load())
+            .add_event::<ParticleBurst>()
+            .add_systems(Update, (
+                attach_cat_emitters_system,
+                ambient_emitter_system,
+                milestone_sparkles_system.run_if(in_state(AppState::Playing)),
+                animation_emitters_system,
+                emit_particles_system,
+                update_particles_system,
+            ).chain());
+    }
+}
+
+/// Gives a newly spawned cat its dust and Zzz emitters.
+pub fn attach_cat_emitters_system(
+    mut commands: Commands,
+    library: Res<ParticleLibrary>,
+    cats: Query<Entity, Added<AnimatedCat>>,
+) {
+    for cat in &cats {
+        commands.entity(cat).with_children(|parent| {
+            for (effect, animation) in [("dust", "run"), ("zzz", "sleep")] {
+                let offset = library.get(effect).map(|def| def.offset).unwrap_or_default();
+                parent.spawn((
+                    SpatialBundle::from_transform(Transform::from_xyz(offset.0, offset.1, 0.0)),
+                    ParticleEmitter { enabled: false, ..ParticleEmitter::new(effect) },
+                    EmitWhileAnimating(animation.to_string()),
+                ));
+            }
+        });
+    }
+}
+
+/// Swaps the ambient emitter when the theme changes.
+pub fn ambient_emitter_system(
+    mut commands: Commands,
+    theme: Res<ActiveTheme>,
+    emitters: Query<Entity, With<AmbientEmitter>>,
+) {
+    if !theme.is_changed() {
+        return;
+    }
+    for entity in &emitters {
+        commands.entity(entity).despawn_recursive();
+    }
+    if let Some(effect) = &theme.0.particles {
+        commands.spawn((
+            SpatialBundle::from_transform(Transform::from_translation(AMBIENT_POSITION)),
+            ParticleEmitter::new(effect),
+            AmbientEmitter,
+        ));
+    }
+}
+
+/// Sparkles (and their sound) around the cat whenever the click count passes a milestone.
+pub fn milestone_sparkles_system(
+    counter: Res<ClickCounter>,
+    cats: Query<&GlobalTransform, With<AnimatedCat>>,
+    mut bursts: EventWriter<ParticleBurst>,
+    mut sounds: EventWriter<PlaySound>,
+    mut earned: EventReader<ClicksEarned>,
+) {
+    // Only clicks earned this frame count, so loading a profile doesn't earn a milestone
+    let amount: u32 = earned.read().map(|event| event.amount).sum();
+    if amount == 0 {
+        return;
+    }
+    let before = counter.0.saturating_sub(amount);
+    if OnMilestone::milestones_reached(counter.0) > OnMilestone::milestones_reached(before)
+        && let Ok(cat) = cats.get_single()
+    {
+        bursts.send(ParticleBurst { effect: "sparkles".to_string(), position: cat.translation() });
+        sounds.send(PlaySound("sparkle".to_string()));
+    }
+}
+
+pub fn animation_emitters_system(
+    cats: Query<&AnimationState, With<AnimatedCat>>,
+    mut emitters: Query<(&mut ParticleEmitter, &EmitWhileAnimating, &Parent)>,
+) {
+    for (mut emitter, animation, parent) in &mut emitters {
+        let playing = cats
+            .get(parent.get())
+            .is_ok_and(|state| state.current_animation == animation.0);
+        if emitter.enabled != playing {
+            emitter.enabled = playing;
+        }
+    }
+}
+
+fn spawn_particle(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    rng: &mut impl Rng,
+    index: usize,
+    def: &EmitterDef,
+    position: Vec3,
+) {
+    let [r, g, b] = def.color;
+    let offset = Vec2::new(pick(rng, (-def.area.0, def.area.0)), pick(rng, (-def.area.1, def.area.1)));
+    let mut sprite = SpriteBundle {
+        transform: Transform::from_translation((position.xy() + offset).extend(def.z))
+            .with_scale(Vec3::splat(sample_curve(&def.scale, 0.0))),
+        sprite: Sprite {
+            color: Color::srgba(r, g, b, sample_curve(&def.alpha, 0.0)),
+            custom_size: Some(Vec2::splat(def.size)),
+            ..default()
+        },
+        ..default()
+    };
+    if let Some(image) = &def.image {
+        sprite.texture = asset_server.load(image.clone());
+    }
+    commands.spawn((
+        sprite,
+        Particle {
+            effect: index,
+            velocity: Vec2::new(pick(rng, def.velocity_x), pick(rng, def.velocity_y)),
+            age: 0.0,
+            lifetime: pick(rng, def.lifetime).max(0.01),
+        },
+    ));
+}
+
+/// Spawns particles for bursts and running emitters, within the global cap.
+#[allow(clippy::too_many_arguments)]
+pub fn emit_particles_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    library: Res<ParticleLibrary>,
+    config: Res<UserConfig>,
+    mut bursts: EventReader<ParticleBurst>,
+    mut emitters: Query<(&mut ParticleEmitter, &GlobalTransform)>,
+    particles: Query<(), With<Particle>>,
+    mut rng: ResMut<GameRng>,
+) {
+    if config.reduce_motion {
+        bursts.clear();
+        return;
+    }
+    let rng = rng.stream(RngStream::Effects);
+    let mut budget = MAX_PARTICLES.saturating_sub(particles.iter().count());
+
+    for burst in bursts.read() {
+        let Some(index) = library.index_of(&burst.effect) else {
+            println!("Unknown particle effect '{}'", burst.effect);
+            continue;
+        };
+        let def = &library.effects[index].1;
+        for _ in 0..(def.burst as usize).min(budget) {
+            spawn_particle(&mut commands, &asset_server, rng, index, def, burst.position);
+            budget -= 1;
+        }
+    }
+
+    for (mut emitter, transform) in &mut emitters {
+        if !emitter.enabled {
+            emitter.accumulator = 0.0;
+            continue;
+        }
+        let Some(index) = library.index_of(&emitter.effect) else {
+            continue;
+        };
+        let def = &library.effects[index].1;
+        emitter.accumulator += def.rate * time.delta_seconds();
+        while emitter.accumulator >= 1.0 && budget > 0 {
+            emitter.accumulator -= 1.0;
+            spawn_particle(&mut commands, &asset_server, rng, index, def, transform.translation());
+            budget -= 1;
+        }
+        // Particles refused for lack of budget are dropped rather than saved up,
+        // which would release them all at once when space frees up
+        if budget == 0 {
+            emitter.accumulator = emitter.accumulator.min(1.0);
+        }
+    }
+}
+
+/// Moves, fades and scales particles, and removes them at the end of their life.
+pub fn update_particles_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    library: Res<ParticleLibrary>,
+    mut particles: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+) {
+    let delta = time.delta_seconds();
+    for (entity, mut particle, mut transform, mut sprite) in &mut particles {
+        particle.age += delta;
+        if particle.age >= particle.lifetime {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let Some((_, def)) = library.effects.get(particle.effect) else {
+            continue;
+        };
+        particle.velocity.y += def.gravity * delta;
+        transform.translation += (particle.velocity * delta).extend(0.0);
+
+        let t = particle.age / particle.lifetime;
+        transform.scale = Vec3::splat(sample_curve(&def.scale, t));
+        sprite.color.set_alpha(sample_curve(&def.alpha, t));
+    }
+}
@@ -0,0 +1,226 @@
+use bevy::prelude::*;
+
+use crate::animation::{AnimationLibrary, AnimationState};
+use crate::background::{ActiveBackground, BackgroundScale};
+use crate::controls::{Action, ActionState};
+use crate::game::{AnimatedCat, RestingAnimation, cat_contains, cat_size, cursor_world_position};
+use crate::state::{AppState, Minigame};
+
+/// Downwards acceleration in world units per second squared
+pub const GRAVITY: f32 = 2400.0;
+/// Falls from higher than this above the floor end in a hard landing
+pub const BIG_DROP_HEIGHT: f32 = 250.0;
+/// Fastest anything can be thrown, so a flick of the mouse doesn't fling it away
+pub const MAX_THROW_SPEED: f32 = 1500.0;
+// Share of its sideways speed the cat keeps after hitting the edge of the screen
+const WALL_BOUNCE: f32 = 0.5;
+/// How quickly the carry velocity follows the mouse, per second
+pub const CARRY_SMOOTHING: f32 = 20.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BodyState {
+    #[default]
+    Grounded,
+    /// Following the cursor, `offset` away from it
+    Held { offset: Vec2 },
+    /// Dropped or thrown, remembering the highest point of the fall
+    Falling { peak: f32 },
+}
+
+/// Lets the cat be picked up, carried and dropped.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub struct CatBody {
+    pub velocity: Vec2,
+    pub state: BodyState,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Landing {
+    Soft,
+    /// After a big drop
+    Hard,
+}
+
+impl Landing {
+    /// The animation played on landing and for how long.
+    pub fn animation(self) -> (&'static str, f32) {
+        match self {
+            Landing::Soft => ("jump", 0.8),
+            Landing::Hard => ("damage", 1.8),
+        }
+    }
+}
+
+impl CatBody {
+    pub fn is_grounded(&self) -> bool {
+        self.state == BodyState::Grounded
+    }
+
+    pub fn pick_up(&mut self, offset: Vec2) {
+        self.velocity = Vec2::ZERO;
+        self.state = BodyState::Held { offset };
+    }
+
+    /// Lets go of the cat at `y`, keeping its carry velocity as a throw.
+    pub fn drop_at(&mut self, y: f32) {
+        self.velocity = self.velocity.clamp_length_max(MAX_THROW_SPEED);
+        self.state = BodyState::Falling { peak: y };
+    }
+
+    /// Moves a falling cat on by `seconds`, bouncing off the sides of `bounds`.
+    /// Returns how it landed once it reaches the floor.
+    pub fn fall(&mut self, position: &mut Vec2, floor: f32, bounds: Rect, seconds: f32) -> Option<Landing> {
+        let BodyState::Falling { peak } = &mut self.state else {
+            return None;
+        };
+        self.velocity.y -= GRAVITY * seconds;
+        *position += self.velocity * seconds;
+        *peak = peak.max(position.y);
+
+        if position.x < bounds.min.x || position.x > bounds.max.x {
+            position.x = position.x.clamp(bounds.min.x, bounds.max.x);
+            self.velocity.x *= -WALL_BOUNCE;
+        }
+        if position.y > bounds.max.y {
+            position.y = bounds.max.y;
+            self.velocity.y = self.velocity.y.min(0.0);
+        }
+        if position.y > floor {
+            return None;
+        }
+
+        let landing = if *peak - floor >= BIG_DROP_HEIGHT { Landing::Hard } else { Landing::Soft };
+        position.y = floor;
+        self.velocity = Vec2::ZERO;
+        self.state = BodyState::Grounded;
+        Some(landing)
+    }
+}
+
+/// The part of the world the camera shows.
+pub fn visible_world_rect(camera: &Camera, camera_transform: &GlobalTransform) -> Option<Rect> {
+    let size = camera.logical_viewport_size()?;
+    let top_left = camera.viewport_to_world_2d(camera_transform, Vec2::ZERO)?;
+    let bottom_right = camera.viewport_to_world_2d(camera_transform, size)?;
+    Some(Rect::from_corners(top_left, bottom_right))
+}
+
+/// Where the centre of something `half_size` big may go: inside the screen
+/// and not below `floor`.
+pub fn body_bounds(visible: Rect, half_size: Vec2, floor: f32) -> Rect {
+    let min = Vec2::new(visible.min.x + half_size.x, floor);
+    let max = Vec2::new(visible.max.x - half_size.x, visible.max.y - half_size.y);
+    Rect::from_corners(min, max.max(min))
+}
+
+pub struct PickupPlugin;
+
+impl Plugin for PickupPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnExit(Minigame::None), land_cat)
+            .add_systems(Update, (
+                carry_cat_system,
+                fall_system,
+            ).chain().run_if(in_state(AppState::Playing).and_then(in_state(Minigame::None))));
+    }
+}
+
+/// The carry action, the right mouse button by default, picks the cat up. It
+/// dangles under the cursor until the button is let go.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn carry_cat_system(
+    time: Res<Time>,
+    actions: Res<ActionState>,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    active: Res<ActiveBackground>,
+    scale: Res<BackgroundScale>,
+    mut cats: Query<(&mut Transform, &GlobalTransform, &Sprite, &mut CatBody, &mut AnimationState), With<AnimatedCat>>,
+    animation_library: Res<AnimationLibrary>,
+) {
+    let Ok((mut transform, global_transform, sprite, mut body, mut state)) = cats.get_single_mut() else {
+        return;
+    };
+    let (camera, camera_transform) = cameras.single();
+    let cursor = cursor_world_position(windows.single(), camera, camera_transform);
+
+    match body.state {
+        BodyState::Held { offset } => {
+            if !actions.pressed(Action::Carry) {
+                body.drop_at(transform.translation.y);
+                return;
+            }
+            let (Some(cursor), Some(visible)) = (cursor, visible_world_rect(camera, camera_transform)) else {
+                return;
+            };
+            let half_size = cat_size(sprite, transform.scale) / 2.0;
+            let bounds = body_bounds(visible, half_size, active.floor_y(&scale));
+            let old = transform.translation.xy();
+            let new = (cursor + offset).clamp(bounds.min, bounds.max);
+            transform.translation = new.extend(transform.translation.z);
+
+            // Smoothed, so letting go keeps the swing of the last few frames
+            let seconds = time.delta_seconds();
+            if seconds > 0.0 {
+                let blend = (CARRY_SMOOTHING * seconds).min(1.0);
+                body.velocity = body.velocity.lerp((new - old) / seconds, blend);
+            }
+        }
+        _ if actions.just_pressed(Action::Carry) => {
+            if let Some(cursor) = cursor
+                && cat_contains(global_transform, sprite, cursor)
+            {
+                body.pick_up(transform.translation.xy() - cursor);
+                state.play_animation("dangle", &animation_library);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Drops the cat under gravity onto the floor of the background, landing with
+/// `jump`, or `damage` from a big drop, before settling down.
+pub fn fall_system(
+    time: Res<Time>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    active: Res<ActiveBackground>,
+    scale: Res<BackgroundScale>,
+    mut cats: Query<(&mut Transform, &Sprite, &mut CatBody, &mut AnimationState), With<AnimatedCat>>,
+    animation_library: Res<AnimationLibrary>,
+    resting: Res<RestingAnimation>,
+) {
+    let Ok((mut transform, sprite, mut body, mut state)) = cats.get_single_mut() else {
+        return;
+    };
+    if !matches!(body.state, BodyState::Falling { .. }) {
+        return;
+    }
+    let (camera, camera_transform) = cameras.single();
+    let Some(visible) = visible_world_rect(camera, camera_transform) else {
+        return;
+    };
+    let floor = active.floor_y(&scale);
+    let bounds = body_bounds(visible, cat_size(sprite, transform.scale) / 2.0, floor);
+    let mut position = transform.translation.xy();
+    let landing = body.fall(&mut position, floor, bounds, time.delta_seconds());
+    transform.translation = position.extend(transform.translation.z);
+
+    if let Some(landing) = landing {
+        let (animation, seconds) = landing.animation();
+        state.play_animation_then_return(animation, &resting.0, seconds, &animation_library);
+    }
+}
+
+/// Puts the cat straight down when a mini-game takes over.
+fn land_cat(
+    active: Res<ActiveBackground>,
+    scale: Res<BackgroundScale>,
+    mut cats: Query<(&mut Transform, &mut CatBody), With<AnimatedCat>>,
+) {
+    for (mut transform, mut body) in &mut cats {
+        if !body.is_grounded() {
+            transform.translation.y = active.floor_y(&scale);
+            *body = CatBody::default();
+        }
+    }
+}
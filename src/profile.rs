@@ -0,0 +1,662 @@
+use bevy::app::AppExit;
+use bevy::input::ButtonState;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::prelude::*;
+use serde::{Deserialize, Deserializer, Serialize, de::DeserializeOwned};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::audio::PlaySound;
+use crate::feeding::{CatNeeds, Inventory};
+use crate::game::{ClickCounter, Statistics};
+use crate::rotation::RotationSetting;
+use crate::share_code;
+use crate::state::{AppState, InGame};
+use crate::theme::ThemeChoice;
+use crate::transition::TransitionStyle;
+
+const SAVE_FILE: &str = "save.ron";
+const SETTINGS_FILE: &str = "settings.ron";
+const STATS_FILE: &str = "stats.ron";
+const INDEX_FILE: &str = "profiles.ron";
+const EXPORT_FILE: &str = "export.txt";
+const IMPORT_FILE: &str = "import.txt";
+pub const MAX_NAME_LEN: usize = 24;
+// Long enough to hold a pasted share code
+const MAX_INPUT_LEN: usize = 512;
+const AUTOSAVE_SECONDS: f32 = 10.0;
+
+/// Progress that belongs to a single profile.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct SaveData {
+    pub clicks: u32,
+    pub inventory: Inventory,
+    pub needs: CatNeeds,
+}
+
+/// Per-profile preferences, stored next to the save but in their own file.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(from = "StoredProfileSettings")]
+pub struct ProfileSettings {
+    pub background_rotation: RotationSetting,
+    pub background_transition: TransitionStyle,
+    pub theme: ThemeChoice,
+}
+
+/// `ProfileSettings` as found on disk, which may still be in an older format.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct StoredProfileSettings {
+    #[serde(deserialize_with = "present")]
+    background_rotation: Option<RotationSetting>,
+    /// Saves from before rotation policies only had a click interval
+    #[serde(deserialize_with = "present")]
+    background_every_clicks: Option<u32>,
+    background_transition: TransitionStyle,
+    theme: ThemeChoice,
+}
+
+/// Reads a field written without `Some(..)`, leaving `None` for when it is missing.
+fn present<'de, D: Deserializer<'de>, T: Deserialize<'de>>(deserializer: D) -> Result<Option<T>, D::Error> {
+    T::deserialize(deserializer).map(Some)
+}
+
+impl From<StoredProfileSettings> for ProfileSettings {
+    fn from(stored: StoredProfileSettings) -> Self {
+        let background_rotation = match (stored.background_rotation, stored.background_every_clicks) {
+            (Some(rotation), _) => rotation,
+            (None, Some(clicks)) => RotationSetting::EveryClicks(clicks),
+            (None, None) => RotationSetting::default(),
+        };
+        Self {
+            background_rotation,
+            background_transition: stored.background_transition,
+            theme: stored.theme,
+        }
+    }
+}
+
+/// Everything that gets loaded when a profile becomes active.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct Profile {
+    pub save: SaveData,
+    pub settings: ProfileSettings,
+    pub stats: Statistics,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct ProfileIndex {
+    last_active: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum ProfileError {
+    InvalidName(String),
+    AlreadyExists(String),
+    NotFound(String),
+    Io(std::io::Error),
+    Parse(String),
+}
+
+impl fmt::Display for ProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProfileError::InvalidName(name) => write!(f, "invalid profile name '{}'", name),
+            ProfileError::AlreadyExists(name) => write!(f, "profile '{}' already exists", name),
+            ProfileError::NotFound(name) => write!(f, "profile '{}' not found", name),
+            ProfileError::Io(err) => write!(f, "file error: {}", err),
+            ProfileError::Parse(err) => write!(f, "could not read profile: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ProfileError {}
+
+impl From<std::io::Error> for ProfileError {
+    fn from(err: std::io::Error) -> Self {
+        ProfileError::Io(err)
+    }
+}
+
+/// On-disk profile storage. Each profile is a directory under `root`
+/// named after the profile, holding its save, settings and statistics.
+pub struct ProfileStore {
+    root: PathBuf,
+}
+
+impl Default for ProfileStore {
+    fn default() -> Self {
+        Self::new("saves")
+    }
+}
+
+impl ProfileStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn validate_name(name: &str) -> Result<(), ProfileError> {
+        let valid = !name.trim().is_empty()
+            && name.trim() == name
+            && name.chars().count() <= MAX_NAME_LEN
+            && name.chars().all(|c| c.is_alphanumeric() || c == ' ' || c == '-' || c == '_');
+        if valid {
+            Ok(())
+        } else {
+            Err(ProfileError::InvalidName(name.to_string()))
+        }
+    }
+
+    fn profile_dir(&self, name: &str) -> PathBuf {
+        self.root.join(name)
+    }
+
+    pub fn exists(&self, name: &str) -> bool {
+        self.profile_dir(name).is_dir()
+    }
+
+    pub fn list(&self) -> Result<Vec<String>, ProfileError> {
+        if !self.root.exists() {
+            return Ok(Vec::new());
+        }
+        let mut names: Vec<String> = fs::read_dir(&self.root)?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| Self::validate_name(name).is_ok())
+            .collect();
+        names.sort();
+        Ok(names)
+    }
+
+    pub fn create(&self, name: &str) -> Result<(), ProfileError> {
+        Self::validate_name(name)?;
+        if self.exists(name) {
+            return Err(ProfileError::AlreadyExists(name.to_string()));
+        }
+        fs::create_dir_all(self.profile_dir(name))?;
+        self.save(name, &Profile::default())
+    }
+
+    pub fn rename(&self, old: &str, new: &str) -> Result<(), ProfileError> {
+        Self::validate_name(new)?;
+        if !self.exists(old) {
+            return Err(ProfileError::NotFound(old.to_string()));
+        }
+        if self.exists(new) {
+            return Err(ProfileError::AlreadyExists(new.to_string()));
+        }
+        let was_active = self.last_active().as_deref() == Some(old);
+        fs::rename(self.profile_dir(old), self.profile_dir(new))?;
+        if was_active {
+            self.set_last_active(Some(new))?;
+        }
+        Ok(())
+    }
+
+    pub fn duplicate(&self, source: &str, new: &str) -> Result<(), ProfileError> {
+        let profile = self.load(source)?;
+        self.create(new)?;
+        self.save(new, &profile)
+    }
+
+    pub fn delete(&self, name: &str) -> Result<(), ProfileError> {
+        if !self.exists(name) {
+            return Err(ProfileError::NotFound(name.to_string()));
+        }
+        let was_active = self.last_active().as_deref() == Some(name);
+        fs::remove_dir_all(self.profile_dir(name))?;
+        if was_active {
+            self.set_last_active(None)?;
+        }
+        Ok(())
+    }
+
+    /// Loads a profile. Files that are missing fall back to their defaults.
+    pub fn load(&self, name: &str) -> Result<Profile, ProfileError> {
+        if !self.exists(name) {
+            return Err(ProfileError::NotFound(name.to_string()));
+        }
+        let dir = self.profile_dir(name);
+        Ok(Profile {
+            save: read_ron_or_default(&dir.join(SAVE_FILE))?,
+            settings: read_ron_or_default(&dir.join(SETTINGS_FILE))?,
+            stats: read_ron_or_default(&dir.join(STATS_FILE))?,
+        })
+    }
+
+    pub fn save(&self, name: &str, profile: &Profile) -> Result<(), ProfileError> {
+        let dir = self.profile_dir(name);
+        fs::create_dir_all(&dir)?;
+        write_ron(&dir.join(SAVE_FILE), &profile.save)?;
+        write_ron(&dir.join(SETTINGS_FILE), &profile.settings)?;
+        write_ron(&dir.join(STATS_FILE), &profile.stats)?;
+        Ok(())
+    }
+
+    pub fn last_active(&self) -> Option<String> {
+        let index: ProfileIndex = read_ron_or_default(&self.root.join(INDEX_FILE)).ok()?;
+        index.last_active.filter(|name| self.exists(name))
+    }
+
+    pub fn set_last_active(&self, name: Option<&str>) -> Result<(), ProfileError> {
+        fs::create_dir_all(&self.root)?;
+        let index = ProfileIndex { last_active: name.map(str::to_string) };
+        write_ron(&self.root.join(INDEX_FILE), &index)
+    }
+}
+
+pub(crate) fn read_ron_or_default<T: DeserializeOwned + Default>(path: &Path) -> Result<T, ProfileError> {
+    match fs::read_to_string(path) {
+        Ok(contents) => ron::from_str(&contents)
+            .map_err(|err| ProfileError::Parse(format!("{}: {}", path.display(), err))),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(T::default()),
+        Err(err) => Err(err.into()),
+    }
+}
+
+pub(crate) fn write_ron<T: Serialize>(path: &Path, value: &T) -> Result<(), ProfileError> {
+    let contents = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+        .map_err(|err| ProfileError::Parse(err.to_string()))?;
+    fs::write(path, contents)?;
+    Ok(())
+}
+
+#[derive(Resource, Default)]
+pub struct ProfileManager {
+    pub store: ProfileStore,
+    pub active: Option<String>,
+}
+
+#[derive(Resource, Default)]
+pub struct ProfilePickerState {
+    pub selected: Option<String>,
+    pub name_input: String,
+    pub status: String,
+    rebuild: bool,
+}
+
+#[derive(Component)]
+pub struct ProfilePicker;
+
+#[derive(Component)]
+pub struct ProfileNameText;
+
+#[derive(Component, Clone)]
+pub enum ProfileButton {
+    Select(String),
+    Play,
+    Create,
+    Rename,
+    Duplicate,
+    Delete,
+    Export,
+    Import,
+}
+
+#[derive(Resource)]
+pub struct AutosaveTimer(Timer);
+
+pub struct ProfilePlugin;
+
+impl Plugin for ProfilePlugin {
+    fn build(&self, app: &mut App) {
+        let manager = ProfileManager::default();
+        let picker = ProfilePickerState {
+            selected: manager.store.last_active(),
+            ..default()
+        };
+
+        app.insert_resource(manager)
+            .insert_resource(picker)
+            .insert_resource(ProfileSettings::default())
+            .insert_resource(AutosaveTimer(Timer::from_seconds(AUTOSAVE_SECONDS, TimerMode::Repeating)))
+            .add_systems(Update, (
+                profile_name_input_system,
+                profile_button_system,
+                refresh_profile_picker_system,
+            ).chain().run_if(in_state(AppState::MainMenu)))
+            .add_systems(Update, autosave_profile_system.run_if(in_state(InGame)))
+            .add_systems(Last, save_on_exit_system);
+    }
+}
+
+/// Collects typed characters into the name field of the picker.
+pub fn profile_name_input_system(
+    mut events: EventReader<KeyboardInput>,
+    mut picker: ResMut<ProfilePickerState>,
+) {
+    picker.bypass_change_detection().rebuild = false;
+    for event in events.read() {
+        if event.state != ButtonState::Pressed {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Backspace => {
+                picker.name_input.pop();
+            }
+            Key::Space if picker.name_input.chars().count() < MAX_INPUT_LEN => {
+                picker.name_input.push(' ');
+            }
+            Key::Character(chars) => {
+                for c in chars.chars() {
+                    if (c.is_alphanumeric() || c == '-' || c == '_' || c == ':')
+                        && picker.name_input.chars().count() < MAX_INPUT_LEN
+                    {
+                        picker.name_input.push(c);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn profile_button_system(
+    buttons: Query<(&Interaction, &ProfileButton), Changed<Interaction>>,
+    mut manager: ResMut<ProfileManager>,
+    mut picker: ResMut<ProfilePickerState>,
+    mut counter: ResMut<ClickCounter>,
+    mut inventory: ResMut<Inventory>,
+    mut needs: ResMut<CatNeeds>,
+    mut stats: ResMut<Statistics>,
+    mut settings: ResMut<ProfileSettings>,
+    mut sounds: EventWriter<PlaySound>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    for (interaction, button) in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        sounds.send(PlaySound("ui_click".to_string()));
+
+        let new_name = picker.name_input.trim().to_string();
+        let selected = picker.selected.clone();
+        let result: Result<String, String> = match (button, selected.as_deref()) {
+            (ProfileButton::Select(name), _) => {
+                picker.selected = Some(name.clone());
+                Ok(String::new())
+            }
+            (ProfileButton::Import, _) => import_from_picker(&manager.store, &new_name).map(|name| {
+                picker.selected = Some(name.clone());
+                format!("Imported '{}'", name)
+            }).map_err(|err| err.to_string()),
+            (ProfileButton::Create, _) => manager.store.create(&new_name).map(|_| {
+                picker.selected = Some(new_name.clone());
+                format!("Created '{}'", new_name)
+            }).map_err(|err| err.to_string()),
+            (ProfileButton::Play, Some(name)) => manager.store.load(name).map(|profile| {
+                counter.0 = profile.save.clicks;
+                *inventory = profile.save.inventory;
+                *needs = profile.save.needs;
+                *stats = profile.stats;
+                *settings = profile.settings;
+                String::new()
+            }).map_err(|err| err.to_string()),
+            (ProfileButton::Rename, Some(name)) => manager.store.rename(name, &new_name).map(|_| {
+                picker.selected = Some(new_name.clone());
+                format!("Renamed '{}' to '{}'", name, new_name)
+            }).map_err(|err| err.to_string()),
+            (ProfileButton::Duplicate, Some(name)) => manager.store.duplicate(name, &new_name).map(|_| {
+                picker.selected = Some(new_name.clone());
+                format!("Duplicated '{}' as '{}'", name, new_name)
+            }).map_err(|err| err.to_string()),
+            (ProfileButton::Export, Some(name)) => export_from_picker(&manager.store, name)
+                .map(|path| format!("Share code for '{}' written to {}", name, path.display()))
+                .map_err(|err| err.to_string()),
+            (ProfileButton::Delete, Some(name)) => manager.store.delete(name).map(|_| {
+                picker.selected = None;
+                format!("Deleted '{}'", name)
+            }).map_err(|err| err.to_string()),
+            (_, None) => Ok("Select a profile first".to_string()),
+        };
+
+        match result {
+            Ok(status) => {
+                if matches!(button, ProfileButton::Play) {
+                    let name = selected.unwrap_or_default();
+                    println!("Loaded profile: {}", name);
+                    if let Err(err) = manager.store.set_last_active(Some(&name)) {
+                        println!("Could not remember active profile: {}", err);
+                    }
+                    manager.active = Some(name);
+                    next_state.set(AppState::Playing);
+                } else if !matches!(button, ProfileButton::Select(_)) {
+                    picker.name_input.clear();
+                }
+                picker.status = status;
+            }
+            Err(err) => picker.status = err,
+        }
+        picker.rebuild = true;
+    }
+}
+
+/// Rebuilds the picker whenever its state changes. It is removed when
+/// leaving the main menu.
+pub fn refresh_profile_picker_system(
+    mut commands: Commands,
+    manager: Res<ProfileManager>,
+    picker: Res<ProfilePickerState>,
+    pickers: Query<Entity, With<ProfilePicker>>,
+    mut name_text: Query<&mut Text, With<ProfileNameText>>,
+) {
+    // Already on the way into the game
+    if manager.active.is_some() {
+        return;
+    }
+
+    if !pickers.is_empty() && !picker.is_changed() {
+        return;
+    }
+
+    // Typing only touches the name field, so avoid rebuilding the whole panel
+    if !pickers.is_empty() && !picker.rebuild {
+        if let Ok(mut text) = name_text.get_single_mut() {
+            text.sections[0].value = format!("Name: {}_", picker.name_input);
+        }
+        return;
+    }
+
+    for entity in &pickers {
+        commands.entity(entity).despawn_recursive();
+    }
+    let profiles = manager.store.list().unwrap_or_else(|err| {
+        println!("Could not list profiles: {}", err);
+        Vec::new()
+    });
+    spawn_profile_picker(&mut commands, &profiles, &picker);
+}
+
+fn spawn_profile_picker(commands: &mut Commands, profiles: &[String], picker: &ProfilePickerState) {
+    let text_style = |size: f32| TextStyle {
+        font_size: size,
+        color: Color::WHITE,
+        ..default()
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            z_index: ZIndex::Global(100),
+            ..default()
+        })
+        .insert((ProfilePicker, StateScoped(AppState::MainMenu)))
+        .with_children(|root| {
+            root.spawn(NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(8.0),
+                    padding: UiRect::all(Val::Px(20.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.85)),
+                ..default()
+            })
+            .with_children(|panel| {
+                panel.spawn(TextBundle::from_section("Choose a profile", text_style(32.0)));
+
+                if profiles.is_empty() {
+                    panel.spawn(TextBundle::from_section(
+                        "No profiles yet - type a name and press New",
+                        text_style(18.0),
+                    ));
+                }
+                for name in profiles {
+                    let selected = picker.selected.as_deref() == Some(name.as_str());
+                    spawn_button(panel, name, ProfileButton::Select(name.clone()), selected);
+                }
+
+                panel
+                    .spawn(TextBundle::from_section(
+                        format!("Name: {}_", picker.name_input),
+                        text_style(20.0),
+                    ))
+                    .insert(ProfileNameText);
+
+                panel
+                    .spawn(NodeBundle {
+                        style: Style {
+                            column_gap: Val::Px(8.0),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|row| {
+                        spawn_button(row, "Play", ProfileButton::Play, false);
+                        spawn_button(row, "New", ProfileButton::Create, false);
+                        spawn_button(row, "Rename", ProfileButton::Rename, false);
+                        spawn_button(row, "Duplicate", ProfileButton::Duplicate, false);
+                        spawn_button(row, "Delete", ProfileButton::Delete, false);
+                        spawn_button(row, "Export", ProfileButton::Export, false);
+                        spawn_button(row, "Import", ProfileButton::Import, false);
+                    });
+
+                panel.spawn(TextBundle::from_section(picker.status.clone(), text_style(16.0)));
+            });
+        });
+}
+
+fn spawn_button(parent: &mut ChildBuilder, label: &str, button: ProfileButton, highlighted: bool) {
+    let color = if highlighted {
+        Color::srgb(0.35, 0.55, 0.35)
+    } else {
+        Color::srgb(0.25, 0.25, 0.25)
+    };
+
+    parent
+        .spawn(ButtonBundle {
+            style: Style {
+                padding: UiRect::axes(Val::Px(12.0), Val::Px(6.0)),
+                ..default()
+            },
+            background_color: BackgroundColor(color),
+            ..default()
+        })
+        .insert(button)
+        .with_children(|button| {
+            button.spawn(TextBundle::from_section(
+                label,
+                TextStyle {
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn export_from_picker(store: &ProfileStore, name: &str) -> Result<PathBuf, ProfileError> {
+    let code = share_code::export_profile(store, name)?;
+    let path = store.root().join(EXPORT_FILE);
+    fs::write(&path, &code)?;
+    println!("Share code for '{}': {}", name, code);
+    Ok(path)
+}
+
+/// Imports the code typed into the name field, or the contents of
+/// `saves/import.txt` when the field is empty.
+fn import_from_picker(store: &ProfileStore, typed: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let code = if typed.is_empty() {
+        fs::read_to_string(store.root().join(IMPORT_FILE))?
+    } else {
+        typed.to_string()
+    };
+    share_code::import_profile(store, &code)
+}
+
+/// Collects the live game resources into a profile that can be written to disk.
+pub fn current_profile(
+    counter: &ClickCounter,
+    inventory: &Inventory,
+    needs: &CatNeeds,
+    settings: &ProfileSettings,
+    stats: &Statistics,
+) -> Profile {
+    Profile {
+        save: SaveData { clicks: counter.0, inventory: inventory.clone(), needs: *needs },
+        settings: settings.clone(),
+        stats: stats.clone(),
+    }
+}
+
+pub(crate) fn save_active_profile(
+    manager: &ProfileManager,
+    counter: &ClickCounter,
+    inventory: &Inventory,
+    needs: &CatNeeds,
+    settings: &ProfileSettings,
+    stats: &Statistics,
+) {
+    if let Some(name) = &manager.active {
+        let profile = current_profile(counter, inventory, needs, settings, stats);
+        if let Err(err) = manager.store.save(name, &profile) {
+            println!("Failed to save profile '{}': {}", name, err);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn autosave_profile_system(
+    time: Res<Time>,
+    mut timer: ResMut<AutosaveTimer>,
+    manager: Res<ProfileManager>,
+    counter: Res<ClickCounter>,
+    inventory: Res<Inventory>,
+    needs: Res<CatNeeds>,
+    settings: Res<ProfileSettings>,
+    stats: Res<Statistics>,
+) {
+    if timer.0.tick(time.delta()).just_finished() {
+        save_active_profile(&manager, &counter, &inventory, &needs, &settings, &stats);
+    }
+}
+
+pub fn save_on_exit_system(
+    mut exit_events: EventReader<AppExit>,
+    manager: Res<ProfileManager>,
+    counter: Res<ClickCounter>,
+    inventory: Res<Inventory>,
+    needs: Res<CatNeeds>,
+    settings: Res<ProfileSettings>,
+    stats: Res<Statistics>,
+) {
+    if exit_events.read().next().is_some() {
+        save_active_profile(&manager, &counter, &inventory, &needs, &settings, &stats);
+    }
+}
@@ -0,0 +1,508 @@
+use bevy::asset::io::file::FileAssetReader;
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+use std::fs;
+
+use crate::animation::{Animation, AnimationLibrary, AnimationState};
+use crate::audio::PlaySound;
+use crate::background::{ActiveBackground, BackgroundScale};
+use crate::controls::{Action, ActionState};
+use crate::feeding::{FoodLibrary, Inventory};
+use crate::game::{AnimatedCat, ClickCounter, ClickMultiplier, ClicksEarned, RestingAnimation, cursor_world_position};
+use crate::gesture::{GestureRecognizer, recognize_gestures_system};
+use crate::loading::GameTextures;
+use crate::particles::{ParticleBurst, ParticleEmitter};
+use crate::pickup::visible_world_rect;
+use crate::rng::{GameRng, RngStream};
+use crate::settings::UserConfig;
+use crate::state::{AppState, InGame, Minigame};
+use crate::toys::{Toy, ToyKind, toy_bounds};
+
+const EVENTS_FILE: &str = "events/events.ron";
+/// How long the golden cat takes to cross the screen
+const GOLDEN_CAT_SECONDS: f32 = 4.0;
+/// Smallest golden cat bonus, before the click multiplier
+pub const GOLDEN_CAT_MIN_BONUS: u32 = 50;
+/// The golden cat is worth this share of the clicks already banked, if that is more
+const GOLDEN_CAT_BANK_SHARE: u32 = 10;
+// Highest the golden cat runs above the floor
+const GOLDEN_CAT_MAX_HEIGHT: f32 = 150.0;
+const GOLDEN_CAT_Z: f32 = 5.0;
+const SURPRISE_BOX_SECONDS: f32 = 20.0;
+pub const ZOOMIES_SECONDS: f32 = 30.0;
+const BANNER_SECONDS: f32 = 3.0;
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RandomEventKind {
+    /// A golden cat runs across the screen and pays a bonus if clicked
+    GoldenCat,
+    /// A box drops in with a free food inside
+    SurpriseBox,
+    /// Clicks are worth double for a while
+    Zoomies,
+}
+
+impl RandomEventKind {
+    pub fn announcement(self) -> &'static str {
+        match self {
+            RandomEventKind::GoldenCat => "Golden cat!",
+            RandomEventKind::SurpriseBox => "Surprise box!",
+            RandomEventKind::Zoomies => "Zoomies!",
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct EventEntry {
+    pub kind: RandomEventKind,
+    pub weight: u32,
+    /// Seconds before this event can happen again
+    pub cooldown: f32,
+}
+
+/// `assets/events/events.ron`: how often events happen and how likely each is.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct EventTable {
+    /// Seconds between events, as `(min, max)`
+    pub interval: (f32, f32),
+    pub events: Vec<EventEntry>,
+}
+
+impl Default for EventTable {
+    fn default() -> Self {
+        Self {
+            interval: (60.0, 180.0),
+            events: vec![
+                EventEntry { kind: RandomEventKind::GoldenCat, weight: 5, cooldown: 120.0 },
+                EventEntry { kind: RandomEventKind::SurpriseBox, weight: 3, cooldown: 180.0 },
+                EventEntry { kind: RandomEventKind::Zoomies, weight: 2, cooldown: 300.0 },
+            ],
+        }
+    }
+}
+
+impl EventTable {
+    pub fn load() -> Self {
+        let path = FileAssetReader::get_base_path().join("assets").join(EVENTS_FILE);
+        let parsed = fs::read_to_string(&path)
+            .map_err(|err| err.to_string())
+            .and_then(|contents| ron::from_str(&contents).map_err(|err| err.to_string()));
+        parsed.unwrap_or_else(|err| {
+            println!("Could not load {}: {}", EVENTS_FILE, err);
+            Self::default()
+        })
+    }
+}
+
+/// Decides when the next event happens and which one it is.
+#[derive(Resource)]
+pub struct EventScheduler {
+    pub table: EventTable,
+    /// Seconds until the next event, or `None` before the first one is rolled
+    pub next_in: Option<f32>,
+    /// Seconds left on each entry's cooldown, in table order
+    cooldowns: Vec<f32>,
+}
+
+impl EventScheduler {
+    pub fn new(table: EventTable) -> Self {
+        Self { cooldowns: vec![0.0; table.events.len()], table, next_in: None }
+    }
+
+    fn roll_interval(&self, rng: &mut impl Rng) -> f32 {
+        let (min, max) = self.table.interval;
+        if max > min { rng.gen_range(min..=max) } else { min }
+    }
+
+    /// Picks an event by weight from those not cooling down, and starts its cooldown.
+    pub fn pick(&mut self, rng: &mut impl Rng) -> Option<RandomEventKind> {
+        let ready = |index: usize| self.cooldowns[index] <= 0.0;
+        let total: u32 = (0..self.table.events.len()).filter(|&i| ready(i)).map(|i| self.table.events[i].weight).sum();
+        if total == 0 {
+            return None;
+        }
+        let mut roll = rng.gen_range(0..total);
+        for (index, entry) in self.table.events.iter().enumerate() {
+            if self.cooldowns[index] > 0.0 {
+                continue;
+            }
+            if roll < entry.weight {
+                self.cooldowns[index] = entry.cooldown;
+                return Some(entry.kind);
+            }
+            roll -= entry.weight;
+        }
+        None
+    }
+
+    /// Advances by `seconds`, returning the event that is due, if any.
+    pub fn tick(&mut self, seconds: f32, rng: &mut impl Rng) -> Option<RandomEventKind> {
+        for cooldown in &mut self.cooldowns {
+            *cooldown = (*cooldown - seconds).max(0.0);
+        }
+        let next_in = match self.next_in {
+            Some(next_in) => next_in - seconds,
+            None => self.roll_interval(rng),
+        };
+        if next_in > 0.0 {
+            self.next_in = Some(next_in);
+            return None;
+        }
+        self.next_in = Some(self.roll_interval(rng));
+        self.pick(rng)
+    }
+}
+
+/// Sent when a random event begins.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct RandomEventStarted(pub RandomEventKind);
+
+/// The announcement on screen and how long the zoomies have left.
+#[derive(Resource, Default)]
+pub struct ActiveEvents {
+    banner: Option<(RandomEventKind, f32)>,
+    pub zoomies_left: f32,
+}
+
+#[derive(Component)]
+pub struct GoldenCat {
+    pub speed: f32,
+}
+
+#[derive(Component)]
+pub struct SurpriseBox {
+    body: Toy,
+    lifetime: f32,
+}
+
+#[derive(Component)]
+pub struct EventBanner;
+
+pub struct RandomEventsPlugin;
+
+impl Plugin for RandomEventsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(EventScheduler::new(EventTable::load()))
+            .init_resource::<ActiveEvents>()
+            .add_event::<RandomEventStarted>()
+            .add_systems(OnEnter(InGame), spawn_event_banner)
+            .add_systems(OnExit(Minigame::None), (clear_event_props, end_zoomies))
+            .add_systems(OnExit(InGame), end_zoomies)
+            .add_systems(Update, (
+                schedule_events_system,
+                start_events_system,
+                golden_cat_system,
+                surprise_box_system,
+                zoomies_system,
+            ).chain()
+                // Clicks on event props are taken before the cat sees them
+                .before(recognize_gestures_system)
+                .run_if(in_state(AppState::Playing).and_then(in_state(Minigame::None))))
+            .add_systems(Update, event_banner_system.run_if(in_state(InGame)));
+    }
+}
+
+fn spawn_event_banner(mut commands: Commands) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    padding: UiRect::top(Val::Px(16.0)),
+                    ..default()
+                },
+                ..default()
+            },
+            StateScoped(InGame),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 32.0,
+                        color: Color::srgb(1.0, 0.85, 0.3),
+                        ..default()
+                    },
+                ),
+                EventBanner,
+            ));
+        });
+}
+
+pub fn schedule_events_system(
+    time: Res<Time>,
+    mut scheduler: ResMut<EventScheduler>,
+    mut rng: ResMut<GameRng>,
+    mut started: EventWriter<RandomEventStarted>,
+) {
+    if let Some(kind) = scheduler.tick(time.delta_seconds(), rng.stream(RngStream::Events)) {
+        println!("Random event: {:?}", kind);
+        started.send(RandomEventStarted(kind));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn start_events_system(
+    mut commands: Commands,
+    mut events: EventReader<RandomEventStarted>,
+    mut rng: ResMut<GameRng>,
+    mut active: ResMut<ActiveEvents>,
+    mut multiplier: ResMut<ClickMultiplier>,
+    textures: Res<GameTextures>,
+    asset_server: Res<AssetServer>,
+    background: Res<ActiveBackground>,
+    scale: Res<BackgroundScale>,
+    animation_library: Res<AnimationLibrary>,
+    resting: Res<RestingAnimation>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut cats: Query<(&TextureAtlas, &mut AnimationState), With<AnimatedCat>>,
+) {
+    let (camera, camera_transform) = cameras.single();
+    let Some(visible) = visible_world_rect(camera, camera_transform) else {
+        events.clear();
+        return;
+    };
+    let floor = background.floor_y(&scale);
+    let rng = rng.stream(RngStream::Events);
+
+    for RandomEventStarted(kind) in events.read() {
+        active.banner = Some((*kind, BANNER_SECONDS));
+        match kind {
+            RandomEventKind::GoldenCat => {
+                let Ok((atlas, _)) = cats.get_single() else {
+                    continue;
+                };
+                // Runs in from either side
+                let from_left = rng.gen_bool(0.5);
+                let x = if from_left { visible.min.x - 64.0 } else { visible.max.x + 64.0 };
+                let y = floor + rng.gen_range(0.0..=GOLDEN_CAT_MAX_HEIGHT);
+                let speed = (visible.width() + 128.0) / GOLDEN_CAT_SECONDS;
+                let animation = animation_library.get("run").map_or_else(AnimationState::default, Animation::start);
+                commands.spawn((
+                    SpriteBundle {
+                        texture: textures.cat.clone(),
+                        transform: Transform::from_xyz(x, y, GOLDEN_CAT_Z).with_scale(Vec3::splat(1.2)),
+                        sprite: Sprite {
+                            custom_size: Some(Vec2::new(64.0, 64.0)),
+                            color: Color::srgb(1.0, 0.85, 0.3),
+                            flip_x: !from_left,
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    TextureAtlas { layout: atlas.layout.clone(), index: 0 },
+                    animation,
+                    GoldenCat { speed: if from_left { speed } else { -speed } },
+                    ParticleEmitter::new("golden_trail"),
+                    StateScoped(InGame),
+                ));
+            }
+            RandomEventKind::SurpriseBox => {
+                let bounds = toy_bounds(visible, ToyKind::Box, &background, &scale);
+                let x = rng.gen_range(bounds.min.x..=bounds.max.x.max(bounds.min.x));
+                commands.spawn((
+                    SpriteBundle {
+                        texture: asset_server.load(ToyKind::Box.image()),
+                        transform: Transform::from_xyz(x, bounds.max.y, GOLDEN_CAT_Z),
+                        sprite: Sprite {
+                            custom_size: Some(ToyKind::Box.size()),
+                            // Wrapped as a present
+                            color: Color::srgb(1.0, 0.6, 0.8),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    SurpriseBox { body: Toy::new(ToyKind::Box), lifetime: SURPRISE_BOX_SECONDS },
+                    StateScoped(InGame),
+                ));
+            }
+            RandomEventKind::Zoomies => {
+                if active.zoomies_left <= 0.0 {
+                    multiplier.0 = multiplier.0.max(1) * 2;
+                }
+                active.zoomies_left = ZOOMIES_SECONDS;
+                if let Ok((_, mut state)) = cats.get_single_mut() {
+                    state.play_animation_then_return("run", &resting.0, 3.0, &animation_library);
+                }
+            }
+        }
+    }
+}
+
+/// The golden cat runs across and off the screen. Clicking it pays the bigger
+/// of a fixed bonus and a share of the clicks already banked.
+#[allow(clippy::too_many_arguments)]
+pub fn golden_cat_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    actions: Res<ActionState>,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    mut counter: ResMut<ClickCounter>,
+    multiplier: Res<ClickMultiplier>,
+    mut golden_cats: Query<(Entity, &GoldenCat, &mut Transform, &Sprite)>,
+    mut recognizer: ResMut<GestureRecognizer>,
+    mut earned: EventWriter<ClicksEarned>,
+    mut bursts: EventWriter<ParticleBurst>,
+    mut sounds: EventWriter<PlaySound>,
+) {
+    let (camera, camera_transform) = cameras.single();
+    let cursor = cursor_world_position(windows.single(), camera, camera_transform);
+    let visible = visible_world_rect(camera, camera_transform);
+    let clicked = actions.just_pressed(Action::Pet);
+
+    for (entity, golden, mut transform, sprite) in &mut golden_cats {
+        transform.translation.x += golden.speed * time.delta_seconds();
+        let size = sprite.custom_size.unwrap_or_default() * transform.scale.xy();
+        let area = Rect::from_center_size(transform.translation.xy(), size);
+
+        if clicked && cursor.is_some_and(|cursor| area.contains(cursor)) {
+            let amount = golden_cat_bonus(counter.0) * multiplier.0.max(1);
+            counter.0 += amount;
+            earned.send(ClicksEarned { amount, position: transform.translation.xy() });
+            bursts.send(ParticleBurst { effect: "sparkles".to_string(), position: transform.translation });
+            sounds.send(PlaySound("sparkle".to_string()));
+            println!("Caught the golden cat for {} clicks", amount);
+            // The click was for the golden cat, not the cat behind it
+            recognizer.claim_press();
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let gone = visible.is_some_and(|visible| {
+            (golden.speed > 0.0 && area.min.x > visible.max.x) || (golden.speed < 0.0 && area.max.x < visible.min.x)
+        });
+        if gone {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Bonus for catching the golden cat, before the click multiplier.
+pub fn golden_cat_bonus(banked: u32) -> u32 {
+    (banked / GOLDEN_CAT_BANK_SHARE).max(GOLDEN_CAT_MIN_BONUS)
+}
+
+/// The surprise box falls to the floor and waits to be clicked open, for a
+/// free food from the shop.
+#[allow(clippy::too_many_arguments)]
+pub fn surprise_box_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    actions: Res<ActionState>,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    background: Res<ActiveBackground>,
+    scale: Res<BackgroundScale>,
+    foods: Res<FoodLibrary>,
+    mut rng: ResMut<GameRng>,
+    mut inventory: ResMut<Inventory>,
+    mut boxes: Query<(Entity, &mut SurpriseBox, &mut Transform)>,
+    mut recognizer: ResMut<GestureRecognizer>,
+    mut bursts: EventWriter<ParticleBurst>,
+) {
+    let (camera, camera_transform) = cameras.single();
+    let Some(visible) = visible_world_rect(camera, camera_transform) else {
+        return;
+    };
+    let cursor = cursor_world_position(windows.single(), camera, camera_transform);
+    let clicked = actions.just_pressed(Action::Pet);
+    let seconds = time.delta_seconds();
+
+    for (entity, mut surprise, mut transform) in &mut boxes {
+        let bounds = toy_bounds(visible, ToyKind::Box, &background, &scale);
+        let mut position = transform.translation.xy();
+        surprise.body.step(&mut position, bounds, seconds);
+        transform.translation = position.extend(transform.translation.z);
+
+        let area = Rect::from_center_size(position, ToyKind::Box.size());
+        if clicked && cursor.is_some_and(|cursor| area.contains(cursor)) {
+            let ids: Vec<&str> = foods.iter().map(|(id, _)| id).collect();
+            if !ids.is_empty() {
+                let id = ids[rng.stream(RngStream::Events).gen_range(0..ids.len())];
+                inventory.add(id, 1);
+                println!("The surprise box had {} inside", foods.get(id).map_or(id, |food| food.name.as_str()));
+            }
+            bursts.send(ParticleBurst { effect: "hearts".to_string(), position: transform.translation });
+            // The box sits on the cat's floor, so it often covers part of the cat
+            recognizer.claim_press();
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        surprise.lifetime -= seconds;
+        if surprise.lifetime <= 0.0 {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// Counts down the zoomies and puts the click value back afterwards.
+pub fn zoomies_system(time: Res<Time>, mut active: ResMut<ActiveEvents>, mut multiplier: ResMut<ClickMultiplier>) {
+    if active.zoomies_left <= 0.0 {
+        return;
+    }
+    active.zoomies_left -= time.delta_seconds();
+    if active.zoomies_left <= 0.0 {
+        active.zoomies_left = 0.0;
+        multiplier.0 = (multiplier.0 / 2).max(1);
+    }
+}
+
+/// Zoomies and announcements stop when a mini-game starts or the game is left,
+/// so mini-games always score with the normal click value.
+fn end_zoomies(mut active: ResMut<ActiveEvents>, mut multiplier: ResMut<ClickMultiplier>) {
+    if active.zoomies_left > 0.0 {
+        multiplier.0 = (multiplier.0 / 2).max(1);
+    }
+    *active = ActiveEvents::default();
+}
+
+/// The golden cat and surprise boxes go away when a mini-game starts.
+#[allow(clippy::type_complexity)]
+fn clear_event_props(
+    mut commands: Commands,
+    props: Query<Entity, Or<(With<GoldenCat>, With<SurpriseBox>)>>,
+) {
+    for entity in &props {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Announces each event for a moment, and counts down the zoomies. Hidden
+/// while a mini-game is running.
+pub fn event_banner_system(
+    time: Res<Time>,
+    config: Res<UserConfig>,
+    minigame: Res<State<Minigame>>,
+    mut active: ResMut<ActiveEvents>,
+    mut banners: Query<(&mut Text, &mut Visibility), With<EventBanner>>,
+) {
+    if let Some((_, remaining)) = &mut active.banner {
+        *remaining -= time.delta_seconds();
+        if *remaining <= 0.0 {
+            active.banner = None;
+        }
+    }
+    let language = config.language;
+    let text = match active.banner {
+        Some((RandomEventKind::Zoomies, _)) | None if active.zoomies_left > 0.0 => {
+            format!("{} {} x2 {:.0}s", language.translate("Zoomies!"), language.translate("Clicks"), active.zoomies_left.ceil())
+        }
+        Some((kind, _)) => language.translate(kind.announcement()).to_string(),
+        None => String::new(),
+    };
+    let visibility = if *minigame.get() == Minigame::None { Visibility::Inherited } else { Visibility::Hidden };
+    for (mut banner, mut shown) in &mut banners {
+        if banner.sections[0].value != text {
+            banner.sections[0].value = text.clone();
+        }
+        if *shown != visibility {
+            *shown = visibility;
+        }
+    }
+}
@@ -0,0 +1,630 @@
+use bevy::asset::io::file::FileAssetReader;
+use bevy::prelude::*;
+use std::fs;
+use std::time::Duration;
+
+use crate::animation::{AnimationLibrary, AnimationState};
+use crate::audio::PlaySound;
+use crate::controls::{Action, ActionState};
+use crate::game::{AnimatedCat, ClickCounter, ClickMultiplier, ClicksEarned, RestingAnimation, Statistics};
+use crate::particles::ParticleBurst;
+use crate::settings::UserConfig;
+use crate::state::{AppState, Minigame};
+
+const BEATMAP_DIR: &str = "rhythm";
+const BEATMAP_EXTENSION: &str = "beat";
+const MARKER_IMAGE: &str = "particles/heart.png";
+
+/// A press this close to a note is perfect
+pub const PERFECT_WINDOW: f32 = 0.05;
+/// A press this close still counts; anything later is a miss
+pub const GOOD_WINDOW: f32 = 0.12;
+/// Seconds before the song starts, so the first markers can scroll in
+const LEAD_IN_SECONDS: f32 = 2.0;
+/// How long after the last note the round ends
+const END_DELAY_SECONDS: f32 = 1.0;
+const RESULTS_SECONDS: f32 = 3.0;
+const FEEDBACK_SECONDS: f32 = 0.6;
+/// Markers are spawned this far ahead of their note
+const LOOKAHEAD_SECONDS: f32 = 3.0;
+const SCROLL_SPEED: f32 = 300.0;
+const TARGET_POSITION: Vec3 = Vec3::new(-300.0, 220.0, 40.0);
+const MARKER_SIZE: f32 = 32.0;
+/// How many recent notes the dance intensity is taken from
+pub const RECENT_NOTES: usize = 8;
+/// Dancing stops below this accuracy
+pub const MIN_DANCE_ACCURACY: f32 = 0.3;
+/// Score needed for one click
+const POINTS_PER_CLICK: u32 = 100;
+
+/// A song for the rhythm game, read from a `.beat` file in `assets/rhythm`:
+///
+/// ```text
+/// # comment
+/// title: Meadow Shuffle
+/// bpm: 100
+/// offset: 0.25
+/// notes:
+/// 4 5 6 7
+/// 8 8.5 9
+/// ```
+///
+/// The offset is in seconds before the first beat and notes are counted in
+/// beats from there, any number per line.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Beatmap {
+    pub title: String,
+    pub bpm: f32,
+    pub offset: f32,
+    pub notes: Vec<f32>,
+}
+
+impl Beatmap {
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut title = String::new();
+        let mut bpm = None;
+        let mut offset = 0.0;
+        let mut notes = Vec::new();
+        let mut in_notes = false;
+
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.split('#').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            if in_notes {
+                for word in line.split_whitespace() {
+                    let beat: f32 = word
+                        .parse()
+                        .map_err(|_| format!("line {}: '{}' is not a beat number", line_number, word))?;
+                    if beat < 0.0 {
+                        return Err(format!("line {}: notes can't come before beat 0", line_number));
+                    }
+                    notes.push(beat);
+                }
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once(':') else {
+                return Err(format!("line {}: expected 'key: value'", line_number));
+            };
+            let value = value.trim();
+            let number = || value.parse::<f32>().map_err(|_| format!("line {}: '{}' is not a number", line_number, value));
+            match key.trim() {
+                "title" => title = value.to_string(),
+                "bpm" => bpm = Some(number()?),
+                "offset" => offset = number()?,
+                "notes" => in_notes = true,
+                other => return Err(format!("line {}: unknown key '{}'", line_number, other)),
+            }
+        }
+
+        let bpm = bpm.ok_or("missing bpm")?;
+        if bpm <= 0.0 {
+            return Err("bpm must be above 0".to_string());
+        }
+        if notes.is_empty() {
+            return Err("no notes".to_string());
+        }
+        notes.sort_by(f32::total_cmp);
+        Ok(Self { title, bpm, offset, notes })
+    }
+
+    pub fn beat_seconds(&self) -> f32 {
+        60.0 / self.bpm
+    }
+
+    /// When each note should be hit, in seconds from the start of the song.
+    pub fn note_times(&self) -> Vec<f32> {
+        self.notes.iter().map(|beat| self.offset + beat * self.beat_seconds()).collect()
+    }
+}
+
+/// How well a note was hit.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Judgement {
+    Perfect,
+    Good,
+    Miss,
+}
+
+impl Judgement {
+    /// Judges a press `offset` seconds away from a note. Presses outside the
+    /// window don't belong to the note at all.
+    pub fn from_offset(offset: f32) -> Option<Self> {
+        let offset = offset.abs();
+        if offset <= PERFECT_WINDOW {
+            Some(Judgement::Perfect)
+        } else if offset <= GOOD_WINDOW {
+            Some(Judgement::Good)
+        } else {
+            None
+        }
+    }
+
+    pub fn points(self) -> u32 {
+        match self {
+            Judgement::Perfect => 100,
+            Judgement::Good => 50,
+            Judgement::Miss => 0,
+        }
+    }
+
+    /// How much the judgement counts towards accuracy
+    pub fn weight(self) -> f32 {
+        match self {
+            Judgement::Perfect => 1.0,
+            Judgement::Good => 0.5,
+            Judgement::Miss => 0.0,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Judgement::Perfect => "Perfect",
+            Judgement::Good => "Good",
+            Judgement::Miss => "Miss",
+        }
+    }
+}
+
+/// Judges presses against a beatmap's notes. Times are song seconds, so it
+/// can be driven by the game clock or by made-up timestamps.
+#[derive(Clone, Debug, Default)]
+pub struct RhythmJudge {
+    times: Vec<f32>,
+    judged: Vec<Option<Judgement>>,
+    /// Judgements in the order they were made
+    history: Vec<Judgement>,
+    pub score: u32,
+    pub combo: u32,
+    pub best_combo: u32,
+}
+
+impl RhythmJudge {
+    pub fn new(times: Vec<f32>) -> Self {
+        Self { judged: vec![None; times.len()], times, ..default() }
+    }
+
+    pub fn note_time(&self, note: usize) -> f32 {
+        self.times[note]
+    }
+
+    pub fn note_count(&self) -> usize {
+        self.times.len()
+    }
+
+    pub fn judgement(&self, note: usize) -> Option<Judgement> {
+        self.judged[note]
+    }
+
+    fn record(&mut self, note: usize, judgement: Judgement) {
+        self.judged[note] = Some(judgement);
+        self.history.push(judgement);
+        self.score += judgement.points();
+        if judgement == Judgement::Miss {
+            self.combo = 0;
+        } else {
+            self.combo += 1;
+            self.best_combo = self.best_combo.max(self.combo);
+        }
+    }
+
+    /// A press at `time` hits the closest note still waiting to be judged,
+    /// if it is inside the window.
+    pub fn press(&mut self, time: f32) -> Option<(usize, Judgement)> {
+        let (note, judgement) = (0..self.times.len())
+            .filter(|&note| self.judged[note].is_none())
+            .filter_map(|note| Judgement::from_offset(time - self.times[note]).map(|judgement| (note, judgement)))
+            .min_by(|(a, _), (b, _)| (time - self.times[*a]).abs().total_cmp(&(time - self.times[*b]).abs()))?;
+        self.record(note, judgement);
+        Some((note, judgement))
+    }
+
+    /// Misses every note whose window closed before `time`, returning them.
+    pub fn advance(&mut self, time: f32) -> Vec<usize> {
+        let missed: Vec<usize> = (0..self.times.len())
+            .filter(|&note| self.judged[note].is_none() && time - self.times[note] > GOOD_WINDOW)
+            .collect();
+        for &note in &missed {
+            self.record(note, Judgement::Miss);
+        }
+        missed
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.judged.iter().all(Option::is_some)
+    }
+
+    /// Accuracy of the last `count` judgements from 0 to 1, or `None` before any.
+    pub fn recent_accuracy(&self, count: usize) -> Option<f32> {
+        let recent = &self.history[self.history.len().saturating_sub(count)..];
+        if recent.is_empty() {
+            return None;
+        }
+        Some(recent.iter().map(|judgement| judgement.weight()).sum::<f32>() / recent.len() as f32)
+    }
+
+    pub fn accuracy(&self) -> Option<f32> {
+        self.recent_accuracy(self.history.len())
+    }
+}
+
+/// Seconds per `dance` frame: the better the recent accuracy, the faster the cat dances.
+pub fn dance_frame_duration(accuracy: f32) -> f32 {
+    0.3 - 0.18 * accuracy.clamp(0.0, 1.0)
+}
+
+/// Every beatmap found in `assets/rhythm`. Each round plays the next one.
+#[derive(Resource, Default)]
+pub struct BeatmapLibrary {
+    beatmaps: Vec<Beatmap>,
+    next: usize,
+}
+
+impl BeatmapLibrary {
+    pub fn discover() -> Self {
+        let dir = FileAssetReader::get_base_path().join("assets").join(BEATMAP_DIR);
+        let mut paths: Vec<_> = fs::read_dir(&dir)
+            .map(|entries| entries.filter_map(|entry| entry.ok()).map(|entry| entry.path()).collect())
+            .unwrap_or_else(|err| {
+                println!("Could not read {}: {}", dir.display(), err);
+                Vec::new()
+            });
+        paths.retain(|path| path.extension().is_some_and(|extension| extension == BEATMAP_EXTENSION));
+        paths.sort();
+
+        let beatmaps = paths
+            .iter()
+            .filter_map(|path| {
+                let parsed = fs::read_to_string(path).map_err(|err| err.to_string()).and_then(|text| Beatmap::parse(&text));
+                match parsed {
+                    Ok(beatmap) => Some(beatmap),
+                    Err(err) => {
+                        println!("Skipping beatmap {}: {}", path.display(), err);
+                        None
+                    }
+                }
+            })
+            .collect();
+        Self { beatmaps, next: 0 }
+    }
+
+    fn take_next(&mut self) -> Option<Beatmap> {
+        let beatmap = self.beatmaps.get(self.next % self.beatmaps.len().max(1))?.clone();
+        self.next += 1;
+        Some(beatmap)
+    }
+}
+
+/// One song of the rhythm game.
+#[derive(Resource)]
+pub struct RhythmRound {
+    pub beatmap: Beatmap,
+    pub judge: RhythmJudge,
+    pub song_time: f32,
+    last_beat: i32,
+    next_marker: usize,
+    feedback: Option<(Judgement, f32)>,
+    /// Counts down while the score is shown, once the song is over
+    results: Option<f32>,
+}
+
+impl RhythmRound {
+    pub fn new(beatmap: Beatmap) -> Self {
+        Self {
+            judge: RhythmJudge::new(beatmap.note_times()),
+            beatmap,
+            song_time: -LEAD_IN_SECONDS,
+            last_beat: -1,
+            next_marker: 0,
+            feedback: None,
+            results: None,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct BeatMarker(pub usize);
+
+#[derive(Component)]
+pub struct RhythmHud;
+
+pub struct RhythmPlugin;
+
+impl Plugin for RhythmPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(BeatmapLibrary::discover())
+            .add_systems(Update, start_rhythm_system.run_if(in_state(AppState::Playing).and_then(in_state(Minigame::None))))
+            .add_systems(OnEnter(Minigame::Rhythm), start_song)
+            .add_systems(OnExit(Minigame::Rhythm), end_song)
+            .add_systems(Update, (
+                rhythm_clock_system,
+                rhythm_input_system,
+                beat_marker_system,
+                dance_intensity_system,
+                rhythm_score_system,
+            ).chain().run_if(in_state(AppState::Playing).and_then(in_state(Minigame::Rhythm))));
+    }
+}
+
+/// The rhythm action, `B` by default, starts a song, if there are any beatmaps.
+pub fn start_rhythm_system(
+    actions: Res<ActionState>,
+    library: Res<BeatmapLibrary>,
+    mut next_minigame: ResMut<NextState<Minigame>>,
+) {
+    if actions.just_pressed(Action::Rhythm) {
+        if library.beatmaps.is_empty() {
+            println!("No beatmaps found in assets/{}", BEATMAP_DIR);
+            return;
+        }
+        next_minigame.set(Minigame::Rhythm);
+    }
+}
+
+fn start_song(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    config: Res<UserConfig>,
+    mut library: ResMut<BeatmapLibrary>,
+    mut next_minigame: ResMut<NextState<Minigame>>,
+) {
+    let Some(beatmap) = library.take_next() else {
+        next_minigame.set(Minigame::None);
+        return;
+    };
+    println!("Starting rhythm game: {} ({} bpm, {} notes)", beatmap.title, beatmap.bpm, beatmap.notes.len());
+    commands.insert_resource(RhythmRound::new(beatmap));
+
+    // The ring markers should be hit in
+    commands.spawn((
+        SpriteBundle {
+            texture: asset_server.load(MARKER_IMAGE),
+            transform: Transform::from_translation(TARGET_POSITION - Vec3::Z),
+            sprite: Sprite {
+                color: Color::srgba(0.2, 0.2, 0.2, 0.6),
+                custom_size: Some(Vec2::splat(MARKER_SIZE * 1.5)),
+                ..default()
+            },
+            ..default()
+        },
+        StateScoped(Minigame::Rhythm),
+    ));
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    padding: UiRect::top(Val::Px(16.0)),
+                    ..default()
+                },
+                ..default()
+            },
+            StateScoped(Minigame::Rhythm),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    config.language.translate("Rhythm"),
+                    TextStyle {
+                        font_size: 32.0,
+                        color: Color::srgb(0.9, 0.5, 1.0),
+                        ..default()
+                    },
+                ),
+                RhythmHud,
+            ));
+        });
+}
+
+fn end_song(
+    mut commands: Commands,
+    animation_library: Res<AnimationLibrary>,
+    resting: Res<RestingAnimation>,
+    mut cats: Query<&mut AnimationState, With<AnimatedCat>>,
+) {
+    // Also puts the frame timing back to normal
+    if let Ok(mut state) = cats.get_single_mut() {
+        state.play_animation(&resting.0, &animation_library);
+    }
+    commands.remove_resource::<RhythmRound>();
+}
+
+/// Advances the song, ticking on every beat and missing notes that went by.
+pub fn rhythm_clock_system(
+    time: Res<Time>,
+    mut round: ResMut<RhythmRound>,
+    mut sounds: EventWriter<PlaySound>,
+) {
+    round.song_time += time.delta_seconds();
+    let song_time = round.song_time;
+
+    let beat = ((song_time - round.beatmap.offset) / round.beatmap.beat_seconds()).floor() as i32;
+    let last_note = round.beatmap.notes.last().copied().unwrap_or(0.0) as i32;
+    if beat > round.last_beat && beat <= last_note {
+        round.last_beat = beat;
+        if beat >= 0 {
+            sounds.send(PlaySound("beat".to_string()));
+        }
+    }
+
+    if !round.judge.advance(song_time).is_empty() {
+        round.feedback = Some((Judgement::Miss, FEEDBACK_SECONDS));
+    }
+}
+
+/// The jump action (`Space`, `F`, `J` or a click) hits the nearest note.
+pub fn rhythm_input_system(
+    actions: Res<ActionState>,
+    mut round: ResMut<RhythmRound>,
+    mut bursts: EventWriter<ParticleBurst>,
+) {
+    let pressed = actions.just_pressed(Action::Jump);
+    if !pressed || round.results.is_some() {
+        return;
+    }
+    let song_time = round.song_time;
+    if let Some((_, judgement)) = round.judge.press(song_time) {
+        round.feedback = Some((judgement, FEEDBACK_SECONDS));
+        if judgement == Judgement::Perfect {
+            bursts.send(ParticleBurst { effect: "sparkles".to_string(), position: TARGET_POSITION });
+        }
+    }
+}
+
+/// Spawns markers ahead of their notes and scrolls them towards the target.
+/// Hit markers disappear; missed ones fade and carry on past.
+pub fn beat_marker_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut round: ResMut<RhythmRound>,
+    mut markers: Query<(Entity, &BeatMarker, &mut Transform, &mut Sprite)>,
+) {
+    while round.next_marker < round.judge.note_count()
+        && round.judge.note_time(round.next_marker) - round.song_time < LOOKAHEAD_SECONDS
+    {
+        let note = round.next_marker;
+        round.next_marker += 1;
+        commands.spawn((
+            SpriteBundle {
+                texture: asset_server.load(MARKER_IMAGE),
+                transform: Transform::from_translation(TARGET_POSITION + Vec3::X * LOOKAHEAD_SECONDS * SCROLL_SPEED),
+                sprite: Sprite {
+                    custom_size: Some(Vec2::splat(MARKER_SIZE)),
+                    ..default()
+                },
+                ..default()
+            },
+            BeatMarker(note),
+            StateScoped(Minigame::Rhythm),
+        ));
+    }
+
+    for (entity, marker, mut transform, mut sprite) in &mut markers {
+        let until_note = round.judge.note_time(marker.0) - round.song_time;
+        transform.translation.x = TARGET_POSITION.x + until_note * SCROLL_SPEED;
+        match round.judge.judgement(marker.0) {
+            Some(Judgement::Miss) => sprite.color = Color::srgba(0.4, 0.4, 0.4, 0.5),
+            Some(_) => commands.entity(entity).despawn_recursive(),
+            None => {}
+        }
+        if until_note < -1.0 {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+/// The cat dances faster the better the recent notes went, and stops
+/// dancing when they go badly.
+pub fn dance_intensity_system(
+    round: Res<RhythmRound>,
+    animation_library: Res<AnimationLibrary>,
+    resting: Res<RestingAnimation>,
+    mut cats: Query<&mut AnimationState, With<AnimatedCat>>,
+) {
+    let Ok(mut state) = cats.get_single_mut() else {
+        return;
+    };
+    // Dance along until the first note is judged
+    let accuracy = round.judge.recent_accuracy(RECENT_NOTES).unwrap_or(0.5);
+    if accuracy < MIN_DANCE_ACCURACY {
+        if state.current_animation != resting.0 {
+            state.play_animation(&resting.0, &animation_library);
+        }
+        return;
+    }
+    if state.current_animation != "dance" {
+        state.play_animation("dance", &animation_library);
+    }
+    let duration = Duration::from_secs_f32(dance_frame_duration(accuracy));
+    if state.timer.duration() != duration {
+        state.timer.set_duration(duration);
+    }
+}
+
+/// Shows the score, the last judgement and the accuracy, then pays the
+/// score out into the click counter when the song ends.
+#[allow(clippy::too_many_arguments)]
+pub fn rhythm_score_system(
+    time: Res<Time>,
+    config: Res<UserConfig>,
+    multiplier: Res<ClickMultiplier>,
+    mut round: ResMut<RhythmRound>,
+    mut counter: ResMut<ClickCounter>,
+    mut stats: ResMut<Statistics>,
+    mut earned: EventWriter<ClicksEarned>,
+    mut huds: Query<&mut Text, With<RhythmHud>>,
+    mut next_minigame: ResMut<NextState<Minigame>>,
+) {
+    let language = config.language;
+    let delta = time.delta_seconds();
+    let last_note = round.judge.note_time(round.judge.note_count() - 1);
+    let reward = round.judge.score / POINTS_PER_CLICK * multiplier.0.max(1);
+
+    if round.results.is_none() && round.judge.is_finished() && round.song_time > last_note + END_DELAY_SECONDS {
+        counter.0 += reward;
+        if reward > 0 {
+            earned.send(ClicksEarned { amount: reward, position: TARGET_POSITION.truncate() });
+        }
+        if round.judge.score > stats.best_rhythm_score {
+            stats.best_rhythm_score = round.judge.score;
+        }
+        println!(
+            "Rhythm game over: {} points, best combo {}, +{} clicks",
+            round.judge.score, round.judge.best_combo, reward
+        );
+        round.results = Some(RESULTS_SECONDS);
+    }
+
+    let accuracy = (round.judge.accuracy().unwrap_or(0.0) * 100.0).round();
+    let text = match round.results {
+        None => {
+            let feedback = match &mut round.feedback {
+                Some((judgement, remaining)) => {
+                    *remaining -= delta;
+                    language.translate(judgement.label())
+                }
+                None => "",
+            };
+            if round.feedback.is_some_and(|(_, remaining)| remaining <= 0.0) {
+                round.feedback = None;
+            }
+            format!(
+                "{}   {}: {}   {}: {}   {}%   {}",
+                round.beatmap.title,
+                language.translate("Score"),
+                round.judge.score,
+                language.translate("Combo"),
+                round.judge.combo,
+                accuracy,
+                feedback
+            )
+        }
+        Some(remaining) => {
+            let remaining = remaining - delta;
+            round.results = Some(remaining);
+            if remaining <= 0.0 {
+                next_minigame.set(Minigame::None);
+            }
+            format!(
+                "{}! {}: {} (+{})   {}%   {}: {}",
+                language.translate("Song over"),
+                language.translate("Score"),
+                round.judge.score,
+                reward,
+                accuracy,
+                language.translate("Best"),
+                stats.best_rhythm_score
+            )
+        }
+    };
+    for mut hud in &mut huds {
+        if hud.sections[0].value != text {
+            hud.sections[0].value = text.clone();
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use bevy::prelude::*;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Separate random streams, so that for example an extra particle doesn't
+/// change which background comes next.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RngStream {
+    /// Which background is shown next
+    Backgrounds,
+    /// When random events happen, which ones, and what they hand out
+    Events,
+    /// Decisions the game makes on its own, such as where toys land and the
+    /// runner's obstacles
+    Ai,
+    /// Sound variations and particles, which don't affect play
+    Effects,
+}
+
+impl RngStream {
+    // Spreads the streams far apart, so seeds next to each other don't share streams
+    fn seed(self, seed: u64) -> u64 {
+        let index = self as u64 + 1;
+        seed ^ index.wrapping_mul(0x9E37_79B9_7F4A_7C15)
+    }
+}
+
+/// Every random decision in the game comes from here. Running with the same
+/// seed, from `--seed` or the config file, replays the same choices.
+#[derive(Resource)]
+pub struct GameRng {
+    seed: u64,
+    streams: HashMap<RngStream, StdRng>,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self { seed, streams: HashMap::new() }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn stream(&mut self, stream: RngStream) -> &mut StdRng {
+        let seed = self.seed;
+        self.streams.entry(stream).or_insert_with(|| StdRng::seed_from_u64(stream.seed(seed)))
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::new(random_seed())
+    }
+}
+
+/// A seed that differs from run to run.
+pub fn random_seed() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_nanos() as u64)
+}
@@ -0,0 +1,236 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::profile::ProfileSettings;
+
+/// Click counts that trigger `OnMilestone`; after the last one every
+/// further thousand clicks counts as a milestone.
+const MILESTONES: [u32; 7] = [10, 25, 50, 100, 250, 500, 1000];
+
+/// What a rotation policy gets to look at each frame.
+pub struct RotationContext {
+    pub previous_clicks: u32,
+    pub clicks: u32,
+    pub delta_seconds: f32,
+    pub local_hour: u32,
+}
+
+/// Decides when the background should change. Implement this and register it
+/// with [`RotationAppExt::register_rotation_policy`] to add your own.
+pub trait RotationPolicy: Send + Sync + 'static {
+    fn should_rotate(&mut self, context: &RotationContext) -> bool;
+}
+
+/// The policy choice stored in the profile's settings.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum RotationSetting {
+    EveryClicks(u32),
+    EverySeconds(f32),
+    OnHour,
+    OnMilestone,
+    Manual,
+    /// A policy registered by name through `register_rotation_policy`
+    Custom(String),
+}
+
+impl Default for RotationSetting {
+    fn default() -> Self {
+        RotationSetting::EveryClicks(5)
+    }
+}
+
+impl RotationSetting {
+    /// Built-in settings in the order the debug key cycles through them.
+    pub fn next_builtin(&self) -> RotationSetting {
+        match self {
+            RotationSetting::EveryClicks(_) => RotationSetting::EverySeconds(30.0),
+            RotationSetting::EverySeconds(_) => RotationSetting::OnHour,
+            RotationSetting::OnHour => RotationSetting::OnMilestone,
+            RotationSetting::OnMilestone => RotationSetting::Manual,
+            RotationSetting::Manual | RotationSetting::Custom(_) => RotationSetting::default(),
+        }
+    }
+}
+
+pub struct EveryClicks(pub u32);
+
+impl RotationPolicy for EveryClicks {
+    fn should_rotate(&mut self, context: &RotationContext) -> bool {
+        let every = self.0.max(1);
+        // Compare buckets rather than testing the exact count, so a click worth
+        // several points can't jump over a multiple
+        context.clicks / every > context.previous_clicks / every
+    }
+}
+
+pub struct EverySeconds {
+    pub seconds: f32,
+    elapsed: f32,
+}
+
+impl EverySeconds {
+    pub fn new(seconds: f32) -> Self {
+        Self { seconds: seconds.max(1.0), elapsed: 0.0 }
+    }
+}
+
+impl RotationPolicy for EverySeconds {
+    fn should_rotate(&mut self, context: &RotationContext) -> bool {
+        self.elapsed += context.delta_seconds;
+        if self.elapsed >= self.seconds {
+            self.elapsed -= self.seconds;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct OnHour {
+    last_hour: Option<u32>,
+}
+
+impl RotationPolicy for OnHour {
+    fn should_rotate(&mut self, context: &RotationContext) -> bool {
+        let changed = self.last_hour.is_some_and(|hour| hour != context.local_hour);
+        self.last_hour = Some(context.local_hour);
+        changed
+    }
+}
+
+pub struct OnMilestone;
+
+impl OnMilestone {
+    pub fn milestones_reached(clicks: u32) -> usize {
+        let listed = MILESTONES.iter().filter(|&&milestone| clicks >= milestone).count();
+        let last = MILESTONES[MILESTONES.len() - 1];
+        listed + (clicks.saturating_sub(last) / 1000) as usize
+    }
+}
+
+impl RotationPolicy for OnMilestone {
+    fn should_rotate(&mut self, context: &RotationContext) -> bool {
+        Self::milestones_reached(context.clicks) > Self::milestones_reached(context.previous_clicks)
+    }
+}
+
+/// Only changes when the player asks for it.
+pub struct Manual;
+
+impl RotationPolicy for Manual {
+    fn should_rotate(&mut self, _context: &RotationContext) -> bool {
+        false
+    }
+}
+
+/// Names the built-in policies without settings are registered under.
+pub const ON_HOUR: &str = "on_hour";
+pub const ON_MILESTONE: &str = "on_milestone";
+pub const MANUAL: &str = "manual";
+
+type PolicyFactory = Box<dyn Fn() -> Box<dyn RotationPolicy> + Send + Sync>;
+
+/// Policies registered by name, for `RotationSetting::Custom`.
+#[derive(Resource, Default)]
+pub struct RotationPolicies {
+    factories: HashMap<String, PolicyFactory>,
+}
+
+impl RotationPolicies {
+    pub fn register(&mut self, name: &str, factory: impl Fn() -> Box<dyn RotationPolicy> + Send + Sync + 'static) {
+        self.factories.insert(name.to_string(), Box::new(factory));
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.factories.contains_key(name)
+    }
+
+    /// Builds the policy for a setting. Unknown custom names fall back to the default.
+    pub fn build(&self, setting: &RotationSetting) -> Box<dyn RotationPolicy> {
+        match setting {
+            RotationSetting::EveryClicks(clicks) => Box::new(EveryClicks(*clicks)),
+            RotationSetting::EverySeconds(seconds) => Box::new(EverySeconds::new(*seconds)),
+            RotationSetting::OnHour => self.build_named(ON_HOUR),
+            RotationSetting::OnMilestone => self.build_named(ON_MILESTONE),
+            RotationSetting::Manual => self.build_named(MANUAL),
+            RotationSetting::Custom(name) => self.build_named(name),
+        }
+    }
+
+    fn build_named(&self, name: &str) -> Box<dyn RotationPolicy> {
+        match self.factories.get(name) {
+            Some(factory) => factory(),
+            None => {
+                println!("Unknown rotation policy '{}', using the default", name);
+                self.build(&RotationSetting::default())
+            }
+        }
+    }
+}
+
+pub trait RotationAppExt {
+    /// Makes a policy available as `RotationSetting::Custom(name)`.
+    fn register_rotation_policy(
+        &mut self,
+        name: &str,
+        factory: impl Fn() -> Box<dyn RotationPolicy> + Send + Sync + 'static,
+    ) -> &mut Self;
+}
+
+/// Registers the built-in policies that take no settings, unless the game has
+/// already registered its own under the same name, before or after adding
+/// the plugins.
+pub fn register_builtin_policies(app: &mut App) {
+    let registered = |app: &App, name: &str| {
+        app.world().get_resource::<RotationPolicies>().is_some_and(|policies| policies.contains(name))
+    };
+    if !registered(app, ON_HOUR) {
+        app.register_rotation_policy(ON_HOUR, || Box::new(OnHour::default()));
+    }
+    if !registered(app, ON_MILESTONE) {
+        app.register_rotation_policy(ON_MILESTONE, || Box::new(OnMilestone));
+    }
+    if !registered(app, MANUAL) {
+        app.register_rotation_policy(MANUAL, || Box::new(Manual));
+    }
+}
+
+impl RotationAppExt for App {
+    fn register_rotation_policy(
+        &mut self,
+        name: &str,
+        factory: impl Fn() -> Box<dyn RotationPolicy> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.world_mut()
+            .get_resource_or_insert_with(RotationPolicies::default)
+            .register(name, factory);
+        self
+    }
+}
+
+/// The policy currently deciding background changes.
+#[derive(Resource)]
+pub struct ActiveRotationPolicy(pub Box<dyn RotationPolicy>);
+
+impl Default for ActiveRotationPolicy {
+    fn default() -> Self {
+        Self(Box::new(EveryClicks(5)))
+    }
+}
+
+/// Rebuilds the active policy whenever the setting changes, including when
+/// a profile is loaded.
+pub fn update_rotation_policy_system(
+    settings: Res<ProfileSettings>,
+    policies: Res<RotationPolicies>,
+    mut active: ResMut<ActiveRotationPolicy>,
+    mut current: Local<Option<RotationSetting>>,
+) {
+    if current.as_ref() != Some(&settings.background_rotation) {
+        println!("Background rotation: {:?}", settings.background_rotation);
+        active.0 = policies.build(&settings.background_rotation);
+        *current = Some(settings.background_rotation.clone());
+    }
+}
@@ -0,0 +1,364 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::animation::{AnimationLibrary, AnimationState};
+use crate::background::ParallaxScroll;
+use crate::controls::{Action, ActionState};
+use crate::game::{AnimatedCat, CAT_HALF_HEIGHT, ClickCounter, ClickMultiplier, ClicksEarned, RestingAnimation, Statistics};
+use crate::rng::{GameRng, RngStream};
+use crate::settings::UserConfig;
+use crate::state::{AppState, Minigame};
+
+pub const CRATE_IMAGE: &str = "minigames/crate.png";
+const LIVES: u32 = 3;
+const RESULTS_SECONDS: f32 = 3.0;
+/// Where the cat runs, left of centre so obstacles can be seen coming
+const CAT_X: f32 = -380.0;
+/// Obstacles appear off the right of the design area and are removed off the left
+const SPAWN_X: f32 = 760.0;
+const DESPAWN_X: f32 = -760.0;
+/// Smaller than the sprite so near misses aren't hits
+pub const CAT_HITBOX: Vec2 = Vec2::new(36.0, 40.0);
+
+pub const START_SPEED: f32 = 300.0;
+pub const MAX_SPEED: f32 = 700.0;
+/// Speed gained per second of running
+const SPEED_RAMP: f32 = 8.0;
+const JUMP_VELOCITY: f32 = 620.0;
+const GRAVITY: f32 = -1600.0;
+const HIT_INVULNERABLE_SECONDS: f32 = 1.2;
+/// Distance run for one point
+const POINT_DISTANCE: f32 = 100.0;
+
+/// Running speed after `elapsed` seconds. The game gets steadily faster up to a cap.
+pub fn runner_speed(elapsed: f32) -> f32 {
+    (START_SPEED + SPEED_RAMP * elapsed).min(MAX_SPEED)
+}
+
+/// Distance to the next obstacle. The range stays at roughly the same time
+/// between obstacles, but shrinks a little as the game speeds up.
+pub fn obstacle_gap(speed: f32, rng: &mut impl Rng) -> f32 {
+    let difficulty = (speed - START_SPEED) / (MAX_SPEED - START_SPEED);
+    let seconds = rng.gen_range(0.9..1.8) * (1.0 - 0.35 * difficulty.clamp(0.0, 1.0));
+    speed * seconds
+}
+
+/// Axis-aligned overlap of two boxes given by their centres and sizes.
+pub fn boxes_overlap(a: Vec2, a_size: Vec2, b: Vec2, b_size: Vec2) -> bool {
+    let gap = (a - b).abs() - (a_size + b_size) / 2.0;
+    gap.x < 0.0 && gap.y < 0.0
+}
+
+/// One run of the endless runner.
+#[derive(Resource)]
+pub struct RunnerGame {
+    pub elapsed: f32,
+    pub distance: f32,
+    pub lives: u32,
+    pub score: u32,
+    jump_height: f32,
+    vertical_speed: f32,
+    invulnerable: f32,
+    next_obstacle: f32,
+    /// Counts down while the score is shown, once the cat is out of lives
+    results: Option<f32>,
+    start: Vec3,
+    floor_y: f32,
+}
+
+impl RunnerGame {
+    pub fn new(start: Vec3) -> Self {
+        Self {
+            elapsed: 0.0,
+            distance: 0.0,
+            lives: LIVES,
+            score: 0,
+            jump_height: 0.0,
+            vertical_speed: 0.0,
+            invulnerable: 0.0,
+            next_obstacle: START_SPEED,
+            results: None,
+            start,
+            floor_y: start.y,
+        }
+    }
+
+    pub fn on_ground(&self) -> bool {
+        self.jump_height <= 0.0
+    }
+}
+
+#[derive(Component)]
+pub struct Obstacle {
+    pub size: Vec2,
+    hit: bool,
+}
+
+#[derive(Component)]
+pub struct RunnerHud;
+
+pub struct RunnerPlugin;
+
+impl Plugin for RunnerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, start_runner_system.run_if(in_state(AppState::Playing).and_then(in_state(Minigame::None))))
+            .add_systems(OnEnter(Minigame::Runner), start_run)
+            .add_systems(OnExit(Minigame::Runner), end_run)
+            .add_systems(Update, (
+                runner_jump_system,
+                scroll_obstacles_system,
+                runner_collision_system,
+                runner_score_system,
+            ).chain().run_if(in_state(AppState::Playing).and_then(in_state(Minigame::Runner))));
+    }
+}
+
+/// The runner action, `E` by default, starts a run.
+pub fn start_runner_system(
+    actions: Res<ActionState>,
+    mut next_minigame: ResMut<NextState<Minigame>>,
+) {
+    if actions.just_pressed(Action::Runner) {
+        println!("Starting endless runner");
+        next_minigame.set(Minigame::Runner);
+    }
+}
+
+fn start_run(
+    mut commands: Commands,
+    config: Res<UserConfig>,
+    animation_library: Res<AnimationLibrary>,
+    mut cats: Query<(&mut Transform, &mut Sprite, &mut AnimationState), With<AnimatedCat>>,
+) {
+    let mut start = Vec3::ZERO;
+    if let Ok((mut transform, mut sprite, mut state)) = cats.get_single_mut() {
+        start = transform.translation;
+        transform.translation.x = CAT_X;
+        sprite.flip_x = false;
+        state.play_animation("run", &animation_library);
+    }
+    commands.insert_resource(RunnerGame::new(start));
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    padding: UiRect::top(Val::Px(16.0)),
+                    ..default()
+                },
+                ..default()
+            },
+            StateScoped(Minigame::Runner),
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    config.language.translate("Runner"),
+                    TextStyle {
+                        font_size: 32.0,
+                        color: Color::srgb(1.0, 0.85, 0.3),
+                        ..default()
+                    },
+                ),
+                RunnerHud,
+            ));
+        });
+}
+
+/// Puts the cat and the scenery back the way they were before the run.
+fn end_run(
+    mut commands: Commands,
+    game: Option<Res<RunnerGame>>,
+    animation_library: Res<AnimationLibrary>,
+    resting: Res<RestingAnimation>,
+    mut scroll: ResMut<ParallaxScroll>,
+    mut cats: Query<(&mut Transform, &mut AnimationState), With<AnimatedCat>>,
+) {
+    if let Some(game) = game
+        && let Ok((mut transform, mut state)) = cats.get_single_mut()
+    {
+        transform.translation = game.start;
+        state.play_animation(&resting.0, &animation_library);
+    }
+    scroll.0 = 0.0;
+    commands.remove_resource::<RunnerGame>();
+}
+
+/// The jump action (`Space`, `Up` or a click) jumps while the cat is on the ground.
+pub fn runner_jump_system(
+    time: Res<Time>,
+    actions: Res<ActionState>,
+    animation_library: Res<AnimationLibrary>,
+    mut game: ResMut<RunnerGame>,
+    mut cats: Query<(&mut Transform, &mut AnimationState), With<AnimatedCat>>,
+) {
+    let Ok((mut transform, mut state)) = cats.get_single_mut() else {
+        return;
+    };
+    let jump_pressed = actions.just_pressed(Action::Jump);
+    if jump_pressed && game.on_ground() && game.results.is_none() {
+        game.vertical_speed = JUMP_VELOCITY;
+        state.play_animation("jump", &animation_library);
+    }
+
+    let delta = time.delta_seconds();
+    if !game.on_ground() || game.vertical_speed > 0.0 {
+        game.vertical_speed += GRAVITY * delta;
+        game.jump_height = (game.jump_height + game.vertical_speed * delta).max(0.0);
+        if game.on_ground() {
+            game.vertical_speed = 0.0;
+            // Don't cut a damage animation short
+            if state.current_animation == "jump" {
+                state.play_animation("run", &animation_library);
+            }
+        }
+    }
+    transform.translation.y = game.floor_y + game.jump_height;
+}
+
+/// Moves the world past the cat, spawning obstacles at random gaps and
+/// speeding up over time.
+pub fn scroll_obstacles_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    mut game: ResMut<RunnerGame>,
+    mut scroll: ResMut<ParallaxScroll>,
+    mut obstacles: Query<(Entity, &mut Transform, &Obstacle)>,
+    mut rng: ResMut<GameRng>,
+) {
+    if game.results.is_some() {
+        return;
+    }
+    let delta = time.delta_seconds();
+    game.elapsed += delta;
+    let speed = runner_speed(game.elapsed);
+    let step = speed * delta;
+    game.distance += step;
+    scroll.0 += step;
+
+    for (entity, mut transform, _) in &mut obstacles {
+        transform.translation.x -= step;
+        if transform.translation.x < DESPAWN_X {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+
+    game.next_obstacle -= step;
+    if game.next_obstacle <= 0.0 {
+        let rng = rng.stream(RngStream::Ai);
+        game.next_obstacle = obstacle_gap(speed, rng);
+        let size = Vec2::new(rng.gen_range(30.0..50.0), rng.gen_range(28.0..64.0));
+        let floor = game.floor_y - CAT_HALF_HEIGHT;
+        commands.spawn((
+            SpriteBundle {
+                texture: asset_server.load(CRATE_IMAGE),
+                transform: Transform::from_xyz(SPAWN_X, floor + size.y / 2.0, 1.0),
+                sprite: Sprite {
+                    custom_size: Some(size),
+                    ..default()
+                },
+                ..default()
+            },
+            Obstacle { size, hit: false },
+            StateScoped(Minigame::Runner),
+        ));
+    }
+}
+
+/// Running into an obstacle costs a life and plays `damage`. The cat can't be
+/// hurt again for a moment afterwards.
+pub fn runner_collision_system(
+    time: Res<Time>,
+    animation_library: Res<AnimationLibrary>,
+    mut game: ResMut<RunnerGame>,
+    mut cats: Query<(&Transform, &mut AnimationState), With<AnimatedCat>>,
+    mut obstacles: Query<(&Transform, &mut Obstacle, &mut Sprite), Without<AnimatedCat>>,
+) {
+    game.invulnerable = (game.invulnerable - time.delta_seconds()).max(0.0);
+    let Ok((cat, mut state)) = cats.get_single_mut() else {
+        return;
+    };
+    if game.invulnerable > 0.0 || game.results.is_some() {
+        return;
+    }
+
+    for (transform, mut obstacle, mut sprite) in &mut obstacles {
+        if obstacle.hit
+            || !boxes_overlap(cat.translation.truncate(), CAT_HITBOX, transform.translation.truncate(), obstacle.size)
+        {
+            continue;
+        }
+        obstacle.hit = true;
+        sprite.color = Color::srgba(1.0, 1.0, 1.0, 0.5);
+        game.lives = game.lives.saturating_sub(1);
+        game.invulnerable = HIT_INVULNERABLE_SECONDS;
+        state.play_animation_then_return("damage", "run", 0.6, &animation_library);
+        println!("Runner: hit an obstacle, {} lives left", game.lives);
+        break;
+    }
+}
+
+/// Keeps the score up to date and pays it out into the click counter once
+/// the cat is out of lives.
+#[allow(clippy::too_many_arguments)]
+pub fn runner_score_system(
+    time: Res<Time>,
+    config: Res<UserConfig>,
+    multiplier: Res<ClickMultiplier>,
+    mut game: ResMut<RunnerGame>,
+    mut counter: ResMut<ClickCounter>,
+    mut stats: ResMut<Statistics>,
+    mut earned: EventWriter<ClicksEarned>,
+    mut huds: Query<&mut Text, With<RunnerHud>>,
+    mut next_minigame: ResMut<NextState<Minigame>>,
+) {
+    let language = config.language;
+    game.score = (game.distance / POINT_DISTANCE) as u32;
+
+    if game.lives == 0 && game.results.is_none() {
+        let amount = game.score * multiplier.0.max(1);
+        counter.0 += amount;
+        if amount > 0 {
+            earned.send(ClicksEarned { amount, position: Vec2::new(CAT_X, game.floor_y) });
+        }
+        if game.score > stats.best_runner_score {
+            stats.best_runner_score = game.score;
+        }
+        println!("Runner over: {} points, +{} clicks", game.score, amount);
+        game.results = Some(RESULTS_SECONDS);
+    }
+
+    let text = match game.results {
+        None => format!(
+            "{}: {}   {}: {}",
+            language.translate("Score"),
+            game.score,
+            language.translate("Lives"),
+            game.lives
+        ),
+        Some(remaining) => {
+            let remaining = remaining - time.delta_seconds();
+            game.results = Some(remaining);
+            if remaining <= 0.0 {
+                next_minigame.set(Minigame::None);
+            }
+            format!(
+                "{}! {}: {} (+{})   {}: {}",
+                language.translate("Game over"),
+                language.translate("Score"),
+                game.score,
+                game.score * multiplier.0.max(1),
+                language.translate("Best"),
+                stats.best_runner_score
+            )
+        }
+    };
+    for mut hud in &mut huds {
+        if hud.sections[0].value != text {
+            hud.sections[0].value = text.clone();
+        }
+    }
+}
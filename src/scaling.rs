@@ -0,0 +1,105 @@
+use bevy::prelude::*;
+use bevy::render::camera::ScalingMode as ProjectionScaling;
+use bevy::window::PrimaryWindow;
+use serde::{Deserialize, Serialize};
+
+use crate::background::{BackgroundScale, LAYER_SIZE};
+use crate::settings::UserConfig;
+
+/// The scene is laid out for this many world units, whatever the window size.
+pub const DESIGN_SIZE: Vec2 = Vec2::new(1280.0, 720.0);
+
+/// How the design area is mapped onto the window.
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScalingMode {
+    /// Show the whole design area, revealing more of the scene on the longer side
+    #[default]
+    Fit,
+    /// Fill the window, cropping the longer side of the design area
+    Fill,
+    /// Like `Fit` but only whole-number zoom levels, for crisp pixel art
+    Integer,
+}
+
+impl ScalingMode {
+    pub fn next(self) -> Self {
+        match self {
+            ScalingMode::Fit => ScalingMode::Fill,
+            ScalingMode::Fill => ScalingMode::Integer,
+            ScalingMode::Integer => ScalingMode::Fit,
+        }
+    }
+
+    /// Logical window pixels per world unit for a window of `window` logical
+    /// pixels at the given display scale factor.
+    pub fn pixels_per_unit(self, window: Vec2, scale_factor: f32) -> f32 {
+        let ratios = window / DESIGN_SIZE;
+        match self {
+            ScalingMode::Fit => ratios.min_element(),
+            ScalingMode::Fill => ratios.max_element(),
+            // Whole physical pixels per unit, never smaller than 1:1
+            ScalingMode::Integer => (ratios.min_element() * scale_factor).floor().max(1.0) / scale_factor,
+        }
+        .max(f32::EPSILON)
+    }
+
+    /// Size of the world area visible in the window.
+    pub fn visible_area(self, window: Vec2, scale_factor: f32) -> Vec2 {
+        window / self.pixels_per_unit(window, scale_factor)
+    }
+}
+
+/// How much the background has to grow to cover `visible` world units. The
+/// layers are tiled three wide and scroll by up to one tile, so two tiles of
+/// width are always on screen.
+pub fn background_cover_scale(visible: Vec2) -> f32 {
+    (visible.y / LAYER_SIZE.y).max(visible.x / (2.0 * LAYER_SIZE.x)).max(1.0)
+}
+
+pub struct ScalingPlugin;
+
+impl Plugin for ScalingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ScalingMode>()
+            .add_systems(Update, apply_scaling_system);
+    }
+}
+
+/// Keeps the camera projection, background size and UI scale in step with the
+/// window and the scaling mode.
+pub fn apply_scaling_system(
+    mode: Res<ScalingMode>,
+    config: Res<UserConfig>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut projections: Query<&mut OrthographicProjection, With<Camera2d>>,
+    mut background_scale: ResMut<BackgroundScale>,
+    mut ui_scale: ResMut<UiScale>,
+    mut last_applied: Local<Option<(ScalingMode, Vec2, f32, f32)>>,
+) {
+    let Ok(window) = windows.get_single() else {
+        return;
+    };
+    let size = Vec2::new(window.width(), window.height());
+    let scale_factor = window.scale_factor();
+    if size.min_element() <= 0.0 {
+        // Minimised
+        return;
+    }
+    let current = Some((*mode, size, scale_factor, config.ui_scale));
+    if *last_applied == current {
+        return;
+    }
+    *last_applied = current;
+
+    let pixels_per_unit = mode.pixels_per_unit(size, scale_factor);
+    for mut projection in &mut projections {
+        projection.scaling_mode = ProjectionScaling::WindowSize(pixels_per_unit);
+    }
+    background_scale.0 = background_cover_scale(mode.visible_area(size, scale_factor));
+    // Text grows with the window but always fits, whatever the mode
+    ui_scale.0 = ScalingMode::Fit.pixels_per_unit(size, scale_factor) * config.ui_scale;
+    println!(
+        "Scaling {:?}: {}x{} window, {:.2} px per unit",
+        *mode, size.x, size.y, pixels_per_unit
+    );
+}
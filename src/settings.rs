@@ -0,0 +1,694 @@
+use bevy::prelude::*;
+use bevy::window::{PrimaryWindow, WindowMode, WindowResized};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::audio::{AudioVolumes, PlaySound};
+use crate::controls::{Action, InputMap, Rebinding, capture_binding_system};
+use crate::profile::{ProfileError, ProfileSettings, read_ron_or_default, write_ron};
+use crate::state::AppState;
+use crate::scaling::ScalingMode;
+
+/// Lives next to the profiles but belongs to the player, not to any save.
+pub const CONFIG_FILE: &str = "config.ron";
+const VOLUME_STEP: f32 = 0.1;
+const UI_SCALES: [f32; 5] = [0.75, 1.0, 1.25, 1.5, 2.0];
+/// How long the window has to stay the same size before it is saved, so
+/// dragging the edge doesn't write the config every frame
+const RESIZE_SAVE_DELAY: f32 = 0.5;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WindowModeSetting {
+    #[default]
+    Windowed,
+    Borderless,
+    Fullscreen,
+}
+
+impl WindowModeSetting {
+    pub fn next(self) -> Self {
+        match self {
+            WindowModeSetting::Windowed => WindowModeSetting::Borderless,
+            WindowModeSetting::Borderless => WindowModeSetting::Fullscreen,
+            WindowModeSetting::Fullscreen => WindowModeSetting::Windowed,
+        }
+    }
+
+    pub fn window_mode(self) -> WindowMode {
+        match self {
+            WindowModeSetting::Windowed => WindowMode::Windowed,
+            WindowModeSetting::Borderless => WindowMode::BorderlessFullscreen,
+            WindowModeSetting::Fullscreen => WindowMode::Fullscreen,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Language {
+    #[default]
+    English,
+    Spanish,
+    German,
+}
+
+impl Language {
+    pub fn next(self) -> Self {
+        match self {
+            Language::English => Language::Spanish,
+            Language::Spanish => Language::German,
+            Language::German => Language::English,
+        }
+    }
+
+    /// Translates an English UI string. Anything without a translation is
+    /// shown in English.
+    pub fn translate(self, english: &'static str) -> &'static str {
+        let translated = match self {
+            Language::English => None,
+            Language::Spanish => match english {
+                "Clicks" => Some("Clics"),
+                "Settings" => Some("Ajustes"),
+                "Master volume" => Some("Volumen general"),
+                "Effects volume" => Some("Volumen de efectos"),
+                "Voice volume" => Some("Volumen de voces"),
+                "Music volume" => Some("Volumen de música"),
+                "Background rotation" => Some("Cambio de fondo"),
+                "Window mode" => Some("Modo de ventana"),
+                "Scaling" => Some("Escalado"),
+                "UI scale" => Some("Tamaño de la interfaz"),
+                "Reduce motion" => Some("Reducir movimiento"),
+                "Debug keys" => Some("Teclas de depuración"),
+                "Language" => Some("Idioma"),
+                "Close" => Some("Cerrar"),
+                "Paused" => Some("En pausa"),
+                "Resume" => Some("Continuar"),
+                "Main menu" => Some("Menú principal"),
+                "Loading" => Some("Cargando"),
+                "Laser chase" => Some("Caza del láser"),
+                "Catches" => Some("Capturas"),
+                "Time" => Some("Tiempo"),
+                "Round over" => Some("Fin de la ronda"),
+                "Best" => Some("Récord"),
+                "Runner" => Some("Carrera sin fin"),
+                "Score" => Some("Puntos"),
+                "Lives" => Some("Vidas"),
+                "Game over" => Some("Fin del juego"),
+                "Rhythm" => Some("Ritmo"),
+                "Combo" => Some("Combo"),
+                "Song over" => Some("Fin de la canción"),
+                "Perfect" => Some("¡Perfecto!"),
+                "Good" => Some("Bien"),
+                "Miss" => Some("Fallo"),
+                "Buy" => Some("Comprar"),
+                "Food" => Some("Comida"),
+                "Mood" => Some("Ánimo"),
+                "Toys" => Some("Juguetes"),
+                "Yarn ball" => Some("Ovillo"),
+                "Box" => Some("Caja"),
+                "Feather wand" => Some("Varita con pluma"),
+                "Golden cat!" => Some("¡Gato dorado!"),
+                "Surprise box!" => Some("¡Caja sorpresa!"),
+                "Zoomies!" => Some("¡Locura felina!"),
+                "Controls" => Some("Controles"),
+                "Back" => Some("Volver"),
+                "Reset" => Some("Restablecer"),
+                "Pet" => Some("Acariciar"),
+                "Pick up" => Some("Coger"),
+                "Pause" => Some("Pausa"),
+                "Food tray" => Some("Bandeja de comida"),
+                "Next background" => Some("Siguiente fondo"),
+                "Jump" => Some("Saltar"),
+                "Debug mode" => Some("Modo de depuración"),
+                "Parallax anchor" => Some("Ancla del paralaje"),
+                "Rotation policy" => Some("Regla de cambio de fondo"),
+                "Clock mode" => Some("Modo del reloj"),
+                "Theme" => Some("Tema"),
+                "Scaling mode" => Some("Modo de escalado"),
+                "Animation" => Some("Animación"),
+                "Press a key or button for" => Some("Pulsa una tecla o botón para"),
+                "Esc cancels" => Some("Esc cancela"),
+                "is already used by" => Some("ya se usa para"),
+                "Click an action to change its binding" => Some("Haz clic en una acción para cambiar su tecla"),
+                "On" => Some("Sí"),
+                "Off" => Some("No"),
+                _ => None,
+            },
+            Language::German => match english {
+                "Clicks" => Some("Klicks"),
+                "Settings" => Some("Einstellungen"),
+                "Master volume" => Some("Gesamtlautstärke"),
+                "Effects volume" => Some("Effektlautstärke"),
+                "Voice volume" => Some("Stimmenlautstärke"),
+                "Music volume" => Some("Musiklautstärke"),
+                "Background rotation" => Some("Hintergrundwechsel"),
+                "Window mode" => Some("Fenstermodus"),
+                "Scaling" => Some("Skalierung"),
+                "UI scale" => Some("Oberflächengröße"),
+                "Reduce motion" => Some("Bewegung reduzieren"),
+                "Debug keys" => Some("Debug-Tasten"),
+                "Language" => Some("Sprache"),
+                "Close" => Some("Schließen"),
+                "Paused" => Some("Pausiert"),
+                "Resume" => Some("Weiter"),
+                "Main menu" => Some("Hauptmenü"),
+                "Loading" => Some("Laden"),
+                "Laser chase" => Some("Laserjagd"),
+                "Catches" => Some("Fänge"),
+                "Time" => Some("Zeit"),
+                "Round over" => Some("Runde vorbei"),
+                "Best" => Some("Rekord"),
+                "Runner" => Some("Endloslauf"),
+                "Score" => Some("Punkte"),
+                "Lives" => Some("Leben"),
+                "Game over" => Some("Spiel vorbei"),
+                "Rhythm" => Some("Rhythmus"),
+                "Combo" => Some("Kombo"),
+                "Song over" => Some("Lied vorbei"),
+                "Perfect" => Some("Perfekt"),
+                "Good" => Some("Gut"),
+                "Miss" => Some("Daneben"),
+                "Buy" => Some("Kaufen"),
+                "Food" => Some("Futter"),
+                "Mood" => Some("Laune"),
+                "Toys" => Some("Spielzeug"),
+                "Yarn ball" => Some("Wollknäuel"),
+                "Box" => Some("Karton"),
+                "Feather wand" => Some("Federangel"),
+                "Golden cat!" => Some("Goldene Katze!"),
+                "Surprise box!" => Some("Überraschungskiste!"),
+                "Zoomies!" => Some("Katzenrennen!"),
+                "Controls" => Some("Steuerung"),
+                "Back" => Some("Zurück"),
+                "Reset" => Some("Zurücksetzen"),
+                "Pet" => Some("Streicheln"),
+                "Pick up" => Some("Hochheben"),
+                "Food tray" => Some("Futterablage"),
+                "Next background" => Some("Nächster Hintergrund"),
+                "Jump" => Some("Springen"),
+                "Debug mode" => Some("Debug-Modus"),
+                "Parallax anchor" => Some("Parallaxe-Anker"),
+                "Rotation policy" => Some("Hintergrundwechsel"),
+                "Clock mode" => Some("Uhrmodus"),
+                "Theme" => Some("Thema"),
+                "Scaling mode" => Some("Skalierungsmodus"),
+                "Press a key or button for" => Some("Taste drücken für"),
+                "Esc cancels" => Some("Esc bricht ab"),
+                "is already used by" => Some("ist schon belegt mit"),
+                "Click an action to change its binding" => Some("Aktion anklicken, um die Taste zu ändern"),
+                "On" => Some("An"),
+                "Off" => Some("Aus"),
+                _ => None,
+            },
+        };
+        translated.unwrap_or(english)
+    }
+}
+
+/// Player preferences shared by every profile, stored in `saves/config.ron`.
+#[derive(Resource, Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct UserConfig {
+    pub volumes: AudioVolumes,
+    pub window_mode: WindowModeSetting,
+    pub window_size: (f32, f32),
+    pub scaling: ScalingMode,
+    /// Multiplies the UI size chosen for the window
+    pub ui_scale: f32,
+    /// Turns off particles, sliding transitions and floating numbers' movement
+    pub reduce_motion: bool,
+    /// Whether `D` and the other debug keys work
+    pub debug_keys: bool,
+    pub language: Language,
+    /// Key, mouse and gamepad bindings for each action
+    pub controls: InputMap,
+    /// Seeds every random choice so a run can be replayed. A new seed is
+    /// picked each run if unset, and `--seed` overrides it.
+    pub seed: Option<u64>,
+}
+
+impl Default for UserConfig {
+    fn default() -> Self {
+        Self {
+            volumes: AudioVolumes::default(),
+            window_mode: WindowModeSetting::default(),
+            window_size: (1280.0, 720.0),
+            scaling: ScalingMode::default(),
+            ui_scale: 1.0,
+            reduce_motion: false,
+            debug_keys: true,
+            language: Language::default(),
+            controls: InputMap::default(),
+            seed: None,
+        }
+    }
+}
+
+impl UserConfig {
+    /// Reads the config from `root`. A missing file gives the defaults and a
+    /// broken one is reported and ignored.
+    pub fn load(root: &Path) -> Self {
+        read_ron_or_default(&root.join(CONFIG_FILE)).unwrap_or_else(|err| {
+            println!("Ignoring config: {}", err);
+            Self::default()
+        })
+    }
+
+    pub fn save(&self, root: &Path) -> Result<(), ProfileError> {
+        fs::create_dir_all(root)?;
+        write_ron(&root.join(CONFIG_FILE), self)
+    }
+
+    /// The primary window as configured, so it opens in the right mode.
+    pub fn window(&self) -> Window {
+        Window {
+            title: "Cat Clicker".to_string(),
+            resolution: self.window_size.into(),
+            mode: self.window_mode.window_mode(),
+            ..default()
+        }
+    }
+}
+
+/// Where the config is saved.
+#[derive(Resource)]
+pub struct ConfigPath(pub std::path::PathBuf);
+
+#[derive(Component)]
+pub struct SettingsPanel;
+
+#[derive(Component, Clone, Copy)]
+pub enum SettingsButton {
+    MasterVolume,
+    SfxVolume,
+    VoiceVolume,
+    MusicVolume,
+    Rotation,
+    WindowMode,
+    Scaling,
+    UiScale,
+    ReduceMotion,
+    DebugKeys,
+    Language,
+    Controls,
+    Rebind(Action),
+    ResetControls,
+    Back,
+    Close,
+}
+
+/// Which page of the settings menu is open.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SettingsPage {
+    #[default]
+    General,
+    Controls,
+}
+
+pub struct SettingsPlugin {
+    pub config: UserConfig,
+    pub root: std::path::PathBuf,
+}
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.config.clone())
+            .insert_resource(ConfigPath(self.root.clone()))
+            .init_resource::<SettingsPage>()
+            .add_systems(Update, (remember_window_size_system, apply_config_system).chain())
+            .add_systems(OnEnter(AppState::Settings), open_general_page)
+            .add_systems(Update, (
+                capture_binding_system,
+                settings_button_system,
+                refresh_settings_menu_system,
+            ).chain().run_if(in_state(AppState::Settings)));
+    }
+}
+
+/// Pushes config changes out to the rest of the game and saves them.
+pub fn apply_config_system(
+    config: Res<UserConfig>,
+    path: Res<ConfigPath>,
+    mut volumes: ResMut<AudioVolumes>,
+    mut scaling: ResMut<ScalingMode>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+    mut first_run: Local<bool>,
+) {
+    if !config.is_changed() {
+        return;
+    }
+    if *volumes != config.volumes {
+        *volumes = config.volumes.clone();
+    }
+    if *scaling != config.scaling {
+        *scaling = config.scaling;
+    }
+    for mut window in &mut windows {
+        let mode = config.window_mode.window_mode();
+        if window.mode != mode {
+            window.mode = mode;
+        }
+    }
+
+    // Nothing to write for the config that was just loaded
+    if !*first_run {
+        *first_run = true;
+        return;
+    }
+    if let Err(err) = config.save(&path.0) {
+        println!("Failed to save settings: {}", err);
+    }
+}
+
+/// Keeps `window_size` up to date with the player's resizing, so the window
+/// reopens at the same size. Only windowed sizes are kept.
+pub fn remember_window_size_system(
+    time: Res<Time>,
+    mut resized: EventReader<WindowResized>,
+    primary: Query<Entity, With<PrimaryWindow>>,
+    mut config: ResMut<UserConfig>,
+    mut pending: Local<Option<((f32, f32), f32)>>,
+) {
+    let primary = primary.get_single().ok();
+    for event in resized.read() {
+        if Some(event.window) == primary && event.width > 0.0 && event.height > 0.0 {
+            *pending = Some(((event.width, event.height), 0.0));
+        }
+    }
+
+    let Some((size, waited)) = pending.as_mut() else {
+        return;
+    };
+    *waited += time.delta_seconds();
+    if *waited < RESIZE_SAVE_DELAY {
+        return;
+    }
+    let size = *size;
+    *pending = None;
+    if config.window_mode == WindowModeSetting::Windowed && config.window_size != size {
+        // Saved by apply_config_system like any other change
+        config.window_size = size;
+    }
+}
+
+fn step_volume(volume: &mut f32) {
+    // Wrap from full back to silent
+    *volume = if *volume >= 1.0 - VOLUME_STEP / 2.0 { 0.0 } else { ((*volume + VOLUME_STEP) * 10.0).round() / 10.0 };
+}
+
+fn open_general_page(mut page: ResMut<SettingsPage>) {
+    *page = SettingsPage::General;
+}
+
+/// Each button moves its setting on to the next value. On the controls page
+/// a button waits for the action's new binding.
+#[allow(clippy::too_many_arguments)]
+pub fn settings_button_system(
+    buttons: Query<(&Interaction, &SettingsButton), Changed<Interaction>>,
+    mut config: ResMut<UserConfig>,
+    mut settings: ResMut<ProfileSettings>,
+    mut page: ResMut<SettingsPage>,
+    mut rebinding: ResMut<Rebinding>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut sounds: EventWriter<PlaySound>,
+) {
+    for (interaction, button) in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        sounds.send(PlaySound("ui_click".to_string()));
+        match button {
+            SettingsButton::MasterVolume => step_volume(&mut config.volumes.master),
+            SettingsButton::SfxVolume => step_volume(&mut config.volumes.sfx),
+            SettingsButton::VoiceVolume => step_volume(&mut config.volumes.voice),
+            SettingsButton::MusicVolume => step_volume(&mut config.volumes.music),
+            SettingsButton::Rotation => {
+                settings.background_rotation = settings.background_rotation.next_builtin();
+            }
+            SettingsButton::WindowMode => config.window_mode = config.window_mode.next(),
+            SettingsButton::Scaling => config.scaling = config.scaling.next(),
+            SettingsButton::UiScale => {
+                let next = UI_SCALES.iter().position(|scale| *scale > config.ui_scale + 0.01);
+                config.ui_scale = next.map(|index| UI_SCALES[index]).unwrap_or(UI_SCALES[0]);
+            }
+            SettingsButton::ReduceMotion => config.reduce_motion = !config.reduce_motion,
+            SettingsButton::DebugKeys => config.debug_keys = !config.debug_keys,
+            SettingsButton::Language => config.language = config.language.next(),
+            SettingsButton::Controls => *page = SettingsPage::Controls,
+            SettingsButton::Rebind(action) => {
+                *rebinding = Rebinding { action: Some(*action), conflict: None };
+            }
+            SettingsButton::ResetControls => {
+                config.controls = InputMap::default();
+                *rebinding = Rebinding::default();
+            }
+            SettingsButton::Back => {
+                *page = SettingsPage::General;
+                *rebinding = Rebinding::default();
+            }
+            SettingsButton::Close => next_state.set(AppState::Paused),
+        }
+    }
+}
+
+/// Builds the menu when it opens and rebuilds it whenever a setting changes.
+/// It is removed when leaving the settings state.
+pub fn refresh_settings_menu_system(
+    mut commands: Commands,
+    config: Res<UserConfig>,
+    settings: Res<ProfileSettings>,
+    page: Res<SettingsPage>,
+    rebinding: Res<Rebinding>,
+    panels: Query<Entity, With<SettingsPanel>>,
+) {
+    let changed = config.is_changed() || settings.is_changed() || page.is_changed() || rebinding.is_changed();
+    if !panels.is_empty() && !changed {
+        return;
+    }
+    for entity in &panels {
+        commands.entity(entity).despawn_recursive();
+    }
+    match *page {
+        SettingsPage::General => spawn_settings_menu(&mut commands, &config, &settings),
+        SettingsPage::Controls => spawn_controls_menu(&mut commands, &config, &rebinding),
+    }
+}
+
+/// Label and current value for each row of the menu.
+pub fn settings_rows(config: &UserConfig, settings: &ProfileSettings) -> Vec<(SettingsButton, &'static str, String)> {
+    let language = config.language;
+    let percent = |volume: f32| format!("{}%", (volume * 100.0).round());
+    let on_off = |on: bool| language.translate(if on { "On" } else { "Off" }).to_string();
+    vec![
+        (SettingsButton::MasterVolume, "Master volume", percent(config.volumes.master)),
+        (SettingsButton::SfxVolume, "Effects volume", percent(config.volumes.sfx)),
+        (SettingsButton::VoiceVolume, "Voice volume", percent(config.volumes.voice)),
+        (SettingsButton::MusicVolume, "Music volume", percent(config.volumes.music)),
+        (SettingsButton::Rotation, "Background rotation", format!("{:?}", settings.background_rotation)),
+        (SettingsButton::WindowMode, "Window mode", format!("{:?}", config.window_mode)),
+        (SettingsButton::Scaling, "Scaling", format!("{:?}", config.scaling)),
+        (SettingsButton::UiScale, "UI scale", format!("{}%", (config.ui_scale * 100.0).round())),
+        (SettingsButton::ReduceMotion, "Reduce motion", on_off(config.reduce_motion)),
+        (SettingsButton::DebugKeys, "Debug keys", on_off(config.debug_keys)),
+        (SettingsButton::Language, "Language", format!("{:?}", config.language)),
+    ]
+}
+
+fn spawn_settings_menu(commands: &mut Commands, config: &UserConfig, settings: &ProfileSettings) {
+    let language = config.language;
+    let text_style = |size: f32| TextStyle {
+        font_size: size,
+        color: Color::WHITE,
+        ..default()
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            z_index: ZIndex::Global(90),
+            ..default()
+        })
+        .insert((SettingsPanel, StateScoped(AppState::Settings)))
+        .with_children(|root| {
+            root.spawn(NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Stretch,
+                    row_gap: Val::Px(6.0),
+                    padding: UiRect::all(Val::Px(20.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.85)),
+                ..default()
+            })
+            .with_children(|panel| {
+                panel.spawn(TextBundle::from_section(language.translate("Settings"), text_style(32.0)));
+
+                for (button, label, value) in settings_rows(config, settings) {
+                    panel
+                        .spawn(NodeBundle {
+                            style: Style {
+                                justify_content: JustifyContent::SpaceBetween,
+                                align_items: AlignItems::Center,
+                                column_gap: Val::Px(24.0),
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .with_children(|row| {
+                            row.spawn(TextBundle::from_section(language.translate(label), text_style(20.0)));
+                            spawn_settings_button(row, &value, button);
+                        });
+                }
+
+                spawn_settings_button(panel, language.translate("Controls"), SettingsButton::Controls);
+                spawn_settings_button(panel, language.translate("Close"), SettingsButton::Close);
+            });
+        });
+}
+
+/// An action's name in the menus, with the digit for test animations.
+pub fn action_name(action: Action, language: Language) -> String {
+    match action {
+        Action::PlayAnimation(digit) => format!("{} {}", language.translate(action.label()), digit),
+        _ => language.translate(action.label()).to_string(),
+    }
+}
+
+/// The line above the controls: what to do next, or why a binding was refused.
+pub fn controls_hint(rebinding: &Rebinding, language: Language) -> String {
+    match (rebinding.action, rebinding.conflict) {
+        (Some(action), _) => format!(
+            "{}: {} ({})",
+            language.translate("Press a key or button for"),
+            action_name(action, language),
+            language.translate("Esc cancels")
+        ),
+        (None, Some((binding, other))) => format!(
+            "{} {} {}",
+            binding.label(),
+            language.translate("is already used by"),
+            action_name(other, language)
+        ),
+        (None, None) => language.translate("Click an action to change its binding").to_string(),
+    }
+}
+
+/// Every action and its bindings in two columns, with buttons to rebind them.
+fn spawn_controls_menu(commands: &mut Commands, config: &UserConfig, rebinding: &Rebinding) {
+    let language = config.language;
+    let text_style = |size: f32| TextStyle {
+        font_size: size,
+        color: Color::WHITE,
+        ..default()
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            z_index: ZIndex::Global(90),
+            ..default()
+        })
+        .insert((SettingsPanel, StateScoped(AppState::Settings)))
+        .with_children(|root| {
+            root.spawn(NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Stretch,
+                    row_gap: Val::Px(6.0),
+                    padding: UiRect::all(Val::Px(20.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.85)),
+                ..default()
+            })
+            .with_children(|panel| {
+                panel.spawn(TextBundle::from_section(language.translate("Controls"), text_style(32.0)));
+                panel.spawn(TextBundle::from_section(controls_hint(rebinding, language), text_style(16.0)));
+
+                panel
+                    .spawn(NodeBundle {
+                        style: Style {
+                            column_gap: Val::Px(32.0),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|columns| {
+                        for half in Action::ALL.chunks(Action::ALL.len().div_ceil(2)) {
+                            columns
+                                .spawn(NodeBundle {
+                                    style: Style {
+                                        flex_direction: FlexDirection::Column,
+                                        row_gap: Val::Px(2.0),
+                                        ..default()
+                                    },
+                                    ..default()
+                                })
+                                .with_children(|column| {
+                                    for action in half {
+                                        column
+                                            .spawn(NodeBundle {
+                                                style: Style {
+                                                    justify_content: JustifyContent::SpaceBetween,
+                                                    align_items: AlignItems::Center,
+                                                    column_gap: Val::Px(16.0),
+                                                    ..default()
+                                                },
+                                                ..default()
+                                            })
+                                            .with_children(|row| {
+                                                row.spawn(TextBundle::from_section(action_name(*action, language), text_style(16.0)));
+                                                let value = if rebinding.action == Some(*action) {
+                                                    "...".to_string()
+                                                } else {
+                                                    config.controls.describe(*action)
+                                                };
+                                                spawn_settings_button(row, &value, SettingsButton::Rebind(*action));
+                                            });
+                                    }
+                                });
+                        }
+                    });
+
+                spawn_settings_button(panel, language.translate("Reset"), SettingsButton::ResetControls);
+                spawn_settings_button(panel, language.translate("Back"), SettingsButton::Back);
+            });
+        });
+}
+
+fn spawn_settings_button(parent: &mut ChildBuilder, label: &str, button: SettingsButton) {
+    parent
+        .spawn(ButtonBundle {
+            style: Style {
+                padding: UiRect::axes(Val::Px(12.0), Val::Px(6.0)),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            background_color: BackgroundColor(Color::srgb(0.25, 0.25, 0.25)),
+            ..default()
+        })
+        .insert(button)
+        .with_children(|button| {
+            button.spawn(TextBundle::from_section(
+                label,
+                TextStyle {
+                    font_size: 20.0,
+                    color: Color::WHITE,
+                    ..default()
+                },
+            ));
+        });
+}
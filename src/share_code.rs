@@ -0,0 +1,132 @@
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use flate2::Compression;
+use flate2::read::DeflateDecoder;
+use flate2::write::DeflateEncoder;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::io::{Read, Write};
+
+use crate::profile::{MAX_NAME_LEN, Profile, ProfileError, ProfileStore};
+
+/// Version written into new share codes. Bump this when `SharedProfile` changes
+/// in a way older builds can't read.
+pub const SHARE_CODE_VERSION: u32 = 1;
+const PREFIX: &str = "CAT";
+// Refuse to inflate anything larger than this, a save is only a few hundred bytes
+const MAX_PAYLOAD_BYTES: u64 = 64 * 1024;
+
+/// What travels inside a share code.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SharedProfile {
+    pub name: String,
+    pub profile: Profile,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ShareCodeError {
+    Malformed,
+    NewerVersion(u32),
+    InvalidEncoding,
+    ChecksumMismatch,
+    Corrupt(String),
+}
+
+impl fmt::Display for ShareCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShareCodeError::Malformed => write!(f, "this doesn't look like a share code"),
+            ShareCodeError::NewerVersion(version) => write!(
+                f,
+                "share code is version {} but this game only understands up to version {}, please update",
+                version, SHARE_CODE_VERSION
+            ),
+            ShareCodeError::InvalidEncoding => {
+                write!(f, "share code contains characters that don't belong in one, check it was copied whole")
+            }
+            ShareCodeError::ChecksumMismatch => {
+                write!(f, "share code checksum doesn't match, it may be incomplete or mistyped")
+            }
+            ShareCodeError::Corrupt(err) => write!(f, "share code contents are damaged: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for ShareCodeError {}
+
+/// Encodes a profile as `CAT<version>:<crc32>:<base64 deflated RON>`.
+pub fn encode(shared: &SharedProfile) -> String {
+    let ron = ron::to_string(shared).expect("profiles always serialize");
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(ron.as_bytes()).expect("writing to a Vec can't fail");
+    let compressed = encoder.finish().expect("writing to a Vec can't fail");
+
+    format!(
+        "{}{}:{:08x}:{}",
+        PREFIX,
+        SHARE_CODE_VERSION,
+        crc32fast::hash(&compressed),
+        URL_SAFE_NO_PAD.encode(&compressed)
+    )
+}
+
+pub fn decode(code: &str) -> Result<SharedProfile, ShareCodeError> {
+    // Codes get pasted from chat and bug reports, so ignore stray whitespace
+    let code: String = code.chars().filter(|c| !c.is_whitespace()).collect();
+
+    let mut parts = code.splitn(3, ':');
+    let (Some(header), Some(checksum), Some(payload)) = (parts.next(), parts.next(), parts.next()) else {
+        return Err(ShareCodeError::Malformed);
+    };
+
+    let version: u32 = header
+        .strip_prefix(PREFIX)
+        .and_then(|version| version.parse().ok())
+        .filter(|version| *version > 0)
+        .ok_or(ShareCodeError::Malformed)?;
+    if version > SHARE_CODE_VERSION {
+        return Err(ShareCodeError::NewerVersion(version));
+    }
+
+    let checksum = u32::from_str_radix(checksum, 16).map_err(|_| ShareCodeError::Malformed)?;
+    let compressed = URL_SAFE_NO_PAD.decode(payload).map_err(|_| ShareCodeError::InvalidEncoding)?;
+    if crc32fast::hash(&compressed) != checksum {
+        return Err(ShareCodeError::ChecksumMismatch);
+    }
+
+    let mut ron = String::new();
+    DeflateDecoder::new(compressed.as_slice())
+        .take(MAX_PAYLOAD_BYTES)
+        .read_to_string(&mut ron)
+        .map_err(|err| ShareCodeError::Corrupt(err.to_string()))?;
+    ron::from_str(&ron).map_err(|err| ShareCodeError::Corrupt(err.to_string()))
+}
+
+pub fn export_profile(store: &ProfileStore, name: &str) -> Result<String, ProfileError> {
+    let profile = store.load(name)?;
+    Ok(encode(&SharedProfile { name: name.to_string(), profile }))
+}
+
+/// Imports a share code as a new profile and returns the name it was stored under.
+/// If the original name is taken a numbered suffix is added.
+pub fn import_profile(store: &ProfileStore, code: &str) -> Result<String, Box<dyn std::error::Error>> {
+    let shared = decode(code)?;
+    let base = if ProfileStore::validate_name(&shared.name).is_ok() {
+        shared.name.clone()
+    } else {
+        "Imported".to_string()
+    };
+
+    let mut name = base.clone();
+    let mut suffix = 2;
+    while store.exists(&name) {
+        let tail = format!(" {}", suffix);
+        let head: String = base.chars().take(MAX_NAME_LEN - tail.len()).collect();
+        name = format!("{}{}", head.trim_end(), tail);
+        suffix += 1;
+    }
+
+    store.create(&name)?;
+    store.save(&name, &shared.profile)?;
+    Ok(name)
+}
@@ -0,0 +1,209 @@
+use bevy::prelude::*;
+
+use crate::audio::PlaySound;
+use crate::controls::{Action, ActionState};
+use crate::feeding::{CatNeeds, Inventory};
+use crate::game::{ClickCounter, Statistics};
+use crate::profile::{ProfileManager, ProfilePickerState, ProfileSettings, save_active_profile};
+use crate::settings::UserConfig;
+
+/// The top-level screens of the game.
+#[derive(States, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum AppState {
+    /// Waiting for the assets the game needs
+    #[default]
+    Loading,
+    /// The profile picker
+    MainMenu,
+    Playing,
+    Paused,
+    Settings,
+}
+
+/// Present while a profile is loaded, whether or not the game is paused.
+/// Entities scoped to it live from pressing Play until returning to the menu.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct InGame;
+
+impl ComputedStates for InGame {
+    type SourceStates = AppState;
+
+    fn compute(state: AppState) -> Option<Self> {
+        matches!(state, AppState::Playing | AppState::Paused | AppState::Settings).then_some(InGame)
+    }
+}
+
+/// A mini-game played in place of the usual clicking. Only used in game, and
+/// reset when returning to the main menu.
+#[derive(States, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Minigame {
+    #[default]
+    None,
+    LaserChase,
+    Runner,
+    Rhythm,
+}
+
+#[derive(Component)]
+pub struct PauseMenu;
+
+#[derive(Component, Clone, Copy)]
+pub enum PauseButton {
+    Resume,
+    Settings,
+    MainMenu,
+}
+
+pub struct StatePlugin;
+
+impl Plugin for StatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<AppState>()
+            .add_computed_state::<InGame>()
+            .enable_state_scoped_entities::<InGame>()
+            .enable_state_scoped_entities::<AppState>()
+            .init_state::<Minigame>()
+            .enable_state_scoped_entities::<Minigame>()
+            .add_systems(OnExit(InGame), end_minigame)
+            .add_systems(Update, escape_key_system.run_if(in_state(InGame)))
+            .add_systems(OnEnter(AppState::Playing), resume_time)
+            .add_systems(OnExit(InGame), resume_time)
+            .add_systems(OnEnter(AppState::Paused), (pause_time, spawn_pause_menu))
+            .add_systems(Update, pause_button_system.run_if(in_state(AppState::Paused)));
+    }
+}
+
+/// The pause action, `Esc` by default, pauses and resumes, and backs out of
+/// the settings to the pause menu.
+pub fn escape_key_system(
+    actions: Res<ActionState>,
+    state: Res<State<AppState>>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if !actions.just_pressed(Action::Pause) {
+        return;
+    }
+    match state.get() {
+        AppState::Playing => next_state.set(AppState::Paused),
+        AppState::Paused => next_state.set(AppState::Playing),
+        AppState::Settings => next_state.set(AppState::Paused),
+        AppState::Loading | AppState::MainMenu => {}
+    }
+}
+
+/// Freezes every timer driven by game time: animations, rotation timers,
+/// the accelerated clock, particles and transitions. Time stays frozen in the
+/// settings, which are only reachable from the pause menu.
+pub fn pause_time(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
+
+pub fn resume_time(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
+
+fn end_minigame(mut next_minigame: ResMut<NextState<Minigame>>) {
+    next_minigame.set(Minigame::None);
+}
+
+fn spawn_pause_menu(mut commands: Commands, config: Res<UserConfig>) {
+    let language = config.language;
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.4)),
+                z_index: ZIndex::Global(80),
+                ..default()
+            },
+            PauseMenu,
+            StateScoped(AppState::Paused),
+        ))
+        .with_children(|root| {
+            root.spawn(NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Stretch,
+                    row_gap: Val::Px(8.0),
+                    padding: UiRect::all(Val::Px(20.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.85)),
+                ..default()
+            })
+            .with_children(|panel| {
+                panel.spawn(TextBundle::from_section(
+                    language.translate("Paused"),
+                    TextStyle {
+                        font_size: 32.0,
+                        color: Color::WHITE,
+                        ..default()
+                    },
+                ));
+                for (label, button) in [
+                    ("Resume", PauseButton::Resume),
+                    ("Settings", PauseButton::Settings),
+                    ("Main menu", PauseButton::MainMenu),
+                ] {
+                    panel
+                        .spawn(ButtonBundle {
+                            style: Style {
+                                padding: UiRect::axes(Val::Px(12.0), Val::Px(6.0)),
+                                justify_content: JustifyContent::Center,
+                                ..default()
+                            },
+                            background_color: BackgroundColor(Color::srgb(0.25, 0.25, 0.25)),
+                            ..default()
+                        })
+                        .insert(button)
+                        .with_children(|button| {
+                            button.spawn(TextBundle::from_section(
+                                language.translate(label),
+                                TextStyle {
+                                    font_size: 20.0,
+                                    color: Color::WHITE,
+                                    ..default()
+                                },
+                            ));
+                        });
+                }
+            });
+        });
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn pause_button_system(
+    buttons: Query<(&Interaction, &PauseButton), Changed<Interaction>>,
+    mut next_state: ResMut<NextState<AppState>>,
+    mut manager: ResMut<ProfileManager>,
+    mut picker: ResMut<ProfilePickerState>,
+    counter: Res<ClickCounter>,
+    inventory: Res<Inventory>,
+    needs: Res<CatNeeds>,
+    settings: Res<ProfileSettings>,
+    stats: Res<Statistics>,
+    mut sounds: EventWriter<PlaySound>,
+) {
+    for (interaction, button) in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        sounds.send(PlaySound("ui_click".to_string()));
+        match button {
+            PauseButton::Resume => next_state.set(AppState::Playing),
+            PauseButton::Settings => next_state.set(AppState::Settings),
+            PauseButton::MainMenu => {
+                // Save and unload the profile so another one can be picked
+                save_active_profile(&manager, &counter, &inventory, &needs, &settings, &stats);
+                picker.selected = manager.active.take();
+                next_state.set(AppState::MainMenu);
+            }
+        }
+    }
+}
@@ -0,0 +1,353 @@
+use bevy::asset::io::file::FileAssetReader;
+use bevy::prelude::*;
+use chrono::Datelike;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use crate::background::{BackgroundConfig, display_name};
+use crate::daynight::GameClock;
+use crate::game::{AnimatedCat, Statistics};
+use crate::profile::ProfileSettings;
+use crate::rng::{GameRng, RngStream};
+use crate::transition::ChangeBackground;
+
+const THEMES_DIR: &str = "themes";
+const MANIFEST_FILE: &str = "theme.ron";
+/// The summer backgrounds that ship in `assets/backgrounds`, used when no
+/// other theme matches.
+pub const DEFAULT_THEME: &str = "summer";
+const DEFAULT_BACKGROUNDS_DIR: &str = "backgrounds";
+// Just in front of the cat sprite
+const ACCESSORY_Z: f32 = 0.1;
+
+/// An inclusive span of the year as (month, day) pairs. `from` may be later
+/// than `to` for ranges that wrap around the new year. End a range on (2, 29)
+/// to include leap days.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct DateRange {
+    pub from: (u32, u32),
+    pub to: (u32, u32),
+}
+
+impl DateRange {
+    pub fn contains(&self, month: u32, day: u32) -> bool {
+        let date = (month, day);
+        if self.from <= self.to {
+            self.from <= date && date <= self.to
+        } else {
+            date >= self.from || date <= self.to
+        }
+    }
+
+    /// Rough length in days, only used to let short holidays win over seasons.
+    fn span(&self) -> u32 {
+        let day_of_year = |(month, day): (u32, u32)| month * 31 + day;
+        let (from, to) = (day_of_year(self.from), day_of_year(self.to));
+        if from <= to { to - from } else { 13 * 31 - from + to }
+    }
+}
+
+/// Text colours used over the scene.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(default)]
+pub struct ThemeColors {
+    /// Counter text during the morning and day
+    pub text: [f32; 3],
+    /// Counter text at dusk and night
+    pub night_text: [f32; 3],
+}
+
+impl Default for ThemeColors {
+    fn default() -> Self {
+        Self { text: [0.0, 0.0, 0.0], night_text: [0.95, 0.95, 1.0] }
+    }
+}
+
+/// An image worn by the cat while the theme is active.
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct ThemeAccessory {
+    /// Image path relative to the theme folder
+    pub image: String,
+    /// Position relative to the centre of the cat sprite
+    #[serde(default)]
+    pub offset: (f32, f32),
+    pub size: (f32, f32),
+}
+
+/// `theme.ron` at the top of a theme folder. Everything is optional.
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct ThemeManifest {
+    pub name: String,
+    /// Folder of backgrounds relative to `assets/`, defaults to the theme's
+    /// own `backgrounds` folder
+    pub backgrounds: String,
+    /// When the theme is picked automatically
+    pub dates: Vec<DateRange>,
+    /// RGB multiplier applied to every background on top of its own tint
+    pub tint: Option<[f32; 3]>,
+    pub colors: ThemeColors,
+    pub accessory: Option<ThemeAccessory>,
+    /// Name of an ambient particle effect to play while the theme is active
+    pub particles: Option<String>,
+}
+
+/// A loaded theme pack.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Theme {
+    /// Folder name, also what `ThemeChoice::Named` refers to
+    pub id: String,
+    pub name: String,
+    pub backgrounds_dir: String,
+    pub dates: Vec<DateRange>,
+    pub tint: Color,
+    pub colors: ThemeColors,
+    /// Accessory with its image resolved to an asset path
+    pub accessory: Option<ThemeAccessory>,
+    pub particles: Option<String>,
+}
+
+impl Theme {
+    /// The built-in summer theme.
+    pub fn summer() -> Self {
+        Self {
+            id: DEFAULT_THEME.to_string(),
+            name: "Summer".to_string(),
+            backgrounds_dir: DEFAULT_BACKGROUNDS_DIR.to_string(),
+            dates: Vec::new(),
+            tint: Color::WHITE,
+            colors: ThemeColors::default(),
+            accessory: None,
+            particles: None,
+        }
+    }
+
+    pub fn load(id: &str) -> Result<Self, String> {
+        let folder = format!("{}/{}", THEMES_DIR, id);
+        let path = FileAssetReader::get_base_path().join("assets").join(&folder).join(MANIFEST_FILE);
+        let contents = fs::read_to_string(&path).map_err(|err| format!("{}/{}: {}", folder, MANIFEST_FILE, err))?;
+        let manifest = ron::from_str(&contents).map_err(|err| format!("{}/{}: {}", folder, MANIFEST_FILE, err))?;
+        Ok(Self::from_manifest(id, manifest))
+    }
+
+    pub fn from_manifest(id: &str, manifest: ThemeManifest) -> Self {
+        let folder = format!("{}/{}", THEMES_DIR, id);
+        let [r, g, b] = manifest.tint.unwrap_or([1.0, 1.0, 1.0]);
+        Self {
+            id: id.to_string(),
+            name: if manifest.name.is_empty() { display_name(id) } else { manifest.name },
+            backgrounds_dir: if manifest.backgrounds.is_empty() {
+                format!("{}/backgrounds", folder)
+            } else {
+                manifest.backgrounds
+            },
+            dates: manifest.dates,
+            tint: Color::srgb(r, g, b),
+            colors: manifest.colors,
+            accessory: manifest.accessory.map(|accessory| ThemeAccessory {
+                image: format!("{}/{}", folder, accessory.image),
+                ..accessory
+            }),
+            particles: manifest.particles,
+        }
+    }
+
+    pub fn text_color(&self, night: bool) -> Color {
+        let [r, g, b] = if night { self.colors.night_text } else { self.colors.text };
+        Color::srgb(r, g, b)
+    }
+
+    fn span_on(&self, month: u32, day: u32) -> Option<u32> {
+        self.dates.iter().filter(|range| range.contains(month, day)).map(DateRange::span).min()
+    }
+}
+
+/// Which theme a profile wants.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub enum ThemeChoice {
+    /// Follow the calendar
+    #[default]
+    Auto,
+    Named(String),
+}
+
+/// Every theme found under `assets/themes`, plus the built-in summer theme.
+#[derive(Resource)]
+pub struct ThemeLibrary {
+    themes: Vec<Theme>,
+}
+
+impl ThemeLibrary {
+    pub fn new(themes: Vec<Theme>) -> Self {
+        let mut all = vec![Theme::summer()];
+        all.extend(themes.into_iter().filter(|theme| theme.id != DEFAULT_THEME));
+        Self { themes: all }
+    }
+
+    /// Loads every folder in `assets/themes` that has a manifest. Broken
+    /// manifests are reported and skipped.
+    pub fn discover() -> Self {
+        let root = FileAssetReader::get_base_path().join("assets").join(THEMES_DIR);
+        let mut ids: Vec<String> = fs::read_dir(&root)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.path().join(MANIFEST_FILE).is_file())
+                    .filter_map(|entry| entry.file_name().into_string().ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+        ids.sort();
+
+        let themes = ids
+            .iter()
+            .filter_map(|id| match Theme::load(id) {
+                Ok(theme) => Some(theme),
+                Err(err) => {
+                    println!("Skipping theme: {}", err);
+                    None
+                }
+            })
+            .collect();
+        Self::new(themes)
+    }
+
+    pub fn themes(&self) -> &[Theme] {
+        &self.themes
+    }
+
+    pub fn get(&self, id: &str) -> Option<&Theme> {
+        self.themes.iter().find(|theme| theme.id == id)
+    }
+
+    /// The theme for a date. When several match, the one with the shortest
+    /// date range wins so holidays take over from their season.
+    pub fn for_date(&self, month: u32, day: u32) -> &Theme {
+        self.themes
+            .iter()
+            .filter_map(|theme| theme.span_on(month, day).map(|span| (span, theme)))
+            .min_by_key(|(span, _)| *span)
+            .map(|(_, theme)| theme)
+            .unwrap_or(&self.themes[0])
+    }
+
+    /// Unknown names fall back to the calendar.
+    pub fn resolve(&self, choice: &ThemeChoice, month: u32, day: u32) -> &Theme {
+        match choice {
+            ThemeChoice::Named(id) => self.get(id).unwrap_or_else(|| self.for_date(month, day)),
+            ThemeChoice::Auto => self.for_date(month, day),
+        }
+    }
+
+    /// The choice after `choice` for the debug key: auto, then each theme in turn.
+    pub fn next_choice(&self, choice: &ThemeChoice) -> ThemeChoice {
+        let next = match choice {
+            ThemeChoice::Auto => 0,
+            ThemeChoice::Named(id) => match self.themes.iter().position(|theme| &theme.id == id) {
+                Some(index) => index + 1,
+                None => self.themes.len(),
+            },
+        };
+        match self.themes.get(next) {
+            Some(theme) => ThemeChoice::Named(theme.id.clone()),
+            None => ThemeChoice::Auto,
+        }
+    }
+}
+
+/// The theme currently in use.
+#[derive(Resource)]
+pub struct ActiveTheme(pub Theme);
+
+/// Sprite showing the theme's accessory on the cat.
+#[derive(Component)]
+pub struct CatAccessory;
+
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        let library = ThemeLibrary::discover();
+        let today = chrono::Local::now();
+        let theme = library.for_date(today.month(), today.day()).clone();
+        println!("Theme: {} ({} themes available)", theme.name, library.themes().len());
+
+        // Start on the theme's backgrounds rather than switching after the first frame
+        app.insert_resource(BackgroundConfig::discover(&theme.backgrounds_dir))
+            .insert_resource(library)
+            .insert_resource(ActiveTheme(theme))
+            .add_systems(Update, (select_theme_system, apply_theme_accessory_system).chain());
+    }
+}
+
+/// Switches theme when the profile's choice changes or the date moves into
+/// another theme's range.
+#[allow(clippy::too_many_arguments)]
+pub fn select_theme_system(
+    settings: Res<ProfileSettings>,
+    library: Res<ThemeLibrary>,
+    stats: Res<Statistics>,
+    clock: Res<GameClock>,
+    mut active: ResMut<ActiveTheme>,
+    mut background_config: ResMut<BackgroundConfig>,
+    mut change_events: EventWriter<ChangeBackground>,
+    mut rng: ResMut<GameRng>,
+) {
+    let today = chrono::Local::now();
+    let wanted = library.resolve(&settings.theme, today.month(), today.day());
+    if wanted.id == active.0.id {
+        return;
+    }
+
+    println!(
+        "Switching theme to {} (particles: {})",
+        wanted.name,
+        wanted.particles.as_deref().unwrap_or("none")
+    );
+    active.0 = wanted.clone();
+    *background_config = BackgroundConfig::discover(&wanted.backgrounds_dir);
+    if let Some(background) = background_config.next_background(&stats, clock.phase(), rng.stream(RngStream::Backgrounds)) {
+        change_events.send(ChangeBackground(background.clone()));
+    }
+}
+
+/// Puts the theme's accessory on the cat, replacing the previous one.
+pub fn apply_theme_accessory_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    active: Res<ActiveTheme>,
+    cats: Query<Entity, With<AnimatedCat>>,
+    accessories: Query<Entity, With<CatAccessory>>,
+    mut applied_to: Local<Option<Entity>>,
+) {
+    let Ok(cat) = cats.get_single() else {
+        return;
+    };
+    // The cat is spawned when a profile is loaded, so also run for each new cat
+    if !active.is_changed() && *applied_to == Some(cat) {
+        return;
+    }
+    *applied_to = Some(cat);
+
+    for entity in &accessories {
+        commands.entity(entity).despawn_recursive();
+    }
+    let Some(accessory) = &active.0.accessory else {
+        return;
+    };
+    let accessory_entity = commands
+        .spawn((
+            SpriteBundle {
+                texture: asset_server.load(accessory.image.clone()),
+                transform: Transform::from_xyz(accessory.offset.0, accessory.offset.1, ACCESSORY_Z),
+                sprite: Sprite {
+                    custom_size: Some(Vec2::new(accessory.size.0, accessory.size.1)),
+                    ..default()
+                },
+                ..default()
+            },
+            CatAccessory,
+        ))
+        .id();
+    commands.entity(cat).add_child(accessory_entity);
+}
@@ -0,0 +1,491 @@
+use bevy::prelude::*;
+use rand::Rng;
+
+use crate::animation::{AnimationLibrary, AnimationState};
+use crate::background::{ActiveBackground, BackgroundScale};
+use crate::controls::{Action, ActionState};
+use crate::game::{AnimatedCat, CAT_HALF_HEIGHT, RestingAnimation, cursor_world_position};
+use crate::gesture::{GestureRecognizer, recognize_gestures_system};
+use crate::laser::{ChaseMove, play_if_different};
+use crate::pickup::{CARRY_SMOOTHING, CatBody, GRAVITY, MAX_THROW_SPEED, body_bounds, visible_world_rect};
+use crate::rng::{GameRng, RngStream};
+use crate::settings::UserConfig;
+use crate::state::{AppState, InGame, Minigame};
+
+/// How long the cat stays interested in a toy after it was last handled
+pub const ATTENTION_SECONDS: f32 = 6.0;
+// Slower than this a toy stops bouncing and settles on the floor
+const SETTLE_SPEED: f32 = 60.0;
+// Just in front of the cat
+const TOY_Z: f32 = 0.2;
+// New toys drop in from this far above the floor
+const SPAWN_HEIGHT: f32 = 250.0;
+// How long the cat plays with each toy before looking for the next one
+const BAT_SECONDS: f32 = 1.2;
+const POUNCE_SECONDS: f32 = 0.8;
+const BOX_SECONDS: f32 = 4.0;
+// Push the yarn ball gets when the cat bats it away
+const BAT_IMPULSE: Vec2 = Vec2::new(350.0, 450.0);
+const FEATHER_FLICK: Vec2 = Vec2::new(80.0, 500.0);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToyKind {
+    Yarn,
+    Box,
+    Feather,
+}
+
+/// How a toy moves.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ToyPhysics {
+    /// Share of its speed kept after hitting the floor or a wall
+    pub bounce: f32,
+    /// How quickly sliding or rolling along the floor slows it, per second
+    pub friction: f32,
+    pub gravity_scale: f32,
+    /// How quickly the air slows it, per second
+    pub drag: f32,
+    /// Spins as it moves, like a ball
+    pub rolls: bool,
+}
+
+impl ToyKind {
+    pub const ALL: [ToyKind; 3] = [ToyKind::Yarn, ToyKind::Box, ToyKind::Feather];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ToyKind::Yarn => "Yarn ball",
+            ToyKind::Box => "Box",
+            ToyKind::Feather => "Feather wand",
+        }
+    }
+
+    pub fn image(self) -> &'static str {
+        match self {
+            ToyKind::Yarn => "toys/yarn.png",
+            ToyKind::Box => "toys/box.png",
+            ToyKind::Feather => "toys/feather.png",
+        }
+    }
+
+    pub fn size(self) -> Vec2 {
+        match self {
+            ToyKind::Yarn => Vec2::new(32.0, 32.0),
+            ToyKind::Box => Vec2::new(64.0, 48.0),
+            ToyKind::Feather => Vec2::new(24.0, 72.0),
+        }
+    }
+
+    pub fn physics(self) -> ToyPhysics {
+        match self {
+            ToyKind::Yarn => ToyPhysics { bounce: 0.7, friction: 1.2, gravity_scale: 1.0, drag: 0.0, rolls: true },
+            ToyKind::Box => ToyPhysics { bounce: 0.15, friction: 8.0, gravity_scale: 1.0, drag: 0.0, rolls: false },
+            // Drifts down slowly
+            ToyKind::Feather => ToyPhysics { bounce: 0.3, friction: 4.0, gravity_scale: 0.35, drag: 1.5, rolls: false },
+        }
+    }
+}
+
+/// A prop that can be thrown around and that the cat plays with.
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub struct Toy {
+    pub kind: ToyKind,
+    pub velocity: Vec2,
+    /// Offset from the cursor while the player is holding it
+    pub held: Option<Vec2>,
+    /// Seconds left before the cat loses interest
+    pub attention: f32,
+}
+
+impl Toy {
+    pub fn new(kind: ToyKind) -> Self {
+        Self { kind, velocity: Vec2::ZERO, held: None, attention: ATTENTION_SECONDS }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.attention > 0.0
+    }
+
+    /// Moves a loose toy on by `seconds` under gravity, bouncing off the edges
+    /// of `bounds`, the area its centre may be in.
+    pub fn step(&mut self, position: &mut Vec2, bounds: Rect, seconds: f32) {
+        let physics = self.kind.physics();
+        self.velocity.y -= GRAVITY * physics.gravity_scale * seconds;
+        self.velocity *= (-physics.drag * seconds).exp();
+        *position += self.velocity * seconds;
+
+        if position.x < bounds.min.x || position.x > bounds.max.x {
+            position.x = position.x.clamp(bounds.min.x, bounds.max.x);
+            self.velocity.x *= -physics.bounce;
+        }
+        if position.y > bounds.max.y {
+            position.y = bounds.max.y;
+            self.velocity.y = -self.velocity.y.abs() * physics.bounce;
+        }
+        if position.y <= bounds.min.y {
+            position.y = bounds.min.y;
+            self.velocity.y = if self.velocity.y < -SETTLE_SPEED { -self.velocity.y * physics.bounce } else { 0.0 };
+            self.velocity.x *= (-physics.friction * seconds).exp();
+            if self.velocity.x.abs() < 1.0 {
+                self.velocity.x = 0.0;
+            }
+        }
+    }
+}
+
+/// What the cat is doing with the toys.
+#[derive(Resource, Default)]
+pub struct ToyPlay {
+    /// Seconds left playing with a toy before looking for another
+    busy: f32,
+    /// The box the cat is sitting in, which is hidden meanwhile
+    in_box: Option<Entity>,
+    chasing: bool,
+}
+
+#[derive(Component)]
+pub struct ToyMenu;
+
+/// Puts a toy out, or away again if it is already out.
+#[derive(Component, Clone, Copy)]
+pub struct ToyButton(pub ToyKind);
+
+pub struct ToyPlugin;
+
+impl Plugin for ToyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ToyPlay>()
+            .add_systems(OnEnter(InGame), spawn_toy_menu)
+            .add_systems(OnExit(Minigame::None), stop_playing)
+            .add_systems(Update, (
+                toy_button_system,
+                grab_toy_system,
+                move_toys_system,
+                play_with_toys_system,
+            ).chain()
+                // A toy grabbed in front of the cat takes the click from it
+                .before(recognize_gestures_system)
+                .run_if(in_state(AppState::Playing).and_then(in_state(Minigame::None))))
+            .add_systems(Update, toy_visibility_system.run_if(in_state(InGame)));
+    }
+}
+
+/// Where the centre of a toy may go. Toys rest on the same floor as the cat.
+pub fn toy_bounds(visible: Rect, kind: ToyKind, active: &ActiveBackground, scale: &BackgroundScale) -> Rect {
+    let half_size = kind.size() / 2.0;
+    let ground = active.floor_y(scale) - CAT_HALF_HEIGHT;
+    body_bounds(visible, half_size, ground + half_size.y)
+}
+
+fn spawn_toy_menu(mut commands: Commands, config: Res<UserConfig>) {
+    let text_style = |size: f32| TextStyle {
+        font_size: size,
+        color: Color::WHITE,
+        ..default()
+    };
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(12.0),
+                    bottom: Val::Px(12.0),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(6.0),
+                    padding: UiRect::all(Val::Px(8.0)),
+                    ..default()
+                },
+                background_color: BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.6)),
+                ..default()
+            },
+            ToyMenu,
+            StateScoped(InGame),
+        ))
+        .with_children(|menu| {
+            menu.spawn(TextBundle::from_section(config.language.translate("Toys"), text_style(16.0)));
+            for kind in ToyKind::ALL {
+                menu.spawn((
+                    ButtonBundle {
+                        style: Style {
+                            padding: UiRect::axes(Val::Px(8.0), Val::Px(4.0)),
+                            ..default()
+                        },
+                        background_color: BackgroundColor(Color::srgb(0.25, 0.25, 0.25)),
+                        ..default()
+                    },
+                    ToyButton(kind),
+                ))
+                .with_children(|button| {
+                    button.spawn(TextBundle::from_section(config.language.translate(kind.label()), text_style(16.0)));
+                });
+            }
+        });
+}
+
+/// Drops a toy in above the cat, or puts it away if it is already out.
+#[allow(clippy::too_many_arguments)]
+pub fn toy_button_system(
+    mut commands: Commands,
+    buttons: Query<(&Interaction, &ToyButton), Changed<Interaction>>,
+    toys: Query<(Entity, &Toy)>,
+    cats: Query<&Transform, With<AnimatedCat>>,
+    asset_server: Res<AssetServer>,
+    active: Res<ActiveBackground>,
+    scale: Res<BackgroundScale>,
+    mut play: ResMut<ToyPlay>,
+    mut rng: ResMut<GameRng>,
+) {
+    for (interaction, button) in &buttons {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        if let Some((entity, _)) = toys.iter().find(|(_, toy)| toy.kind == button.0) {
+            commands.entity(entity).despawn_recursive();
+            if play.in_box == Some(entity) {
+                play.in_box = None;
+                play.busy = 0.0;
+            }
+            continue;
+        }
+
+        let rng = rng.stream(RngStream::Ai);
+        let cat_x = cats.get_single().map_or(0.0, |transform| transform.translation.x);
+        let mut toy = Toy::new(button.0);
+        toy.velocity = Vec2::new(rng.gen_range(-150.0..=150.0), 0.0);
+        commands.spawn((
+            SpriteBundle {
+                texture: asset_server.load(button.0.image()),
+                transform: Transform::from_xyz(
+                    cat_x + rng.gen_range(-200.0..=200.0),
+                    active.floor_y(&scale) + SPAWN_HEIGHT,
+                    TOY_Z,
+                ),
+                sprite: Sprite {
+                    custom_size: Some(button.0.size()),
+                    ..default()
+                },
+                ..default()
+            },
+            toy,
+            StateScoped(InGame),
+        ));
+    }
+}
+
+/// Toys are picked up with the pet action, the left mouse button by default,
+/// and thrown by letting go while moving.
+#[allow(clippy::too_many_arguments)]
+pub fn grab_toy_system(
+    time: Res<Time>,
+    actions: Res<ActionState>,
+    windows: Query<&Window>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    active: Res<ActiveBackground>,
+    scale: Res<BackgroundScale>,
+    play: Res<ToyPlay>,
+    mut toys: Query<(Entity, &mut Toy, &mut Transform)>,
+    mut recognizer: ResMut<GestureRecognizer>,
+) {
+    let (camera, camera_transform) = cameras.single();
+    let cursor = cursor_world_position(windows.single(), camera, camera_transform);
+
+    if actions.just_pressed(Action::Pet)
+        && let Some(cursor) = cursor
+    {
+        // The frontmost toy under the cursor, skipping the box the cat is in
+        let grabbed = toys
+            .iter_mut()
+            .filter(|(entity, toy, transform)| {
+                play.in_box != Some(*entity)
+                    && Rect::from_center_size(transform.translation.xy(), toy.kind.size()).contains(cursor)
+            })
+            .max_by(|(_, _, a), (_, _, b)| a.translation.z.total_cmp(&b.translation.z));
+        if let Some((_, mut toy, transform)) = grabbed {
+            toy.held = Some(transform.translation.xy() - cursor);
+            toy.velocity = Vec2::ZERO;
+            // Carrying and throwing the toy isn't a tap or stroke on the cat
+            recognizer.claim_press();
+        }
+    }
+
+    let visible = visible_world_rect(camera, camera_transform);
+    let seconds = time.delta_seconds();
+    for (_, mut toy, mut transform) in &mut toys {
+        let Some(offset) = toy.held else {
+            continue;
+        };
+        toy.attention = ATTENTION_SECONDS;
+        if !actions.pressed(Action::Pet) {
+            toy.held = None;
+            toy.velocity = toy.velocity.clamp_length_max(MAX_THROW_SPEED);
+            continue;
+        }
+        let (Some(cursor), Some(visible)) = (cursor, visible) else {
+            continue;
+        };
+        let bounds = toy_bounds(visible, toy.kind, &active, &scale);
+        let old = transform.translation.xy();
+        let new = (cursor + offset).clamp(bounds.min, bounds.max);
+        transform.translation = new.extend(transform.translation.z);
+        if seconds > 0.0 {
+            let blend = (CARRY_SMOOTHING * seconds).min(1.0);
+            toy.velocity = toy.velocity.lerp((new - old) / seconds, blend);
+        }
+    }
+}
+
+/// Moves the loose toys, rolls the yarn ball and lets the cat's interest fade.
+pub fn move_toys_system(
+    time: Res<Time>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    active: Res<ActiveBackground>,
+    scale: Res<BackgroundScale>,
+    play: Res<ToyPlay>,
+    mut toys: Query<(Entity, &mut Toy, &mut Transform)>,
+) {
+    let (camera, camera_transform) = cameras.single();
+    let Some(visible) = visible_world_rect(camera, camera_transform) else {
+        return;
+    };
+    let seconds = time.delta_seconds();
+    for (entity, mut toy, mut transform) in &mut toys {
+        toy.attention = (toy.attention - seconds).max(0.0);
+        if toy.held.is_some() || play.in_box == Some(entity) {
+            continue;
+        }
+        let bounds = toy_bounds(visible, toy.kind, &active, &scale);
+        let mut position = transform.translation.xy();
+        toy.step(&mut position, bounds, seconds);
+        transform.translation = position.extend(transform.translation.z);
+        if toy.kind.physics().rolls {
+            let radius = toy.kind.size().x / 2.0;
+            transform.rotate_z(-toy.velocity.x * seconds / radius);
+        }
+    }
+}
+
+/// Sends the cat after the nearest toy it is still interested in. It bats the
+/// yarn ball away, pounces on the feather and climbs into the box.
+#[allow(clippy::type_complexity)]
+pub fn play_with_toys_system(
+    time: Res<Time>,
+    animation_library: Res<AnimationLibrary>,
+    resting: Res<RestingAnimation>,
+    mut play: ResMut<ToyPlay>,
+    mut toys: Query<(Entity, &mut Toy, &Transform), Without<AnimatedCat>>,
+    mut cats: Query<(&mut Transform, &mut Sprite, &mut AnimationState, &CatBody), With<AnimatedCat>>,
+) {
+    let Ok((mut cat, mut sprite, mut state, body)) = cats.get_single_mut() else {
+        return;
+    };
+    if !body.is_grounded() {
+        play.chasing = false;
+        play.in_box = None;
+        play.busy = 0.0;
+        return;
+    }
+    let delta = time.delta_seconds();
+    if play.busy > 0.0 {
+        play.busy -= delta;
+        if play.busy <= 0.0 {
+            play.in_box = None;
+        }
+        return;
+    }
+
+    let target = toys
+        .iter_mut()
+        .filter(|(_, toy, _)| toy.is_active())
+        .min_by(|(_, _, a), (_, _, b)| {
+            (a.translation.x - cat.translation.x).abs().total_cmp(&(b.translation.x - cat.translation.x).abs())
+        });
+    let Some((entity, mut toy, toy_transform)) = target else {
+        if play.chasing {
+            play.chasing = false;
+            sprite.flip_x = false;
+            play_if_different(&mut state, &resting.0, &animation_library);
+        }
+        return;
+    };
+    play.chasing = true;
+
+    let dx = toy_transform.translation.x - cat.translation.x;
+    let dy = toy_transform.translation.y - cat.translation.y;
+    let chase = ChaseMove::choose(dx, dy);
+    if dx.abs() > 1.0 {
+        // The sprite sheet faces right
+        sprite.flip_x = dx < 0.0;
+    }
+    match chase {
+        ChaseMove::Run | ChaseMove::Walk => {
+            if let Some(animation) = chase.animation() {
+                play_if_different(&mut state, animation, &animation_library);
+            }
+            let step = (chase.speed() * delta).min(dx.abs());
+            cat.translation.x += step * dx.signum();
+        }
+        ChaseMove::Wait => play_if_different(&mut state, &resting.0, &animation_library),
+        ChaseMove::Pounce => match toy.kind {
+            ToyKind::Yarn => {
+                if toy.held.is_none() {
+                    toy.velocity = Vec2::new(BAT_IMPULSE.x * dx.signum(), BAT_IMPULSE.y);
+                }
+                state.play_animation_then_return("play", &resting.0, BAT_SECONDS, &animation_library);
+                play.busy = BAT_SECONDS;
+            }
+            ToyKind::Feather => {
+                if toy.held.is_none() {
+                    toy.velocity = Vec2::new(FEATHER_FLICK.x * dx.signum(), FEATHER_FLICK.y);
+                }
+                state.play_animation_then_return("jump", &resting.0, POUNCE_SECONDS, &animation_library);
+                play.busy = POUNCE_SECONDS;
+            }
+            ToyKind::Box if toy.held.is_some() => {
+                play_if_different(&mut state, &resting.0, &animation_library);
+            }
+            ToyKind::Box => {
+                // The `box_play` frames draw the box around the cat
+                cat.translation.x = toy_transform.translation.x;
+                sprite.flip_x = false;
+                toy.attention = 0.0;
+                toy.velocity = Vec2::ZERO;
+                state.play_animation_then_return("box_play", &resting.0, BOX_SECONDS, &animation_library);
+                play.busy = BOX_SECONDS;
+                play.in_box = Some(entity);
+            }
+        },
+    }
+}
+
+/// Gets the cat out of the box when a mini-game starts.
+fn stop_playing(mut play: ResMut<ToyPlay>, mut cats: Query<&mut Sprite, With<AnimatedCat>>) {
+    *play = ToyPlay::default();
+    for mut sprite in &mut cats {
+        sprite.flip_x = false;
+    }
+}
+
+/// Hides the toys and their menu during mini-games, and the box while the cat
+/// is in it. Buttons of toys that are out are highlighted.
+pub fn toy_visibility_system(
+    minigame: Res<State<Minigame>>,
+    play: Res<ToyPlay>,
+    mut toys: Query<(Entity, &Toy, &mut Visibility), Without<ToyMenu>>,
+    mut menus: Query<&mut Visibility, With<ToyMenu>>,
+    mut buttons: Query<(&ToyButton, &mut BackgroundColor)>,
+) {
+    let playing = *minigame.get() == Minigame::None;
+    let shown = |show: bool| if show { Visibility::Inherited } else { Visibility::Hidden };
+    for (entity, _, mut visibility) in &mut toys {
+        visibility.set_if_neq(shown(playing && play.in_box != Some(entity)));
+    }
+    for mut visibility in &mut menus {
+        visibility.set_if_neq(shown(playing));
+    }
+    for (button, mut color) in &mut buttons {
+        let out = toys.iter().any(|(_, toy, _)| toy.kind == button.0);
+        let wanted = if out { Color::srgb(0.35, 0.55, 0.35) } else { Color::srgb(0.25, 0.25, 0.25) };
+        color.set_if_neq(BackgroundColor(wanted));
+    }
+}
@@ -0,0 +1,258 @@
+use bevy::asset::LoadState;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::background::{
+    ActiveBackground, BACKGROUND_Z, Background, BackgroundOpacity, BackgroundScale, BackgroundSet, LAYER_SIZE,
+    spawn_background_layers,
+};
+use crate::profile::ProfileSettings;
+use crate::settings::UserConfig;
+
+const TRANSITION_SECONDS: f32 = 1.0;
+// In front of the outgoing background but still behind the black overlay and the cat
+const INCOMING_Z: f32 = -0.9;
+const OVERLAY_Z: f32 = -0.5;
+
+/// How one background gives way to the next.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TransitionStyle {
+    #[default]
+    Crossfade,
+    Slide,
+    FadeThroughBlack,
+}
+
+/// Ask for the background to change. The change starts once the new layers
+/// have loaded; if several arrive during a transition only the latest is kept.
+#[derive(Event)]
+pub struct ChangeBackground(pub BackgroundSet);
+
+/// How both backgrounds should look at one point of a transition.
+#[derive(Debug, PartialEq)]
+pub struct TransitionFrame {
+    pub outgoing_opacity: f32,
+    pub incoming_opacity: f32,
+    pub outgoing_x: f32,
+    pub incoming_x: f32,
+    pub black: f32,
+}
+
+impl TransitionStyle {
+    /// Describes the transition at `progress` (0 = start, 1 = done).
+    pub fn frame(self, progress: f32) -> TransitionFrame {
+        let t = progress.clamp(0.0, 1.0);
+        let eased = t * t * (3.0 - 2.0 * t);
+        match self {
+            TransitionStyle::Crossfade => TransitionFrame {
+                outgoing_opacity: 1.0,
+                incoming_opacity: eased,
+                outgoing_x: 0.0,
+                incoming_x: 0.0,
+                black: 0.0,
+            },
+            TransitionStyle::Slide => TransitionFrame {
+                outgoing_opacity: 1.0,
+                incoming_opacity: 1.0,
+                outgoing_x: -LAYER_SIZE.x * eased,
+                incoming_x: LAYER_SIZE.x * (1.0 - eased),
+                black: 0.0,
+            },
+            TransitionStyle::FadeThroughBlack => {
+                let first_half = t < 0.5;
+                TransitionFrame {
+                    outgoing_opacity: if first_half { 1.0 } else { 0.0 },
+                    incoming_opacity: if first_half { 0.0 } else { 1.0 },
+                    outgoing_x: 0.0,
+                    incoming_x: 0.0,
+                    black: if first_half { t * 2.0 } else { 2.0 - t * 2.0 },
+                }
+            }
+        }
+    }
+}
+
+struct RunningTransition {
+    background: BackgroundSet,
+    style: TransitionStyle,
+    incoming: Entity,
+    handles: Vec<Handle<Image>>,
+    overlay: Option<Entity>,
+    // None while the new layers are still loading
+    elapsed: Option<f32>,
+}
+
+#[derive(Resource, Default)]
+pub struct BackgroundTransition {
+    queued: Option<BackgroundSet>,
+    running: Option<RunningTransition>,
+}
+
+#[derive(Component)]
+pub struct IncomingBackground;
+
+#[derive(Component)]
+pub struct TransitionOverlay;
+
+pub struct TransitionPlugin;
+
+impl Plugin for TransitionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ChangeBackground>()
+            .insert_resource(BackgroundTransition::default())
+            .add_systems(Update, (
+                queue_background_change_system,
+                start_background_transition_system,
+                advance_background_transition_system,
+            ).chain());
+    }
+}
+
+pub fn queue_background_change_system(
+    mut events: EventReader<ChangeBackground>,
+    mut transition: ResMut<BackgroundTransition>,
+) {
+    if let Some(ChangeBackground(background)) = events.read().last() {
+        transition.queued = Some(background.clone());
+    }
+}
+
+pub fn start_background_transition_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<ProfileSettings>,
+    config: Res<UserConfig>,
+    mut transition: ResMut<BackgroundTransition>,
+) {
+    if transition.running.is_some() {
+        return;
+    }
+    let Some(background) = transition.queued.take() else {
+        return;
+    };
+
+    // Build the new background hidden until every layer has loaded
+    let incoming = commands
+        .spawn((
+            SpatialBundle {
+                transform: Transform::from_xyz(0.0, 0.0, INCOMING_Z),
+                visibility: Visibility::Hidden,
+                ..default()
+            },
+            IncomingBackground,
+            BackgroundOpacity(0.0),
+        ))
+        .id();
+    let handles = spawn_background_layers(&mut commands, incoming, &background, &asset_server);
+
+    transition.running = Some(RunningTransition {
+        background,
+        // Sliding moves the whole screen, so fall back to a crossfade
+        style: match settings.background_transition {
+            TransitionStyle::Slide if config.reduce_motion => TransitionStyle::Crossfade,
+            style => style,
+        },
+        incoming,
+        handles,
+        overlay: None,
+        elapsed: None,
+    });
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn advance_background_transition_system(
+    mut commands: Commands,
+    time: Res<Time>,
+    asset_server: Res<AssetServer>,
+    scale: Res<BackgroundScale>,
+    mut transition: ResMut<BackgroundTransition>,
+    mut active: ResMut<ActiveBackground>,
+    mut outgoing: Query<
+        (Entity, &mut Transform, &mut BackgroundOpacity),
+        (With<Background>, Without<IncomingBackground>),
+    >,
+    mut incoming: Query<
+        (&mut Transform, &mut BackgroundOpacity, &mut Visibility),
+        (With<IncomingBackground>, Without<Background>),
+    >,
+    mut overlays: Query<&mut Sprite, With<TransitionOverlay>>,
+) {
+    let Some(running) = transition.running.as_mut() else {
+        return;
+    };
+
+    let elapsed = match running.elapsed {
+        Some(elapsed) => elapsed + time.delta_seconds(),
+        None => {
+            // Failed loads count as finished so a bad file can't stall the rotation
+            let ready = running.handles.iter().all(|handle| {
+                matches!(asset_server.load_state(handle), LoadState::Loaded | LoadState::Failed(_))
+            });
+            if !ready {
+                return;
+            }
+            if let Ok((_, _, mut visibility)) = incoming.get_mut(running.incoming) {
+                *visibility = Visibility::Inherited;
+            }
+            if running.style == TransitionStyle::FadeThroughBlack {
+                running.overlay = Some(
+                    commands
+                        .spawn((
+                            SpriteBundle {
+                                sprite: Sprite {
+                                    color: Color::BLACK.with_alpha(0.0),
+                                    custom_size: Some(LAYER_SIZE * 2.0 * scale.0),
+                                    ..default()
+                                },
+                                transform: Transform::from_xyz(0.0, 0.0, OVERLAY_Z),
+                                ..default()
+                            },
+                            TransitionOverlay,
+                        ))
+                        .id(),
+                );
+            }
+            0.0
+        }
+    };
+    running.elapsed = Some(elapsed);
+
+    let progress = elapsed / TRANSITION_SECONDS;
+    let frame = running.style.frame(progress);
+    // Slides are described for an unscaled background
+    for (_, mut transform, mut opacity) in &mut outgoing {
+        transform.translation.x = frame.outgoing_x * scale.0;
+        opacity.0 = frame.outgoing_opacity;
+    }
+    if let Ok((mut transform, mut opacity, _)) = incoming.get_mut(running.incoming) {
+        transform.translation.x = frame.incoming_x * scale.0;
+        opacity.0 = frame.incoming_opacity;
+    }
+    for mut sprite in &mut overlays {
+        sprite.color.set_alpha(frame.black);
+    }
+
+    if progress < 1.0 {
+        return;
+    }
+
+    // Done: the incoming background takes over
+    for (entity, _, _) in &outgoing {
+        commands.entity(entity).despawn_recursive();
+    }
+    if let Some(overlay) = running.overlay {
+        commands.entity(overlay).despawn_recursive();
+    }
+    if let Ok((mut transform, mut opacity, _)) = incoming.get_mut(running.incoming) {
+        transform.translation = Vec3::new(0.0, 0.0, BACKGROUND_Z);
+        opacity.0 = 1.0;
+    }
+    commands
+        .entity(running.incoming)
+        .remove::<IncomingBackground>()
+        .insert(Background);
+
+    if let Some(finished) = transition.running.take() {
+        active.0 = Some(finished.background);
+    }
+}